@@ -0,0 +1,37 @@
+//! Benchmarks of YAML helper functions.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pipeline::yutil::get_value_by_path;
+use serde_yaml::Value;
+
+fn bench_get_value_by_path(c: &mut Criterion) {
+    let yaml: Value = serde_yaml::from_str(
+        r#"
+        stages:
+            build:
+                image: "alpine"
+                steps:
+                    compile:
+                        command: "cargo build"
+                        environment:
+                            profile:
+                                name: "release"
+        "#,
+    )
+    .unwrap();
+
+    c.bench_function("get_value_by_path shallow", |b| {
+        b.iter(|| get_value_by_path(black_box(&yaml), black_box("stages")))
+    });
+    c.bench_function("get_value_by_path deep", |b| {
+        b.iter(|| {
+            get_value_by_path(
+                black_box(&yaml),
+                black_box("stages.build.steps.compile.environment.profile.name"),
+            )
+        })
+    });
+}
+
+criterion_group!(benches, bench_get_value_by_path);
+criterion_main!(benches);
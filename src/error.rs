@@ -1,17 +1,29 @@
 //! Error utilities.
 
-use log::{debug, error};
+use log::{debug, error, warn};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 
 /// Shortcut result type for convenience.
 pub type Result<T> = std::result::Result<T, Pipeline>;
 
+/// Severity of a pipeline error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// A problem which does not prevent the pipeline from running.
+    Warning,
+    /// A regular error.
+    Error,
+    /// An error after which the pipeline must be aborted.
+    Fatal,
+}
+
 /// Represents a pipeline error.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Pipeline {
     error_string: String,
     debug_string: Option<String>,
+    severity: Severity,
 }
 
 impl Pipeline {
@@ -21,6 +33,7 @@ impl Pipeline {
         Self {
             error_string: error_string.to_string(),
             debug_string: None,
+            severity: Severity::Error,
         }
     }
 
@@ -30,22 +43,164 @@ impl Pipeline {
         Self {
             error_string: error_string.to_string(),
             debug_string: Some(debug_string.to_string()),
+            severity: Severity::Error,
         }
     }
 
+    /// Change severity of the error. Errors are constructed with [`Severity::Error`] by default.
+    #[must_use]
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Prefix the error message with context, e.g. the operation which failed, separated with a
+    /// colon: context `Loading ci.yaml` turns `error` into `Loading ci.yaml: error`. Debug string
+    /// and severity are kept.
+    #[must_use]
+    pub fn context(mut self, ctx: &str) -> Self {
+        self.error_string = format!("{}: {}", ctx, self.error_string);
+        self
+    }
+
+    /// Obtain a copy of the error with context prefixed to its message, as [`Pipeline::context`]
+    /// does, leaving the error itself intact.
+    #[must_use]
+    pub fn with_context(&self, ctx: &str) -> Self {
+        self.clone().context(ctx)
+    }
+
+    /// Obtain the primary error message, i.e. the text shown by [`Display`].
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.error_string
+    }
+
+    /// Obtain the extra debug string, if any.
+    #[must_use]
+    pub fn debug_detail(&self) -> Option<&str> {
+        self.debug_string.as_deref()
+    }
+
+    /// Obtain severity of the error.
+    #[must_use]
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Check whether the error has [`Severity::Fatal`] severity.
+    #[must_use]
+    pub fn is_fatal(&self) -> bool {
+        self.severity == Severity::Fatal
+    }
+
+    /// Check whether the error has [`Severity::Warning`] severity.
+    #[must_use]
+    pub fn is_warning(&self) -> bool {
+        self.severity == Severity::Warning
+    }
+
     /// Print pipeline error internals.
+    ///
+    /// Warnings are printed at the warning level, other errors at the error level.
     pub fn print_verbose(&self) {
-        error!("{}", self.error_string);
+        if self.is_warning() {
+            warn!("{}", self.error_string);
+        } else {
+            error!("{}", self.error_string);
+        }
         if let Some(dbg_str) = self.debug_string.as_ref() {
             debug!("{}", dbg_str);
         }
     }
 }
 
+/// By default only the error string is shown. The alternate form (`{:#}`) appends the debug
+/// string, if any, on a separate line, which is useful when errors are written to a sink other
+/// than the `log` crate and [`Pipeline::print_verbose`] cannot be used.
 impl Display for Pipeline {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.error_string)
+        write!(f, "{}", self.error_string)?;
+        match (f.alternate(), self.debug_string.as_ref()) {
+            (true, Some(dbg_str)) => write!(f, "\n{}", dbg_str),
+            _ => Ok(()),
+        }
     }
 }
 
 impl Error for Pipeline {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    /* -------------------------- */
+    /* ---- Test definitions ---- */
+    /* -------------------------- */
+
+    #[rstest]
+    fn new_constructs_error_with_error_severity() {
+        assert_eq!(Severity::Error, Pipeline::new("error").severity());
+        assert_eq!(
+            Severity::Error,
+            Pipeline::new_debug("error", "debug").severity()
+        );
+    }
+
+    #[rstest]
+    fn accessors_expose_message_and_debug_detail() {
+        let err = Pipeline::new("error");
+        assert_eq!("error", err.message());
+        assert_eq!(None, err.debug_detail());
+
+        let err = Pipeline::new_debug("error", "debug");
+        assert_eq!("error", err.message());
+        assert_eq!(Some("debug"), err.debug_detail());
+    }
+
+    #[rstest]
+    #[case(Severity::Warning, false, true)]
+    #[case(Severity::Error, false, false)]
+    #[case(Severity::Fatal, true, false)]
+    fn severity_accessors_reflect_severity(
+        #[case] severity: Severity,
+        #[case] fatal: bool,
+        #[case] warning: bool,
+    ) {
+        let err = Pipeline::new("error").with_severity(severity);
+        assert_eq!(severity, err.severity());
+        assert_eq!(fatal, err.is_fatal());
+        assert_eq!(warning, err.is_warning());
+    }
+
+    #[rstest]
+    fn context_prefixes_message_and_keeps_details() {
+        let err = Pipeline::new_debug("error", "debug")
+            .with_severity(Severity::Fatal)
+            .context("Could not load `ci.yaml`");
+        assert_eq!("Could not load `ci.yaml`: error", err.message());
+        assert_eq!(Some("debug"), err.debug_detail());
+        assert_eq!(Severity::Fatal, err.severity());
+    }
+
+    #[rstest]
+    fn with_context_leaves_original_intact() {
+        let err = Pipeline::new("error");
+        let annotated = err.with_context("outer").with_context("outermost");
+        assert_eq!("outermost: outer: error", annotated.message());
+        assert_eq!("error", err.message());
+    }
+
+    #[rstest]
+    #[case(Pipeline::new("error"), "error", "error")]
+    #[case(Pipeline::new_debug("error", "debug"), "error", "error\ndebug")]
+    fn display_appends_debug_string_in_alternate_form(
+        #[case] err: Pipeline,
+        #[case] plain: &str,
+        #[case] alternate: &str,
+    ) {
+        assert_eq!(plain, format!("{}", err));
+        assert_eq!(alternate, format!("{:#}", err));
+    }
+}
@@ -0,0 +1,231 @@
+//! JUnit XML reports.
+//!
+//! A run report is rendered in the JUnit format understood by most CI systems: every stage
+//! becomes a test suite and every step becomes a test case. Failed steps carry their error
+//! message and captured standard error, steps which were not executed are marked as skipped.
+//! Other captured output of executed steps is rendered as `system-out` and `system-err` elements.
+//! Hooks of the run are not rendered.
+
+use crate::runner::{RunReport, StageOutcome, Status, StepOutcome};
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Render a run report as a JUnit XML document.
+#[must_use]
+pub fn to_junit_xml(report: &RunReport) -> String {
+    let steps = || report.stages.iter().flat_map(|s| &s.steps);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"pipeline\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">",
+        steps().count(),
+        steps().filter(|s| s.status == Status::Failed).count(),
+        steps().filter(|s| s.status == Status::Skipped).count(),
+        seconds(report.stages.iter().map(|s| s.duration).sum()),
+    );
+    for stage in &report.stages {
+        write_suite(&mut xml, stage);
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+// Render a stage as a test suite
+fn write_suite(xml: &mut String, stage: &StageOutcome) {
+    let count = |status| stage.steps.iter().filter(|s| s.status == status).count();
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">",
+        escape(&stage.name),
+        stage.steps.len(),
+        count(Status::Failed),
+        count(Status::Skipped),
+        seconds(stage.duration),
+    );
+    for step in &stage.steps {
+        write_case(xml, &stage.name, step);
+    }
+    xml.push_str("  </testsuite>\n");
+}
+
+// Render a step as a test case
+fn write_case(xml: &mut String, stage_name: &str, step: &StepOutcome) {
+    let _ = write!(
+        xml,
+        "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+        escape(&step.label()),
+        escape(stage_name),
+        seconds(step.duration),
+    );
+
+    let mut children = String::new();
+    match step.status {
+        Status::Succeeded => {}
+        Status::Skipped => children.push_str("      <skipped/>\n"),
+        Status::Failed => {
+            let _ = writeln!(
+                children,
+                "      <failure message=\"{}\">{}</failure>",
+                escape(step.error.as_deref().unwrap_or_default()),
+                escape(step.stderr.as_deref().unwrap_or_default()),
+            );
+        }
+    }
+    // Standard error of failed steps is already rendered as the failure content
+    let stderr = step
+        .stderr
+        .as_ref()
+        .filter(|_| step.status != Status::Failed);
+    for (element, output) in [("system-out", step.stdout.as_ref()), ("system-err", stderr)] {
+        if let Some(output) = output.filter(|o| !o.is_empty()) {
+            let _ = writeln!(children, "      <{0}>{1}</{0}>", element, escape(output));
+        }
+    }
+
+    if children.is_empty() {
+        xml.push_str("/>\n");
+    } else {
+        let _ = write!(xml, ">\n{}    </testcase>\n", children);
+    }
+}
+
+// Format a duration as seconds with millisecond precision
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+// Escape text for use in XML attributes and content
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than whitespace are not allowed in XML 1.0
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    /* ------------------ */
+    /* ---- Fixtures ---- */
+    /* ------------------ */
+
+    fn step(name: Option<&str>, status: Status, millis: u64) -> StepOutcome {
+        StepOutcome {
+            name: name.map(str::to_string),
+            run: "make <all>".to_string(),
+            status,
+            exit_code: None,
+            error: (status == Status::Failed)
+                .then(|| "Command exited with code 2, expected 0".to_string()),
+            stdout: None,
+            stderr: (status == Status::Failed).then(|| "error: \"missing\" & more".to_string()),
+            duration: Duration::from_millis(millis),
+        }
+    }
+
+    #[fixture]
+    fn report() -> RunReport {
+        RunReport {
+            stages: vec![
+                StageOutcome {
+                    name: "build".to_string(),
+                    status: Status::Failed,
+                    steps: vec![
+                        step(Some("compile"), Status::Succeeded, 1500),
+                        step(None, Status::Failed, 250),
+                        step(Some("docs"), Status::Skipped, 0),
+                    ],
+                    duration: Duration::from_millis(1750),
+                },
+                StageOutcome {
+                    name: "test".to_string(),
+                    status: Status::Skipped,
+                    steps: vec![step(Some("unit"), Status::Skipped, 0)],
+                    duration: Duration::ZERO,
+                },
+            ],
+            hooks: vec![],
+        }
+    }
+
+    /* -------------------------- */
+    /* ---- Test definitions ---- */
+    /* -------------------------- */
+
+    #[rstest]
+    fn to_junit_xml_renders_stages_as_suites(report: RunReport) {
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="pipeline" tests="4" failures="1" skipped="2" time="1.750">
+  <testsuite name="build" tests="3" failures="1" skipped="1" time="1.750">
+    <testcase name="compile" classname="build" time="1.500"/>
+    <testcase name="make &lt;all&gt;" classname="build" time="0.250">
+      <failure message="Command exited with code 2, expected 0">error: &quot;missing&quot; &amp; more</failure>
+    </testcase>
+    <testcase name="docs" classname="build" time="0.000">
+      <skipped/>
+    </testcase>
+  </testsuite>
+  <testsuite name="test" tests="1" failures="0" skipped="1" time="0.000">
+    <testcase name="unit" classname="test" time="0.000">
+      <skipped/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#;
+        assert_eq!(expected, to_junit_xml(&report));
+    }
+
+    #[rstest]
+    fn to_junit_xml_renders_captured_output() {
+        let mut report = report();
+        report.stages[0].steps[0].stdout = Some("built <ok>".to_string());
+        report.stages[0].steps[0].stderr = Some("warning: unused".to_string());
+        report.stages[0].steps[1].stdout = Some("partial".to_string());
+        let xml = to_junit_xml(&report);
+
+        assert!(xml.contains(
+            r#"    <testcase name="compile" classname="build" time="1.500">
+      <system-out>built &lt;ok&gt;</system-out>
+      <system-err>warning: unused</system-err>
+    </testcase>
+"#
+        ));
+        assert!(xml.contains(
+            r#"      <failure message="Command exited with code 2, expected 0">error: &quot;missing&quot; &amp; more</failure>
+      <system-out>partial</system-out>
+    </testcase>
+"#
+        ));
+    }
+
+    #[rstest]
+    fn to_junit_xml_renders_empty_report() {
+        let xml = to_junit_xml(&RunReport {
+            stages: vec![],
+            hooks: vec![],
+        });
+        assert!(xml.contains(
+            r#"<testsuites name="pipeline" tests="0" failures="0" skipped="0" time="0.000">"#
+        ));
+    }
+
+    #[rstest]
+    #[case("a\u{1b}[31mb", "a[31mb")]
+    #[case("line\nline", "line\nline")]
+    #[case("'x'", "&apos;x&apos;")]
+    fn escape_produces_valid_xml_text(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(expected, escape(input));
+    }
+}
@@ -0,0 +1,13 @@
+//! Utilities for handling test pipelines.
+
+#![deny(warnings)]
+#![deny(missing_docs, rustdoc::missing_crate_level_docs)]
+
+pub mod error;
+pub mod junit;
+pub mod loader;
+pub mod pipeline;
+pub mod runner;
+pub mod span;
+pub mod subst;
+pub mod yutil;
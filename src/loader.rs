@@ -0,0 +1,531 @@
+//! Loaders for pipeline files.
+//!
+//! Every loader converts its input into a [`serde_yaml::Value`] so that the [`crate::yutil`] API
+//! works uniformly regardless of the input format.
+
+use crate::error::Pipeline;
+use crate::error::Result;
+use crate::span::snippet;
+#[cfg(feature = "toml")]
+use serde_yaml::Mapping;
+use serde_yaml::Value;
+use std::io::Read;
+use std::path::Path;
+
+/// Load a YAML document.
+///
+/// # Errors
+/// The function returns an error if the reader fails or the input is not a valid YAML document.
+pub fn load_yaml<R: Read>(reader: R) -> Result<Value> {
+    serde_yaml::from_reader(reader)
+        .map_err(|e| Pipeline::new_debug("Could not parse YAML input", &e.to_string()))
+}
+
+/// Load a YAML document from text, pointing at the offending location in error messages.
+///
+/// Unlike [`load_yaml`], the error message of a malformed document is followed by a snippet of
+/// the text with a caret under the offending column, see [`snippet`], when the parser reports
+/// a location.
+///
+/// # Errors
+/// The function returns an error if the input is not a valid YAML document.
+pub fn load_yaml_str(text: &str) -> Result<Value> {
+    serde_yaml::from_str(text).map_err(|e| match e.location() {
+        Some(location) => Pipeline::new(&format!(
+            "Could not parse YAML input: {}\n{}",
+            e,
+            snippet(text, location.line(), location.column())
+        )),
+        None => Pipeline::new_debug("Could not parse YAML input", &e.to_string()),
+    })
+}
+
+/// Load a JSON document.
+///
+/// JSON objects become mappings (preserving the order of keys), arrays become sequences and
+/// remaining values become corresponding YAML scalars.
+///
+/// # Errors
+/// The function returns an error if the reader fails or the input is not a valid JSON document.
+#[cfg(feature = "json")]
+pub fn load_json<R: Read>(reader: R) -> Result<Value> {
+    serde_json::from_reader(reader)
+        .map_err(|e| Pipeline::new_debug("Could not parse JSON input", &e.to_string()))
+}
+
+/// Load a TOML document.
+///
+/// The document is converted into a YAML value as follows:
+///  - tables (including inline tables and arrays of tables) become mappings, preserving the
+///    order of keys,
+///  - arrays become sequences,
+///  - strings, integers, floats and booleans become corresponding YAML scalars,
+///  - datetimes become strings formatted according to RFC 3339 (e.g. `1979-05-27T07:32:00Z`),
+///    local dates and times keep their TOML form (e.g. `1979-05-27` or `07:32:00`).
+///
+/// # Errors
+/// The function returns an error if the reader fails or the input is not a valid TOML document.
+#[cfg(feature = "toml")]
+pub fn load_toml<R: Read>(mut reader: R) -> Result<Value> {
+    let mut text = String::new();
+    reader
+        .read_to_string(&mut text)
+        .map_err(|e| Pipeline::new_debug("Could not read TOML input", &e.to_string()))?;
+    let toml_value: toml::Value = toml::from_str(&text)
+        .map_err(|e| Pipeline::new_debug("Could not parse TOML input", &e.to_string()))?;
+    Ok(toml_to_yaml(toml_value))
+}
+
+// Convert a TOML value into its YAML counterpart
+#[cfg(feature = "toml")]
+fn toml_to_yaml(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::Number(i.into()),
+        toml::Value::Float(f) => Value::Number(f.into()),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(arr) => Value::Sequence(arr.into_iter().map(toml_to_yaml).collect()),
+        toml::Value::Table(table) => Value::Mapping(
+            table
+                .into_iter()
+                .map(|(k, v)| (Value::String(k), toml_to_yaml(v)))
+                .collect::<Mapping>(),
+        ),
+    }
+}
+
+/// Path meaning the standard input when passed to [`load_auto`].
+pub const STDIN_PATH: &str = "-";
+
+/// Load a document of unknown format from a reader.
+///
+/// The content is sniffed as in [`load_auto`], except that content which does not look like
+/// JSON is loaded as YAML. The source describes the reader in error messages, e.g. `Standard
+/// input`.
+///
+/// # Errors
+/// The function returns an error if the reader fails or the input is not UTF-8 text, or the
+/// content is not a valid document.
+pub fn load_sniffed<R: Read>(mut reader: R, source: &str) -> Result<Value> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).map_err(|e| {
+        Pipeline::new_debug(
+            &format!("Could not read {}", source.to_lowercase()),
+            &e.to_string(),
+        )
+    })?;
+    let text = decode_text(bytes, source)?;
+    if looks_like_json(&text) {
+        load_json_or_yaml(text.as_bytes())
+    } else {
+        load_yaml_str(&text)
+    }
+}
+
+/// Load a document selecting the format automatically.
+///
+/// The format is selected based on the file extension:
+///  - `yaml` and `yml` files are loaded as YAML,
+///  - `json` files are loaded as JSON (requires the `json` feature),
+///  - `toml` files are loaded as TOML (requires the `toml` feature).
+///
+/// The standard input is read if the path is [`STDIN_PATH`], see [`load_sniffed`].
+///
+/// The file must be UTF-8 encoded; a leading byte order mark, added by some editors, is skipped.
+///
+/// Files with any other extension are sniffed: if the first non-whitespace character is `{` or
+/// `[` the content is loaded as JSON. Since JSON is a subset of YAML, the YAML loader is used for
+/// such content when the `json` feature is disabled.
+///
+/// # Errors
+/// The function returns an error if the file cannot be read or is not UTF-8 text, the format
+/// cannot be determined or is not supported, or the content is not a valid document of the
+/// selected format.
+pub fn load_auto(path: &Path) -> Result<Value> {
+    if path == Path::new(STDIN_PATH) {
+        return load_sniffed(std::io::stdin().lock(), "Standard input");
+    }
+
+    let bytes = std::fs::read(path).map_err(|e| {
+        Pipeline::new_debug(
+            &format!("Could not read file `{}`", path.display()),
+            &e.to_string(),
+        )
+    })?;
+    let text = decode_text(bytes, &format!("File `{}`", path.display()))?;
+    let input = text.as_bytes();
+
+    let extension = path
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("yaml" | "yml") => load_yaml_str(&text),
+        Some("json") => load_json_checked(input),
+        Some("toml") => load_toml_checked(input),
+        _ if looks_like_json(&text) => load_json_or_yaml(input),
+        _ => Err(Pipeline::new(&format!(
+            "Could not detect format of `{}`, please use a .yaml, .json or .toml extension",
+            path.display()
+        ))),
+    }
+}
+
+/// Obtain names of document formats supported by [`load_auto`], depending on enabled features.
+#[must_use]
+pub fn supported_formats() -> Vec<&'static str> {
+    let mut formats = vec!["yaml"];
+    if cfg!(feature = "json") {
+        formats.push("json");
+    }
+    if cfg!(feature = "toml") {
+        formats.push("toml");
+    }
+    formats
+}
+
+// Check whether text looks like a JSON document
+fn looks_like_json(text: &str) -> bool {
+    matches!(text.trim_start().chars().next(), Some('{' | '['))
+}
+
+// Load JSON content, falling back to the YAML loader if the JSON support is not compiled in.
+// JSON is a subset of YAML, so the result is the same
+fn load_json_or_yaml(input: &[u8]) -> Result<Value> {
+    if cfg!(feature = "json") {
+        load_json_checked(input)
+    } else {
+        load_yaml(input)
+    }
+}
+
+// Decode UTF-8 text skipping a byte order mark. The source is used in error messages
+fn decode_text(mut bytes: Vec<u8>, source: &str) -> Result<String> {
+    const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+    if bytes.starts_with(b"\xFF\xFE") || bytes.starts_with(b"\xFE\xFF") {
+        return Err(Pipeline::new(&format!(
+            "{} is encoded as UTF-16, only UTF-8 is supported",
+            source
+        )));
+    }
+    if bytes.starts_with(UTF8_BOM) {
+        bytes.drain(..UTF8_BOM.len());
+    }
+    String::from_utf8(bytes).map_err(|e| {
+        Pipeline::new_debug(
+            &format!("{} is not valid UTF-8 text", source),
+            &format!(
+                "Invalid byte sequence at offset {}",
+                e.utf8_error().valid_up_to()
+            ),
+        )
+    })
+}
+
+// Load a JSON document or report that the support is not compiled in
+fn load_json_checked(input: &[u8]) -> Result<Value> {
+    #[cfg(feature = "json")]
+    return load_json(input);
+    #[cfg(not(feature = "json"))]
+    return Err(Pipeline::new_debug(
+        "JSON input is not supported",
+        &format!(
+            "Enable the `json` feature. Input size: {} bytes",
+            input.len()
+        ),
+    ));
+}
+
+// Load a TOML document or report that the support is not compiled in
+fn load_toml_checked(input: &[u8]) -> Result<Value> {
+    #[cfg(feature = "toml")]
+    return load_toml(input);
+    #[cfg(not(feature = "toml"))]
+    return Err(Pipeline::new_debug(
+        "TOML input is not supported",
+        &format!(
+            "Enable the `toml` feature. Input size: {} bytes",
+            input.len()
+        ),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yutil::get_typed_value_by_path;
+    use rstest::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /* ------------------ */
+    /* ---- Fixtures ---- */
+    /* ------------------ */
+
+    // Temporary file which is removed when dropped. Every file gets a unique name ending with the
+    // given one, as tests run in parallel
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, content: impl AsRef<[u8]>) -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "pipeline-loader-{}-{}-{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed),
+                name
+            ));
+            std::fs::write(&path, content).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /* -------------------------- */
+    /* ---- Test definitions ---- */
+    /* -------------------------- */
+
+    #[rstest]
+    fn load_yaml_converts_document_into_value() {
+        let value = load_yaml("name: John Doe\nage: 22".as_bytes()).unwrap();
+        assert_eq!(22, get_typed_value_by_path::<u64>(&value, "age").unwrap());
+    }
+
+    #[rstest]
+    fn load_yaml_returns_error_when_invalid_document_is_passed() {
+        assert!(load_yaml("key: [".as_bytes()).is_err());
+    }
+
+    #[rstest]
+    fn supported_formats_reflect_enabled_features() {
+        let formats = supported_formats();
+        assert_eq!(Some(&"yaml"), formats.first());
+        assert_eq!(cfg!(feature = "json"), formats.contains(&"json"));
+        assert_eq!(cfg!(feature = "toml"), formats.contains(&"toml"));
+    }
+
+    #[rstest]
+    #[case("bom.yaml")]
+    #[case("bom.conf")]
+    fn load_auto_skips_byte_order_mark(#[case] name: &str) {
+        let file = TempFile::new(name, b"\xEF\xBB\xBF[\"John Doe\"]");
+        let value = load_auto(&file.0).unwrap();
+        assert_eq!(Value::Sequence(vec![Value::from("John Doe")]), value);
+    }
+
+    #[rstest]
+    #[case(b"\xFF\xFEn\0", "is encoded as UTF-16, only UTF-8 is supported")]
+    #[case(b"\xFE\xFF\0n", "is encoded as UTF-16, only UTF-8 is supported")]
+    #[case(b"name: \xE9t\xE9", "is not valid UTF-8 text")]
+    fn load_auto_returns_error_when_file_is_not_utf8(
+        #[case] content: &[u8],
+        #[case] expected: &str,
+    ) {
+        let file = TempFile::new("latin1.yaml", content);
+        let err = load_auto(&file.0).unwrap_err();
+        assert!(err.to_string().ends_with(expected), "{}", err);
+        assert!(err.to_string().contains("latin1.yaml"));
+    }
+
+    #[rstest]
+    fn load_yaml_str_points_at_offending_location() {
+        let err = load_yaml_str("stages:\n  - name: [build\n    steps: []\n").unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.starts_with("Could not parse YAML input: "),
+            "{}",
+            message
+        );
+        assert!(
+            message.ends_with(
+                "  |\n1 | stages:\n2 |   - name: [build\n3 |     steps: []\n  |          ^"
+            ),
+            "{}",
+            message
+        );
+    }
+
+    #[rstest]
+    fn load_yaml_str_converts_document_into_value() {
+        assert_eq!(
+            load_yaml("a: [1]".as_bytes()).unwrap(),
+            load_yaml_str("a: [1]").unwrap()
+        );
+    }
+
+    #[rstest]
+    fn load_auto_shows_snippet_when_yaml_file_is_malformed() {
+        let file = TempFile::new("malformed.yaml", "name: [John");
+        let message = load_auto(&file.0).unwrap_err().to_string();
+        assert!(message.contains("1 | name: [John\n"), "{}", message);
+    }
+
+    #[rstest]
+    #[case("doc.yaml")]
+    #[case("doc.YML")]
+    fn load_auto_loads_yaml_by_extension(#[case] name: &str) {
+        let file = TempFile::new(name, "name: John Doe");
+        let value = load_auto(&file.0).unwrap();
+        assert_eq!(
+            "John Doe",
+            get_typed_value_by_path::<str>(&value, "name").unwrap()
+        );
+    }
+
+    #[rstest]
+    fn load_auto_sniffs_json_content_when_extension_is_unknown() {
+        let file = TempFile::new("doc.conf", "  \n{ \"name\": \"John Doe\" }");
+        let value = load_auto(&file.0).unwrap();
+        assert_eq!(
+            "John Doe",
+            get_typed_value_by_path::<str>(&value, "name").unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case("doc.conf")]
+    #[case("doc")]
+    fn load_auto_returns_error_when_format_is_ambiguous(#[case] name: &str) {
+        let file = TempFile::new(name, "name: John Doe");
+        assert!(load_auto(&file.0).is_err());
+    }
+
+    #[rstest]
+    #[case("name: John Doe")]
+    #[case("\u{feff}{ \"name\": \"John Doe\" }")]
+    fn load_sniffed_loads_yaml_and_json(#[case] text: &str) {
+        let value = load_sniffed(text.as_bytes(), "Standard input").unwrap();
+        assert_eq!(
+            "John Doe",
+            get_typed_value_by_path::<str>(&value, "name").unwrap()
+        );
+    }
+
+    #[rstest]
+    fn load_sniffed_names_source_in_errors() {
+        let err = load_sniffed(&b"\xFF\xFE"[..], "Standard input").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Standard input is encoded as UTF-16"));
+    }
+
+    #[rstest]
+    fn load_auto_returns_error_when_file_does_not_exist() {
+        assert!(load_auto(Path::new("/non/existing/file.yaml")).is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[rstest]
+    fn load_auto_loads_json_by_extension() {
+        let file = TempFile::new("doc.json", "{ \"age\": 22 }");
+        let value = load_auto(&file.0).unwrap();
+        assert_eq!(22, get_typed_value_by_path::<u64>(&value, "age").unwrap());
+    }
+
+    #[cfg(feature = "toml")]
+    #[rstest]
+    fn load_auto_loads_toml_by_extension() {
+        let file = TempFile::new("doc.toml", "age = 22");
+        let value = load_auto(&file.0).unwrap();
+        assert_eq!(22, get_typed_value_by_path::<u64>(&value, "age").unwrap());
+    }
+
+    #[cfg(not(feature = "toml"))]
+    #[rstest]
+    fn load_auto_returns_error_when_toml_support_is_disabled() {
+        let file = TempFile::new("doc.toml", "age = 22");
+        assert!(load_auto(&file.0).is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[rstest]
+    fn load_json_converts_document_into_yaml_value() {
+        let input = r#"
+            {
+                "name": "John Doe",
+                "age": 22,
+                "rank_delta": -10,
+                "score": 214.67,
+                "adult": true,
+                "cars_owned": [{ "name": "Ford Mustang", "last_inspection": null }]
+            }
+        "#;
+        let value = load_json(input.as_bytes()).unwrap();
+
+        assert_eq!(
+            "John Doe",
+            get_typed_value_by_path::<str>(&value, "name").unwrap()
+        );
+        assert_eq!(22, get_typed_value_by_path::<u64>(&value, "age").unwrap());
+        assert_eq!(
+            -10,
+            get_typed_value_by_path::<i64>(&value, "rank_delta").unwrap()
+        );
+        assert!(get_typed_value_by_path::<bool>(&value, "adult").unwrap());
+        assert!(value["cars_owned"][0]["last_inspection"].is_null());
+    }
+
+    #[cfg(feature = "json")]
+    #[rstest]
+    fn load_json_returns_error_when_invalid_document_is_passed() {
+        assert!(load_json("{ \"key\": ".as_bytes()).is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[rstest]
+    fn load_toml_converts_document_into_yaml_value() {
+        let input = r#"
+            name = "John Doe"
+            age = 22
+            score = 214.67
+            adult = true
+            born = 1979-05-27T07:32:00Z
+
+            [[cars_owned]]
+            name = "Ford Mustang"
+            last_inspection = { date = 2020-01-05 }
+        "#;
+        let value = load_toml(input.as_bytes()).unwrap();
+
+        assert_eq!(
+            "John Doe",
+            get_typed_value_by_path::<str>(&value, "name").unwrap()
+        );
+        assert_eq!(22, get_typed_value_by_path::<u64>(&value, "age").unwrap());
+        assert!(get_typed_value_by_path::<bool>(&value, "adult").unwrap());
+        assert_eq!(
+            "1979-05-27T07:32:00Z",
+            get_typed_value_by_path::<str>(&value, "born").unwrap()
+        );
+        assert_eq!(
+            "2020-01-05",
+            get_typed_value_by_path::<str>(&value["cars_owned"][0], "last_inspection.date")
+                .unwrap()
+        );
+    }
+
+    #[cfg(feature = "toml")]
+    #[rstest]
+    fn load_toml_preserves_key_order() {
+        let value = load_toml("b = 1\na = 2".as_bytes()).unwrap();
+        let keys: Vec<_> = value
+            .as_mapping()
+            .unwrap()
+            .iter()
+            .map(|(k, _)| k.as_str().unwrap())
+            .collect();
+        assert_eq!(vec!["b", "a"], keys);
+    }
+
+    #[cfg(feature = "toml")]
+    #[rstest]
+    fn load_toml_returns_error_when_invalid_document_is_passed() {
+        assert!(load_toml("key = ".as_bytes()).is_err());
+    }
+}
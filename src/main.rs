@@ -3,12 +3,699 @@
 #![deny(warnings)]
 #![deny(missing_docs, rustdoc::missing_crate_level_docs)]
 
-pub mod error;
-pub mod yutil;
-
+use clap::{ArgEnum, Parser};
+use env_logger::WriteStyle;
 use log::info;
+use pipeline::error::{Pipeline, Result};
+use pipeline::junit::to_junit_xml;
+use pipeline::loader::{supported_formats, STDIN_PATH};
+use pipeline::pipeline::{
+    document_vars, expand_for_each, load_value_with_warnings, redact_secrets, secret_values,
+    PipelineDef, HOOK_VARS,
+};
+use pipeline::runner::{
+    CancelToken, RunEvent, RunOptions, RunReport, ScratchDir, Status, DEFAULT_MAX_OUTPUT_BYTES,
+    SCRATCH_DIR_VAR,
+};
+use pipeline::subst::{Chain, EnvResolver, Placeholder, Precedence, Resolver, ShellEscaped};
+use pipeline::yutil::{merge, ConverterRegistry};
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+// Interval of checking the pipeline file for changes in watch mode
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// Time the pipeline file must stay unchanged before a rerun, so that a burst of writes (e.g. an
+// editor saving a file in several steps) triggers a single run
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+// Interval of checking whether the runner was interrupted
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// Set once the runner receives SIGINT or SIGTERM
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// Set while interrupts cancel runs rather than terminating the runner
+static CANCEL_ON_INTERRUPT: AtomicBool = AtomicBool::new(false);
+
+/// Terminal coloring policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+enum Color {
+    /// Colorize the output only if it is written to a terminal.
+    Auto,
+    /// Always colorize the output.
+    Always,
+    /// Never colorize the output.
+    Never,
+}
+
+impl From<Color> for WriteStyle {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Auto => Self::Auto,
+            Color::Always => Self::Always,
+            Color::Never => Self::Never,
+        }
+    }
+}
+
+/// Command line arguments.
+#[derive(Debug, Clone, Parser)]
+#[clap(about, version)]
+struct Args {
+    /// Pipeline file to run. Use `-` to read the pipeline from the standard input. When given
+    /// multiple times, the files are deep merged from left to right, later files taking
+    /// precedence.
+    #[clap(
+        short,
+        long,
+        default_value = "pipeline.yaml",
+        multiple_occurrences(true),
+        number_of_values(1)
+    )]
+    file: Vec<PathBuf>,
+
+    /// Print commands instead of executing them.
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Stop at the first failing stage. Use `--fail-fast=false` to attempt all stages.
+    #[clap(long, default_value = "true", parse(try_from_str))]
+    fail_fast: bool,
+
+    /// Write a JUnit XML report to the given file.
+    #[clap(long, value_name = "PATH")]
+    junit: Option<PathBuf>,
+
+    /// Print the fully resolved pipeline document, with secrets redacted, and exit.
+    #[clap(long)]
+    print_config: bool,
+
+    /// Maximum number of bytes of output kept per stream of a step, unless the step sets its own
+    /// `max_output_bytes`. Longer output is truncated in the middle. Use `none` to keep the whole
+    /// output.
+    #[clap(
+        long,
+        value_name = "BYTES",
+        default_value_t = OutputLimit(Some(DEFAULT_MAX_OUTPUT_BYTES))
+    )]
+    max_output_bytes: OutputLimit,
+
+    /// Rerun the pipeline whenever the pipeline file changes, cancelling a run in progress.
+    #[clap(long)]
+    watch: bool,
+
+    /// When to color status lines of stages and steps, and log levels. `auto` colors them only if
+    /// the standard error is a terminal.
+    #[clap(long, arg_enum, default_value = "auto")]
+    color: Color,
+
+    /// Keep the scratch directory of a run (`${PIPELINE_TMP}`) instead of removing it.
+    #[clap(long)]
+    keep_tmp: bool,
+
+    /// Fail if a `${NAME}` placeholder refers to an unknown variable instead of leaving it
+    /// untouched. Escape placeholders meant literally as `\${NAME}`.
+    #[clap(long)]
+    strict_vars: bool,
+
+    /// Set a variable as `KEY=VALUE`, may be given multiple times. The variable is used for
+    /// `${KEY}` placeholders in place of a process environment variable of the same name and is
+    /// exported to steps, unless defined by the pipeline itself.
+    #[clap(
+        long = "env",
+        value_name = "KEY=VALUE",
+        parse(try_from_str = parse_env_entry),
+        multiple_occurrences(true),
+        number_of_values(1)
+    )]
+    env: Vec<(String, String)>,
+
+    /// Quote values substituted into `run` commands, so that each becomes a single literal shell
+    /// word. Placeholders of commands must not be quoted then, e.g. `echo ${MSG}` rather than
+    /// `echo "${MSG}"`.
+    #[clap(long)]
+    shell_escape: bool,
+
+    /// Print supported input formats, value types and features as JSON, and exit.
+    #[clap(long)]
+    capabilities: bool,
+}
+
+/// Limit of captured output, `None` meaning no limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct OutputLimit(Option<usize>);
+
+impl FromStr for OutputLimit {
+    type Err = String;
+
+    fn from_str(text: &str) -> std::result::Result<Self, Self::Err> {
+        match text {
+            "none" => Ok(Self(None)),
+            _ => text
+                .parse()
+                .map(|bytes| Self(Some(bytes)))
+                .map_err(|_| format!("Invalid limit `{}`, expected bytes or `none`", text)),
+        }
+    }
+}
+
+impl Display for OutputLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(bytes) => write!(f, "{}", bytes),
+            None => write!(f, "none"),
+        }
+    }
+}
+
+impl From<&Args> for RunOptions {
+    fn from(args: &Args) -> Self {
+        Self {
+            dry_run: args.dry_run,
+            fail_fast: args.fail_fast,
+            max_output_bytes: args.max_output_bytes.0,
+            env: args.env.iter().cloned().collect(),
+            forward_output: true,
+            ..Self::default()
+        }
+    }
+}
+
+// Split a `KEY=VALUE` entry of the `--env` option
+fn parse_env_entry(entry: &str) -> std::result::Result<(String, String), String> {
+    match entry.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("Invalid entry `{}`, expected `KEY=VALUE`", entry)),
+    }
+}
+
+// Load, merge and resolve the pipeline documents, printing warnings found in the files, and
+// create the scratch directory of the run. Every file is loaded before any of them is merged, so
+// a missing file is reported before a run. The directory is created only once the files are read,
+// as reading the standard input may block and interrupts then terminate the runner
+fn load(args: &Args) -> Result<(Value, ScratchDir)> {
+    let mut documents = Vec::with_capacity(args.file.len());
+    for path in &args.file {
+        let (document, warnings) = load_value_with_warnings(path)?;
+        for warning in &warnings {
+            warning.print_verbose();
+        }
+        documents.push(document);
+    }
+    let mut documents = documents.into_iter();
+    let mut value = documents.next().unwrap_or(Value::Null);
+    for overlay in documents {
+        merge(&mut value, &overlay)?;
+    }
+    expand_for_each(&mut value)?;
+    let scratch = ScratchDir::create()?;
+    let value = resolve(args, value, scratch.path())?;
+    Ok((value, scratch))
+}
+
+// Substitute variables of a merged pipeline document
+fn resolve(args: &Args, mut value: Value, scratch_dir: &Path) -> Result<Value> {
+    let mut vars = document_vars(&value)?;
+    vars.entry(SCRATCH_DIR_VAR.to_string())
+        .or_insert_with(|| scratch_dir.to_string_lossy().into_owned());
+    // Variables of the command line shadow the process environment
+    let cli_env: BTreeMap<_, _> = args.env.iter().cloned().collect();
+    let env = Chain::new(&cli_env, EnvResolver);
+    let resolver = Precedence::default().compose(&vars, &env);
+    let placeholder = Placeholder::default().strict(args.strict_vars);
+
+    // Hooks refer to variables known only once the stages ran, so they are substituted
+    // partially, keeping escapes for the substitution of those variables when the hooks run
+    const HOOKS: [&str; 3] = ["on_success", "on_failure", "on_complete"];
+    let mut hooks = Mapping::new();
+    for hook in HOOKS {
+        if let Some(steps) = value.get_mut(hook) {
+            hooks.insert(Value::from(hook), std::mem::take(steps));
+        }
+    }
+    let mut hooks = Value::Mapping(hooks);
+    substitute(
+        &mut value,
+        &placeholder,
+        &resolver,
+        args.shell_escape,
+        false,
+    )?;
+    // Placeholders of hooks are checked the way a full substitution would check them
+    placeholder.substitute(&mut hooks.clone(), &Chain::new(&resolver, HookVars))?;
+    substitute(&mut hooks, &placeholder, &resolver, args.shell_escape, true)?;
+    for hook in HOOKS {
+        if let (Some(slot), Some(steps)) = (value.get_mut(hook), hooks.get_mut(hook)) {
+            *slot = std::mem::take(steps);
+        }
+    }
+    Ok(value)
+}
+
+// Substitute variables of a pipeline document, fully or partially. Values substituted into `run`
+// commands are shell escaped if requested
+fn substitute(
+    value: &mut Value,
+    placeholder: &Placeholder,
+    resolver: &dyn Resolver,
+    shell_escape: bool,
+    partial: bool,
+) -> Result<()> {
+    let substitute = |value: &mut Value, resolver: &dyn Resolver| {
+        if partial {
+            placeholder.substitute_partial(value, resolver)
+        } else {
+            placeholder.substitute(value, resolver)
+        }
+    };
+    if !shell_escape {
+        return substitute(value, resolver);
+    }
+
+    // Commands are taken out of the document, so that they are substituted exactly once
+    let mut commands: Vec<Value> = run_commands(value)
+        .into_iter()
+        .map(std::mem::take)
+        .collect();
+    substitute(value, resolver)?;
+    let escaped = ShellEscaped(resolver);
+    for command in &mut commands {
+        substitute(command, &escaped)?;
+    }
+    for (slot, command) in run_commands(value).into_iter().zip(commands) {
+        *slot = command;
+    }
+    Ok(())
+}
+
+// Obtain `run` commands of steps and hooks of a pipeline document in document order
+fn run_commands(value: &mut Value) -> Vec<&mut Value> {
+    fn step_commands<'a>(steps: &'a mut Value, commands: &mut Vec<&'a mut Value>) {
+        let Value::Sequence(steps) = steps else {
+            return;
+        };
+        commands.extend(steps.iter_mut().filter_map(|step| step.get_mut("run")));
+    }
+
+    let mut commands = Vec::new();
+    let Value::Mapping(map) = value else {
+        return commands;
+    };
+    for (key, node) in map.iter_mut() {
+        match key.as_str() {
+            Some("stages") => {
+                if let Value::Sequence(stages) = node {
+                    for steps in stages.iter_mut().filter_map(|stage| stage.get_mut("steps")) {
+                        step_commands(steps, &mut commands);
+                    }
+                }
+            }
+            Some("on_success" | "on_failure" | "on_complete") => {
+                step_commands(node, &mut commands);
+            }
+            _ => {}
+        }
+    }
+    commands
+}
+
+// Resolver of hook variables to arbitrary values, for checking placeholders of hooks
+struct HookVars;
+
+impl Resolver for HookVars {
+    fn resolve(&self, name: &str) -> Option<String> {
+        HOOK_VARS.contains(&name).then(String::new)
+    }
+}
+
+// Describe capabilities of this build as a JSON object
+fn capabilities_json() -> String {
+    fn list(items: &[&str]) -> String {
+        let items: Vec<_> = items.iter().map(|item| json_string(item)).collect();
+        format!("[{}]", items.join(", "))
+    }
+
+    let features: Vec<_> = [
+        ("json", cfg!(feature = "json")),
+        ("toml", cfg!(feature = "toml")),
+        ("chrono", cfg!(feature = "chrono")),
+        ("regex", cfg!(feature = "regex")),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(name, _)| name)
+    .collect();
+    format!(
+        "{{\"name\": {}, \"version\": {}, \"input_formats\": {}, \"types\": {}, \"features\": {}}}",
+        json_string(env!("CARGO_PKG_NAME")),
+        json_string(env!("CARGO_PKG_VERSION")),
+        list(&supported_formats()),
+        list(&ConverterRegistry::with_builtin().names()),
+        list(&features),
+    )
+}
+
+// Quote a string as a JSON string literal. Characters other than quotes, backslashes and control
+// characters are valid within JSON strings and are kept as they are
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            ch if u32::from(ch) < 0x20 => {
+                quoted.push_str(&format!("\\u{:04x}", u32::from(ch)));
+            }
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Print the resolved pipeline document with secrets redacted
+fn print_config(mut value: Value) -> Result<()> {
+    redact_secrets(&mut value);
+    let yaml = serde_yaml::to_string(&value)
+        .map_err(|e| Pipeline::new_debug("Could not serialize the pipeline", &e.to_string()))?;
+    print!("{}", yaml);
+    Ok(())
+}
+
+// Make SIGINT (e.g. Ctrl-C) and SIGTERM cancel runs instead of killing the runner, so that runs
+// end normally and e.g. their scratch directories are removed. Outside of runs, see
+// `cancel_runs_on_interrupt`, the signals terminate the runner as usual
+#[cfg(unix)]
+fn install_interrupt_handler() {
+    extern "C" fn handle(signal: libc::c_int) {
+        if CANCEL_ON_INTERRUPT.load(Ordering::SeqCst) {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+            return;
+        }
+        // SAFETY: restoring the default disposition and raising a signal are async-signal-safe
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    }
+    let handler = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // SAFETY: the handler only uses atomics and async-signal-safe functions
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+#[cfg(not(unix))]
+fn install_interrupt_handler() {}
+
+// Make interrupts cancel runs while a function executes, rather than terminating the runner
+fn cancel_runs_on_interrupt<T>(f: impl FnOnce() -> T) -> T {
+    let previous = CANCEL_ON_INTERRUPT.swap(true, Ordering::SeqCst);
+    let result = f();
+    CANCEL_ON_INTERRUPT.store(previous, Ordering::SeqCst);
+    result
+}
+
+// Check whether the runner was interrupted
+fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+// Cancel a run once the runner is interrupted. The watching thread ends along with the run
+fn cancel_on_interrupt(cancel: CancelToken) {
+    std::thread::spawn(move || {
+        while !cancel.is_cancelled() {
+            if interrupted() {
+                info!("Interrupted, cancelling the run");
+                cancel.cancel();
+            }
+            std::thread::sleep(INTERRUPT_POLL_INTERVAL);
+        }
+    });
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    install_interrupt_handler();
+    env_logger::Builder::from_default_env()
+        .write_style(args.color.into())
+        .init();
+
+    if args.capabilities {
+        println!("{}", capabilities_json());
+        return ExitCode::SUCCESS;
+    }
+    if args.watch {
+        if args.file.iter().any(|file| file == Path::new(STDIN_PATH)) {
+            Pipeline::new("The standard input cannot be watched, please pass a file")
+                .print_verbose();
+            return ExitCode::FAILURE;
+        }
+        cancel_runs_on_interrupt(|| watch(&args))
+    } else {
+        execute(&args, CancelToken::new())
+    }
+}
+
+// Load and run the pipeline once
+fn execute(args: &Args, cancel: CancelToken) -> ExitCode {
+    cancel_on_interrupt(cancel.clone());
+    let (value, scratch) = match load(args) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            e.print_verbose();
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if args.print_config {
+        return match print_config(value) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                e.print_verbose();
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let def = match PipelineDef::from_value(&value) {
+        Ok(def) => def,
+        Err(e) => {
+            e.print_verbose();
+            return ExitCode::FAILURE;
+        }
+    };
 
-fn main() {
-    env_logger::init();
     info!("Running pipeline");
+    let opts = RunOptions {
+        cancel,
+        secrets: secret_values(&value),
+        scratch_dir: Some(scratch.path().to_path_buf()),
+        ..RunOptions::from(args)
+    };
+    let colored = colorize(args.color.into(), std::io::stderr().is_terminal());
+    let Some(report) = cancel_runs_on_interrupt(|| run(&def, &opts, colored)) else {
+        Pipeline::new("The run ended unexpectedly").print_verbose();
+        return ExitCode::FAILURE;
+    };
+    if args.keep_tmp {
+        info!("Scratch directory kept at `{}`", scratch.keep().display());
+    }
+
+    if let Some(path) = &args.junit {
+        if let Err(e) = std::fs::write(path, to_junit_xml(&report)) {
+            Pipeline::new_debug(
+                &format!("Could not write JUnit report to `{}`", path.display()),
+                &e.to_string(),
+            )
+            .print_verbose();
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if report.succeeded() && !interrupted() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+// Run a pipeline, printing a status line to the standard error whenever a step or a stage finishes.
+// Returns the report of the run, `None` if the run ended without one
+fn run(def: &PipelineDef, opts: &RunOptions, colored: bool) -> Option<RunReport> {
+    for event in def.run_events(opts) {
+        match event {
+            RunEvent::StepFinished { outcome, .. } => {
+                eprintln!(
+                    "{}",
+                    status_line("Step", &outcome.label(), outcome.status, colored)
+                );
+            }
+            RunEvent::StageFinished { outcome } => {
+                eprintln!(
+                    "{}",
+                    status_line("Stage", &outcome.name, outcome.status, colored)
+                );
+            }
+            RunEvent::RunFinished { report } => return Some(report),
+            _ => {}
+        }
+    }
+    None
+}
+
+// Decide whether to colorize a stream given the coloring policy
+fn colorize(style: WriteStyle, is_terminal: bool) -> bool {
+    match style {
+        WriteStyle::Always => true,
+        WriteStyle::Auto => is_terminal,
+        _ => false,
+    }
+}
+
+// Format a status line of a stage or a step, colored green, red or yellow by the status if
+// requested
+fn status_line(kind: &str, name: &str, status: Status, colored: bool) -> String {
+    let (status, color) = match status {
+        Status::Succeeded => ("succeeded", "32"),
+        Status::Failed => ("failed", "31"),
+        Status::Skipped => ("skipped", "33"),
+    };
+    let line = format!("{} `{}` {}", kind, name, status);
+    if colored {
+        format!("\x1b[{}m{}\x1b[0m", color, line)
+    } else {
+        line
+    }
+}
+
+// Run the pipeline on every change of the pipeline files until the runner is interrupted
+fn watch(args: &Args) -> ExitCode {
+    loop {
+        let modified = modification_times(&args.file);
+        let cancel = CancelToken::new();
+        let run = {
+            let (args, cancel) = (args.clone(), cancel.clone());
+            std::thread::spawn(move || execute(&args, cancel))
+        };
+
+        wait_for_change(&args.file, modified);
+        cancel.cancel();
+        let _ = run.join();
+        if interrupted() {
+            return ExitCode::FAILURE;
+        }
+        info!("Pipeline file changed, rerunning the pipeline");
+    }
+}
+
+// Block until the modification times of files differ from the given ones and settle, or the
+// runner is interrupted
+fn wait_for_change(paths: &[PathBuf], mut modified: Vec<Option<SystemTime>>) {
+    while modification_times(paths) == modified {
+        if interrupted() {
+            return;
+        }
+        std::thread::sleep(WATCH_POLL_INTERVAL);
+    }
+    loop {
+        modified = modification_times(paths);
+        std::thread::sleep(WATCH_DEBOUNCE);
+        if modification_times(paths) == modified || interrupted() {
+            return;
+        }
+    }
+}
+
+// Obtain the modification times of files, `None` for a file if it cannot be determined e.g. the
+// file is missing
+fn modification_times(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    /* -------------------------- */
+    /* ---- Test definitions ---- */
+    /* -------------------------- */
+
+    #[rstest]
+    #[case("yaml", "\"yaml\"")]
+    #[case("a\"b\\c", "\"a\\\"b\\\\c\"")]
+    #[case("line\nnext\ttab\r", "\"line\\nnext\\ttab\\r\"")]
+    #[case("\u{1}\u{1f}\u{7f}", "\"\\u0001\\u001f\u{7f}\"")]
+    #[case("zażółć ✓", "\"zażółć ✓\"")]
+    fn json_string_escapes_text_as_json(#[case] text: &str, #[case] expected: &str) {
+        assert_eq!(expected, json_string(text));
+    }
+
+    #[cfg(feature = "json")]
+    #[rstest]
+    fn capabilities_json_is_valid_json() {
+        let capabilities: serde_json::Value = serde_json::from_str(&capabilities_json()).unwrap();
+        assert_eq!(env!("CARGO_PKG_NAME"), capabilities["name"]);
+        assert!(capabilities["types"].is_array());
+    }
+
+    #[rstest]
+    #[case(Color::Never, true, false)]
+    #[case(Color::Never, false, false)]
+    #[case(Color::Auto, false, false)]
+    #[case(Color::Auto, true, true)]
+    #[case(Color::Always, false, true)]
+    fn colorize_follows_policy_and_terminal(
+        #[case] color: Color,
+        #[case] is_terminal: bool,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(expected, colorize(color.into(), is_terminal));
+    }
+
+    #[rstest]
+    #[case(
+        Status::Succeeded,
+        "Step `build` succeeded",
+        "\x1b[32mStep `build` succeeded\x1b[0m"
+    )]
+    #[case(
+        Status::Failed,
+        "Step `build` failed",
+        "\x1b[31mStep `build` failed\x1b[0m"
+    )]
+    #[case(
+        Status::Skipped,
+        "Step `build` skipped",
+        "\x1b[33mStep `build` skipped\x1b[0m"
+    )]
+    fn status_line_is_plain_unless_colored(
+        #[case] status: Status,
+        #[case] plain: &str,
+        #[case] colored: &str,
+    ) {
+        let line = |color: Color, is_terminal| {
+            status_line("Step", "build", status, colorize(color.into(), is_terminal))
+        };
+        assert_eq!(plain, line(Color::Never, true));
+        assert_eq!(plain, line(Color::Auto, false));
+        assert_eq!(colored, line(Color::Always, false));
+    }
 }
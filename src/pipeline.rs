@@ -0,0 +1,1300 @@
+//! Pipeline definition.
+//!
+//! A pipeline comprises a list of stages executed in the order of their definition. Each stage
+//! comprises a list of steps, each step being a single shell command:
+//!
+//! ```yaml
+//! stages:
+//!   - name: build
+//!     steps:
+//!       - name: compile
+//!         run: cargo build
+//!   - name: test
+//!     steps:
+//!       - run: cargo test
+//! ```
+//!
+//! Steps are run with a shell, in an environment and a working directory which can be
+//! configured with [`Settings`] at three levels: pipeline-wide `defaults`, a stage and a step.
+//! See [`Settings::overlay`] for the precedence rules:
+//!
+//! ```yaml
+//! defaults:
+//!   shell: bash -eo pipefail -c
+//!   env: { PROFILE: release }
+//! stages:
+//!   - name: build
+//!     timeout_secs: 600
+//!     steps:
+//!       - run: cargo build --profile $PROFILE
+//!         workdir: backend
+//! ```
+//!
+//! String values may refer to variables defined in the `vars` block or in the process
+//! environment with `${NAME}` placeholders. Placeholders are substituted in the loaded document,
+//! before the definition is constructed, see [`document_vars`] and [`crate::subst`].
+//!
+//! A step with a `for_each` list is run once per item, the item being available to `${item}`
+//! placeholders (`${item.<key>}` for nested values), see [`expand_for_each`]:
+//!
+//! ```yaml
+//! regions: [eu-west-1, us-east-1]
+//! stages:
+//!   - name: deploy
+//!     steps:
+//!       - name: deploy to ${item}
+//!         run: ./deploy.sh ${item}
+//!         for_each: regions
+//! ```
+//!
+//! The definition can be loaded from a file with [`load_from_file`] or obtained from an already
+//! loaded document with [`PipelineDef::from_value`]:
+//!
+//! ```
+//! use pipeline::loader::load_yaml;
+//! use pipeline::pipeline::PipelineDef;
+//!
+//! let yaml = "
+//! stages:
+//!   - name: build
+//!     steps:
+//!       - run: cargo build
+//! ";
+//! let def = PipelineDef::from_value(&load_yaml(yaml.as_bytes())?)?;
+//!
+//! assert_eq!("build", def.stages[0].name);
+//! assert_eq!("cargo build", def.stages[0].steps[0].run);
+//! # Ok::<(), pipeline::error::Pipeline>(())
+//! ```
+
+use crate::error::Pipeline;
+use crate::error::Result;
+use crate::error::Severity;
+use crate::loader::load_auto;
+use crate::runner::SCRATCH_DIR_VAR;
+use crate::subst::{unresolved_placeholders, Placeholder};
+use crate::yutil::{get_value_by_path, value_type_name, walk};
+use serde::Deserialize;
+use serde_yaml::{Sequence, Value};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// Keys recognized at each level of a pipeline document
+const PIPELINE_KEYS: &[&str] = &[
+    "vars",
+    "defaults",
+    "stages",
+    "on_success",
+    "on_failure",
+    "on_complete",
+];
+const SETTINGS_KEYS: &[&str] = &[
+    "shell",
+    "env",
+    "workdir",
+    "timeout_secs",
+    "max_output_bytes",
+];
+const STAGE_KEYS: &[&str] = &["name", "steps"];
+const STEP_KEYS: &[&str] = &["name", "run", "expected_exit", "for_each"];
+
+/// Top-level pipeline definition.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct PipelineDef {
+    /// Variables available to `${NAME}` placeholders, see [`crate::subst`].
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+    /// Settings applied to all steps unless overridden by a stage or a step.
+    #[serde(default)]
+    pub defaults: Settings,
+    /// Stages in the order of execution.
+    pub stages: Vec<Stage>,
+    /// Hook steps run after the stages if no stage failed, see [`HOOK_VARS`].
+    #[serde(default)]
+    pub on_success: Vec<Step>,
+    /// Hook steps run after the stages if a stage failed, see [`HOOK_VARS`].
+    #[serde(default)]
+    pub on_failure: Vec<Step>,
+    /// Hook steps run after the stages regardless of the outcome, following `on_success` or
+    /// `on_failure`, see [`HOOK_VARS`].
+    #[serde(default)]
+    pub on_complete: Vec<Step>,
+}
+
+/// Variables describing the outcome of a run, available to placeholders of hook steps.
+///
+/// Hooks (`on_success`, `on_failure` and `on_complete` steps of [`PipelineDef`]) are run after
+/// the stages, regardless of fail-fast, unless the run is a dry run or is cancelled. Every step
+/// of a hook is run even if a previous one fails. Failures of hooks are logged but do not change
+/// the outcome of the run. Following variables are substituted into commands, names and
+/// environment variables of hook steps when they run:
+///  - `pipeline.status`: `succeeded` or `failed`,
+///  - `pipeline.failed_stage`: name of the first failed stage, empty if none failed.
+///
+/// Escaped placeholders, e.g. `\${pipeline.status}`, are unescaped at that point, so other
+/// variables of hooks should be substituted beforehand with
+/// [`crate::subst::Placeholder::substitute_partial`], which keeps escapes.
+pub const HOOK_VARS: &[&str] = &["pipeline.status", "pipeline.failed_stage"];
+
+/// A named group of steps.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Stage {
+    /// Stage name.
+    pub name: String,
+    /// Settings applied to all steps of the stage unless overridden by a step.
+    #[serde(flatten)]
+    pub settings: Settings,
+    /// Steps in the order of execution.
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+/// A single command executed within a stage.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Step {
+    /// Optional step name.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Settings of the step.
+    #[serde(flatten)]
+    pub settings: Settings,
+    /// Shell command to run.
+    pub run: String,
+    /// Exit code meaning success, 0 if not given. Any other exit code fails the step, including
+    /// 0 when a non-zero code is expected.
+    #[serde(default)]
+    pub expected_exit: Option<i32>,
+    /// Items the step is repeated for, see [`expand_for_each`]. Definitions with remaining loops
+    /// are rejected by [`PipelineDef::validate`].
+    #[serde(default)]
+    pub for_each: Option<Value>,
+}
+
+impl Step {
+    /// Obtain a label identifying the step: its name if given, a name derived from the command
+    /// otherwise (see [`derive_step_name`]).
+    #[must_use]
+    pub fn label(&self) -> Cow<'_, str> {
+        step_label(self.name.as_deref(), &self.run)
+    }
+}
+
+/// Execution settings of steps.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Settings {
+    /// Shell used to run commands, e.g. `bash -eo pipefail -c`. The value is split on
+    /// whitespace and the command is passed as the last argument. Defaults to `sh -c` (`cmd /C`
+    /// on Windows).
+    #[serde(default)]
+    pub shell: Option<String>,
+    /// Environment variables set for commands, in addition to the inherited environment.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Working directory of commands. Relative paths are resolved against the working
+    /// directory of the runner. Defaults to the working directory of the runner.
+    #[serde(default)]
+    pub workdir: Option<PathBuf>,
+    /// Time after which a command is killed and its step fails. No limit by default.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Maximum number of bytes of output kept per stream of a command, see
+    /// [`crate::runner::StepOutcome::stdout`]. Defaults to the limit of the run.
+    #[serde(default)]
+    pub max_output_bytes: Option<usize>,
+}
+
+impl Settings {
+    /// Apply more specific settings on top of these ones.
+    ///
+    /// Fields set in `other` win, unset fields are taken from `self`. Environment variables are
+    /// merged, variables defined in `other` replacing the ones with the same name. The runner
+    /// computes settings of a step as `defaults.overlay(stage).overlay(step)`, so the precedence
+    /// is step > stage > pipeline defaults.
+    #[must_use]
+    pub fn overlay(&self, other: &Self) -> Self {
+        let mut env = self.env.clone();
+        env.extend(other.env.iter().map(|(k, v)| (k.clone(), v.clone())));
+        Self {
+            shell: other.shell.clone().or_else(|| self.shell.clone()),
+            env,
+            workdir: other.workdir.clone().or_else(|| self.workdir.clone()),
+            timeout_secs: other.timeout_secs.or(self.timeout_secs),
+            max_output_bytes: other.max_output_bytes.or(self.max_output_bytes),
+        }
+    }
+}
+
+impl PipelineDef {
+    /// Obtain hooks run after the stages given whether the stages succeeded, as pairs of a hook
+    /// name and its steps in the order of execution. Hooks without steps are omitted.
+    #[must_use]
+    pub fn hooks(&self, succeeded: bool) -> Vec<(&'static str, &[Step])> {
+        let outcome = if succeeded {
+            ("on_success", self.on_success.as_slice())
+        } else {
+            ("on_failure", self.on_failure.as_slice())
+        };
+        [outcome, ("on_complete", self.on_complete.as_slice())]
+            .into_iter()
+            .filter(|(_, steps)| !steps.is_empty())
+            .collect()
+    }
+
+    /// Construct a pipeline definition from a YAML value.
+    ///
+    /// Loops of steps are expanded with [`expand_for_each`] and the definition is validated with
+    /// [`PipelineDef::validate`].
+    ///
+    /// # Errors
+    /// The function returns an error if the value does not describe a valid pipeline.
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let mut expanded = value.clone();
+        expand_for_each(&mut expanded)?;
+        let def: Self = serde_yaml::from_value(expanded).map_err(|e| {
+            Pipeline::new_debug(
+                &format!("Invalid pipeline definition: {}", e),
+                &format!("Input object: {:?}", value),
+            )
+        })?;
+        def.validate()?;
+        Ok(def)
+    }
+
+    /// Parse and construct a pipeline definition from a YAML reader in one go.
+    ///
+    /// Unlike [`PipelineDef::from_value`], no intermediate [`Value`] is built, so the function
+    /// suits cases where the document is not processed (e.g. traversed or substituted) before
+    /// deserialization. Error messages include the location of the offending node, when known.
+    /// The definition is validated with [`PipelineDef::validate`], so steps with `for_each` loops,
+    /// which need the document to be expanded, are rejected.
+    ///
+    /// # Errors
+    /// The function returns an error if the reader fails or the input does not describe a valid
+    /// pipeline.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        // Parser errors carry the location of the offending node in their message
+        let def: Self = serde_yaml::from_reader(reader)
+            .map_err(|e| Pipeline::new(&format!("Invalid pipeline definition: {}", e)))?;
+        def.validate()?;
+        Ok(def)
+    }
+
+    /// Validate the definition.
+    ///
+    /// Following rules are checked:
+    ///  - stage names must not be empty or whitespace-only,
+    ///  - step names, if given, must not be empty or whitespace-only,
+    ///  - step names must be unique within a stage or a hook,
+    ///  - steps must not have unexpanded `for_each` loops.
+    ///
+    /// # Errors
+    /// The function returns an error describing the first rule violation found.
+    pub fn validate(&self) -> Result<()> {
+        for (stage_idx, stage) in self.stages.iter().enumerate() {
+            if stage.name.trim().is_empty() {
+                return Err(Pipeline::new(&format!(
+                    "Stage #{} has an empty name",
+                    stage_idx + 1
+                )));
+            }
+
+            validate_steps(&stage.steps, "stage", &stage.name)?;
+        }
+        for (hook, steps) in [
+            ("on_success", &self.on_success),
+            ("on_failure", &self.on_failure),
+            ("on_complete", &self.on_complete),
+        ] {
+            validate_steps(steps, "hook", hook)?;
+        }
+        Ok(())
+    }
+}
+
+// Validate steps of a stage or a hook, `kind` being a lowercase name of the group
+fn validate_steps(steps: &[Step], kind: &str, group: &str) -> Result<()> {
+    let mut step_names = HashSet::new();
+    for (step_idx, step) in steps.iter().enumerate() {
+        if step.for_each.is_some() {
+            return Err(Pipeline::new(&format!(
+                "Step `{}` of {} `{}` has a `for_each` loop which was not expanded",
+                step.label(),
+                kind,
+                group
+            )));
+        }
+        let Some(name) = step.name.as_deref() else {
+            continue;
+        };
+        if name.trim().is_empty() {
+            return Err(Pipeline::new(&format!(
+                "Step #{} of {} `{}` has an empty name",
+                step_idx + 1,
+                kind,
+                group
+            )));
+        }
+        if !step_names.insert(name) {
+            let mut kind = kind.to_string();
+            kind[..1].make_ascii_uppercase();
+            return Err(Pipeline::new(&format!(
+                "{} `{}` contains more than one step named `{}`",
+                kind, group, name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Maximum number of characters of a step name derived from its command.
+pub const MAX_DERIVED_NAME_CHARS: usize = 40;
+
+/// Derive a name of an unnamed step from its command.
+///
+/// The name is the first non-blank line of the command with whitespace collapsed. It is cut to
+/// [`MAX_DERIVED_NAME_CHARS`] characters and followed by `...` when the command is longer, i.e.
+/// when the line is cut or more lines follow.
+#[must_use]
+pub fn derive_step_name(run: &str) -> String {
+    let mut lines = run.lines().skip_while(|l| l.trim().is_empty());
+    let line = lines.next().unwrap_or_default();
+    let mut words = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut cut = lines.any(|l| !l.trim().is_empty());
+    if let Some((idx, _)) = words.char_indices().nth(MAX_DERIVED_NAME_CHARS) {
+        words.truncate(idx);
+        words.truncate(words.trim_end().len());
+        cut = true;
+    }
+    if cut {
+        words.push_str("...");
+    }
+    words
+}
+
+// Label of a step given its optional name and its command
+pub(crate) fn step_label<'a>(name: Option<&'a str>, run: &str) -> Cow<'a, str> {
+    name.map_or_else(|| Cow::Owned(derive_step_name(run)), Cow::Borrowed)
+}
+
+/// Expand steps with `for_each` loops in a pipeline document.
+///
+/// Steps of stages and of hooks (see [`HOOK_VARS`]) are expanded. A step with a `for_each` key is
+/// replaced with one copy of the step per item, in the order of
+/// items. The key holds either an inline sequence of items or a path (see
+/// [`crate::yutil::get_value_by_path`]) to a sequence elsewhere in the document, e.g. under a
+/// top-level key. An empty sequence removes the step.
+///
+/// In every copy `${item}` placeholders are replaced with the item, if it is a scalar. Values
+/// nested in an item are available as `${item.<path>}`, e.g. `${item.name}` or `${item.ports.0}`.
+/// Other placeholders, including escaped ones, are kept verbatim for the substitution of
+/// variables (see [`crate::subst::Placeholder::substitute_partial`]), so expansion should run
+/// first. Names of expanded steps should refer to the item to remain unique.
+///
+/// # Errors
+/// The function returns an error if a `for_each` value is neither a sequence nor a path to a
+/// sequence, or a placeholder of a step is not terminated.
+pub fn expand_for_each(value: &mut Value) -> Result<()> {
+    let document = value.clone();
+    let stages = value
+        .get_mut("stages")
+        .and_then(Value::as_sequence_mut)
+        .into_iter()
+        .flatten();
+    for (stage_idx, stage) in stages.enumerate() {
+        let stage_ctx = stage.get("name").and_then(Value::as_str).map_or_else(
+            || format!("stage #{}", stage_idx + 1),
+            |name| format!("stage `{}`", name),
+        );
+        if let Some(steps) = stage.get_mut("steps").and_then(Value::as_sequence_mut) {
+            expand_steps(&document, steps, &stage_ctx)?;
+        }
+    }
+    for hook in ["on_success", "on_failure", "on_complete"] {
+        if let Some(steps) = value.get_mut(hook).and_then(Value::as_sequence_mut) {
+            expand_steps(&document, steps, &format!("hook `{}`", hook))?;
+        }
+    }
+    Ok(())
+}
+
+// Expand steps with `for_each` loops of a stage or a hook described by `ctx`
+fn expand_steps(document: &Value, steps: &mut Vec<Value>, ctx: &str) -> Result<()> {
+    let mut expanded = Vec::with_capacity(steps.len());
+    for (step_idx, mut step) in steps.drain(..).enumerate() {
+        let for_each = step
+            .as_mapping_mut()
+            .and_then(|m| m.remove(&"for_each".into()));
+        let Some(for_each) = for_each else {
+            expanded.push(step);
+            continue;
+        };
+        let items = loop_items(document, &for_each).map_err(|e| {
+            let message = format!(
+                "Invalid `for_each` of step #{} of {}: {}",
+                step_idx + 1,
+                ctx,
+                e.message()
+            );
+            e.debug_detail().map_or_else(
+                || Pipeline::new(&message),
+                |detail| Pipeline::new_debug(&message, detail),
+            )
+        })?;
+        for item in items {
+            let mut copy = step.clone();
+            Placeholder::default().substitute_partial(&mut copy, &item_vars(item))?;
+            expanded.push(copy);
+        }
+    }
+    *steps = expanded;
+    Ok(())
+}
+
+// Obtain items of a `for_each` loop given inline or as a path
+fn loop_items<'a>(document: &'a Value, for_each: &'a Value) -> Result<&'a Sequence> {
+    let items = match for_each {
+        Value::String(path) => get_value_by_path(document, path)?,
+        other => other,
+    };
+    items.as_sequence().ok_or_else(|| {
+        Pipeline::new(&format!(
+            "expected a sequence or a path to a sequence, found {}",
+            value_type_name(items)
+        ))
+    })
+}
+
+// Variables exposing a loop item to placeholders
+fn item_vars(item: &Value) -> BTreeMap<String, String> {
+    let mut vars = BTreeMap::new();
+    // Loop items come from a parsed document, so they are within the depth limit
+    let _ = walk(item, &mut |path, node| {
+        let text = match node {
+            Value::String(s) => s.clone(),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::Null => String::new(),
+            _ => return,
+        };
+        let name = std::iter::once("item").chain(path.iter().copied());
+        vars.insert(name.collect::<Vec<_>>().join("."), text);
+    });
+    vars
+}
+
+/// Load a pipeline definition from a file.
+///
+/// The file format is selected by [`load_auto`].
+///
+/// # Errors
+/// The function returns an error if the file cannot be loaded or does not describe a valid
+/// pipeline.
+pub fn load_from_file(path: &Path) -> Result<PipelineDef> {
+    PipelineDef::from_value(&load_auto(path)?)
+}
+
+/// Load a pipeline document from a file along with warnings about its content.
+///
+/// The document is not validated as a pipeline definition, which allows inspecting or
+/// processing it before [`PipelineDef::from_value`] is called. Warnings are collected with
+/// [`collect_warnings`].
+///
+/// # Errors
+/// The function returns an error if the file cannot be loaded.
+pub fn load_value_with_warnings(path: &Path) -> Result<(Value, Vec<Pipeline>)> {
+    let value = load_auto(path)?;
+    let warnings = collect_warnings(&value);
+    Ok((value, warnings))
+}
+
+/// Obtain variables defined in the `vars` block of a pipeline document.
+///
+/// An empty map is returned if the document has no `vars` block.
+///
+/// # Errors
+/// The function returns an error if the block is not a mapping of strings to strings.
+pub fn document_vars(value: &Value) -> Result<BTreeMap<String, String>> {
+    match value.get("vars") {
+        None | Some(Value::Null) => Ok(BTreeMap::new()),
+        Some(vars) => serde_yaml::from_value(vars.clone()).map_err(|e| {
+            Pipeline::new_debug(
+                &format!("Invalid pipeline variables: {}", e),
+                &format!("Input object: {:?}", vars),
+            )
+        }),
+    }
+}
+
+/// Obtain names of variables a pipeline expects from its environment.
+///
+/// Values of the `vars` block, as well as commands, names, shells, working directories and
+/// environment values of all steps, stages, hooks and defaults, are scanned for `${NAME}`
+/// placeholders (see [`crate::subst`]). Variables defined in the `vars` block of the pipeline,
+/// [`HOOK_VARS`] and [`SCRATCH_DIR_VAR`] are provided by the pipeline itself, as are
+/// placeholders with a default value, and are not reported. A variable of the block referring to
+/// the environment, e.g. `TAG: ${GIT_TAG}`, makes the referred variable required. Names are
+/// returned sorted and without duplicates.
+///
+/// Placeholders are substituted when a document is loaded, so the definition must be
+/// constructed from a document which was not substituted yet.
+#[must_use]
+pub fn required_env_vars(def: &PipelineDef) -> Vec<String> {
+    fn settings_texts(settings: &Settings, texts: &mut Sequence) {
+        texts.extend(settings.shell.iter().cloned().map(Value::from));
+        texts.extend(settings.env.values().cloned().map(Value::from));
+        let workdir = settings.workdir.iter();
+        texts.extend(workdir.map(|dir| Value::from(dir.to_string_lossy().into_owned())));
+    }
+    fn step_texts(step: &Step, texts: &mut Sequence) {
+        texts.push(Value::from(step.run.clone()));
+        texts.extend(step.name.iter().cloned().map(Value::from));
+        settings_texts(&step.settings, texts);
+    }
+
+    // Values of variables may refer to the environment as well, references to other variables
+    // are resolved by the block itself
+    let mut texts: Sequence = def.vars.values().cloned().map(Value::from).collect();
+    settings_texts(&def.defaults, &mut texts);
+    for stage in &def.stages {
+        texts.push(Value::from(stage.name.clone()));
+        settings_texts(&stage.settings, &mut texts);
+        stage
+            .steps
+            .iter()
+            .for_each(|step| step_texts(step, &mut texts));
+    }
+    for step in def
+        .on_success
+        .iter()
+        .chain(&def.on_failure)
+        .chain(&def.on_complete)
+    {
+        step_texts(step, &mut texts);
+    }
+
+    let mut known = def.vars.clone();
+    for name in HOOK_VARS.iter().chain(&[SCRATCH_DIR_VAR]) {
+        known.insert(name.to_string(), String::new());
+    }
+    let required: BTreeSet<_> = unresolved_placeholders(&Value::Sequence(texts), &known)
+        .into_iter()
+        .map(|(_, name)| name)
+        .collect();
+    required.into_iter().collect()
+}
+
+/// Collect non-fatal problems of a pipeline document.
+///
+/// At the moment unknown keys, which are ignored when the definition is parsed, are reported.
+/// Top-level keys holding items of `for_each` loops (see [`expand_for_each`]) are not reported.
+/// All returned errors have [`Severity::Warning`] severity.
+#[must_use]
+pub fn collect_warnings(value: &Value) -> Vec<Pipeline> {
+    let mut warnings = Vec::new();
+    let loop_sources = loop_sources(value);
+    unknown_keys(
+        value,
+        &[PIPELINE_KEYS, &loop_sources],
+        "the pipeline",
+        &mut warnings,
+    );
+    if let Some(defaults) = value.get("defaults") {
+        unknown_keys(
+            defaults,
+            &[SETTINGS_KEYS],
+            "pipeline defaults",
+            &mut warnings,
+        );
+    }
+
+    let stages = value.get("stages").and_then(Value::as_sequence);
+    for (stage_idx, stage) in stages.into_iter().flatten().enumerate() {
+        let stage_ctx = stage.get("name").and_then(Value::as_str).map_or_else(
+            || format!("stage #{}", stage_idx + 1),
+            |name| format!("stage `{}`", name),
+        );
+        unknown_keys(
+            stage,
+            &[STAGE_KEYS, SETTINGS_KEYS],
+            &stage_ctx,
+            &mut warnings,
+        );
+
+        let steps = stage.get("steps").and_then(Value::as_sequence);
+        for (step_idx, step) in steps.into_iter().flatten().enumerate() {
+            let step_ctx = format!("step #{} of {}", step_idx + 1, stage_ctx);
+            unknown_keys(step, &[STEP_KEYS, SETTINGS_KEYS], &step_ctx, &mut warnings);
+        }
+    }
+
+    for hook in ["on_success", "on_failure", "on_complete"] {
+        let steps = value.get(hook).and_then(Value::as_sequence);
+        for (step_idx, step) in steps.into_iter().flatten().enumerate() {
+            let step_ctx = format!("step #{} of hook `{}`", step_idx + 1, hook);
+            unknown_keys(step, &[STEP_KEYS, SETTINGS_KEYS], &step_ctx, &mut warnings);
+        }
+    }
+    warnings
+}
+
+// Top-level keys holding items of `for_each` loops given as paths
+fn loop_sources(value: &Value) -> Vec<&str> {
+    let stages = value.get("stages").and_then(Value::as_sequence);
+    let hooks = ["on_success", "on_failure", "on_complete"].map(|hook| value.get(hook));
+    stages
+        .into_iter()
+        .flatten()
+        .filter_map(|stage| stage.get("steps"))
+        .chain(hooks.into_iter().flatten())
+        .filter_map(Value::as_sequence)
+        .flatten()
+        .filter_map(|step| step.get("for_each").and_then(Value::as_str))
+        .filter_map(|path| path.split('.').next())
+        .collect()
+}
+
+/// Replace secret values of a pipeline document with a placeholder.
+///
+/// A scalar is considered secret if it is stored under a key which contains (case-insensitively)
+/// one of [`SECRET_KEY_MARKERS`] e.g. `DB_PASSWORD` or `api_token`. Such values are replaced with
+/// [`REDACTED`], as well as their occurrences in all other strings of the document, which covers
+/// secrets substituted into commands. Secrets coming from sources not present in the document,
+/// e.g. environment variables referenced directly by commands, cannot be detected.
+pub fn redact_secrets(value: &mut Value) {
+    fn redact(value: &mut Value, secrets: &[String]) {
+        match value {
+            Value::Mapping(map) => {
+                for (key, child) in map.iter_mut() {
+                    if key.as_str().is_some_and(is_secret_key)
+                        && matches!(child, Value::String(_) | Value::Number(_))
+                    {
+                        *child = Value::from(REDACTED);
+                    } else {
+                        redact(child, secrets);
+                    }
+                }
+            }
+            Value::Sequence(seq) => seq.iter_mut().for_each(|item| redact(item, secrets)),
+            Value::String(s) => {
+                for secret in secrets {
+                    if s.contains(secret.as_str()) {
+                        *s = s.replace(secret.as_str(), REDACTED);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let secrets = secret_values(value);
+    redact(value, &secrets);
+}
+
+/// Obtain secret values of a pipeline document, see [`redact_secrets`].
+///
+/// Empty values are skipped. Values are ordered from the longest to the shortest, so that
+/// secrets containing other ones are fully masked when replaced in order.
+#[must_use]
+pub fn secret_values(value: &Value) -> Vec<String> {
+    fn collect(value: &Value, secrets: &mut Vec<String>) {
+        match value {
+            Value::Mapping(map) => {
+                for (key, child) in map {
+                    let secret_key = key.as_str().is_some_and(is_secret_key);
+                    match child {
+                        Value::String(s) if secret_key && !s.is_empty() => secrets.push(s.clone()),
+                        Value::Number(n) if secret_key => secrets.push(n.to_string()),
+                        _ => collect(child, secrets),
+                    }
+                }
+            }
+            Value::Sequence(seq) => seq.iter().for_each(|item| collect(item, secrets)),
+            _ => {}
+        }
+    }
+
+    let mut secrets = Vec::new();
+    collect(value, &mut secrets);
+    secrets.sort_unstable_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    secrets.dedup();
+    secrets
+}
+
+/// Parts of key names which mark their values as secret, see [`redact_secrets`].
+pub const SECRET_KEY_MARKERS: &[&str] = &[
+    "secret",
+    "password",
+    "passwd",
+    "token",
+    "api_key",
+    "apikey",
+    "credential",
+];
+
+/// Placeholder of redacted values.
+pub const REDACTED: &str = "***";
+
+// Check whether a key name marks a secret value
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SECRET_KEY_MARKERS.iter().any(|marker| key.contains(marker))
+}
+
+// Report keys of a mapping which are not allowed
+fn unknown_keys(value: &Value, allowed: &[&[&str]], context: &str, warnings: &mut Vec<Pipeline>) {
+    let Some(map) = value.as_mapping() else {
+        return;
+    };
+    let allowed = allowed.concat();
+    for (key, _) in map {
+        if !key.as_str().is_some_and(|k| allowed.contains(&k)) {
+            let key = key
+                .as_str()
+                .map_or_else(|| format!("{:?}", key), str::to_string);
+            warnings.push(
+                Pipeline::new_debug(
+                    &format!("Unknown key `{}` in {} is ignored", key, context),
+                    &format!("Known keys: {}", allowed.join(", ")),
+                )
+                .with_severity(Severity::Warning),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    /* ------------------ */
+    /* ---- Fixtures ---- */
+    /* ------------------ */
+
+    #[fixture]
+    fn test_yaml() -> Value {
+        serde_yaml::from_str(
+            r#"
+            stages:
+                - name: "build"
+                  steps:
+                    - name: "compile"
+                      run: "cargo build"
+                    - run: "cargo doc"
+                - name: "empty"
+        "#,
+        )
+        .unwrap()
+    }
+
+    /* -------------------------- */
+    /* ---- Test definitions ---- */
+    /* -------------------------- */
+
+    #[rstest]
+    #[case("cargo build", "cargo build")]
+    #[case("  cargo   test\t--release ", "cargo test --release")]
+    #[case("\n\ncargo fmt\n  \n", "cargo fmt")]
+    #[case("set -e\ncargo build", "set -e...")]
+    #[case(
+        "cargo test --workspace --all-features --no-fail-fast",
+        "cargo test --workspace --all-features --..."
+    )]
+    #[case(
+        "echo 'żółć żółć żółć żółć żółć żółć żółć żółć'",
+        "echo 'żółć żółć żółć żółć żółć żółć żółć..."
+    )]
+    #[case(
+        "echo 1234567890123456789012345678901234 x",
+        "echo 1234567890123456789012345678901234..."
+    )]
+    #[case("", "")]
+    fn derive_step_name_uses_shortened_first_line(#[case] run: &str, #[case] expected: &str) {
+        assert_eq!(expected, derive_step_name(run));
+    }
+
+    #[rstest]
+    fn required_env_vars_reports_external_variables() {
+        let yaml = r#"
+            vars: { TAG: v1, IMAGE: "${REGISTRY}/app:${TAG}", REF: "${GIT_TAG}" }
+            defaults:
+              env: { PROFILE: "${PROFILE}" }
+            stages:
+              - name: "deploy to ${REGION}"
+                workdir: "${PIPELINE_TMP}/${APP}"
+                steps:
+                  - run: "docker push ${REGISTRY}/app:${TAG} ${EXTRA:-}"
+                  - run: "echo \\${LITERAL} ${REGISTRY}"
+            on_failure:
+              - run: "./notify.sh ${pipeline.status} ${WEBHOOK}"
+        "#;
+        let def = PipelineDef::from_value(&serde_yaml::from_str(yaml).unwrap()).unwrap();
+        assert_eq!(
+            vec!["APP", "GIT_TAG", "PROFILE", "REGION", "REGISTRY", "WEBHOOK"],
+            required_env_vars(&def)
+        );
+    }
+
+    #[rstest]
+    fn step_label_prefers_given_name() {
+        let mut step = Step {
+            run: "cargo build\ncargo doc".to_string(),
+            ..Step::default()
+        };
+        assert_eq!("cargo build...", step.label());
+
+        step.name = Some("compile".to_string());
+        assert_eq!("compile", step.label());
+    }
+
+    #[rstest]
+    fn from_value_parses_pipeline_definition(test_yaml: Value) {
+        let def = PipelineDef::from_value(&test_yaml).unwrap();
+
+        assert_eq!(2, def.stages.len());
+        assert_eq!("build", def.stages[0].name);
+        assert_eq!(
+            Step {
+                name: Some("compile".to_string()),
+                run: "cargo build".to_string(),
+                ..Step::default()
+            },
+            def.stages[0].steps[0]
+        );
+        assert_eq!(None, def.stages[0].steps[1].name);
+        assert!(def.stages[1].steps.is_empty());
+    }
+
+    #[rstest]
+    #[case("stages: ~")]
+    #[case("stages: [{ steps: [] }]")]
+    #[case("stages: [{ name: build, steps: [{ name: compile }] }]")]
+    fn from_value_returns_error_when_invalid_definition_is_passed(#[case] yaml: &str) {
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+        assert!(PipelineDef::from_value(&value).is_err());
+    }
+
+    #[rstest]
+    #[case("stages: [{ name: '' }]")]
+    #[case("stages: [{ name: ' \t' }]")]
+    #[case("stages: [{ name: build, steps: [{ name: '', run: make }] }]")]
+    #[case("stages: [{ name: build, steps: [{ name: '  ', run: make }] }]")]
+    #[case("stages: [{ name: build, steps: [{ name: a, run: make }, { name: a, run: make }] }]")]
+    fn from_value_returns_error_when_validation_fails(#[case] yaml: &str) {
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+        assert!(PipelineDef::from_value(&value).is_err());
+    }
+
+    #[rstest]
+    fn from_reader_parses_pipeline_definition() {
+        let yaml = "stages: [{ name: build, steps: [{ name: compile, run: cargo build }] }]";
+        let def = PipelineDef::from_reader(yaml.as_bytes()).unwrap();
+        assert_eq!("cargo build", def.stages[0].steps[0].run);
+    }
+
+    #[rstest]
+    #[case("stages:\n  - name: build\n    steps: [\n", "at line 4 column 1")]
+    #[case("stages:\n  - name: [build]\n", "at line 2 column 11")]
+    #[case("stages: [{ name: '' }]", "Stage #1 has an empty name")]
+    fn from_reader_returns_error_when_invalid_definition_is_passed(
+        #[case] yaml: &str,
+        #[case] expected: &str,
+    ) {
+        let err = PipelineDef::from_reader(yaml.as_bytes()).unwrap_err();
+        assert!(err.to_string().ends_with(expected), "{}", err);
+    }
+
+    #[rstest]
+    #[case("stages: []", &[])]
+    #[case("vars: ~\nstages: []", &[])]
+    #[case("vars: { TAG: v1, MODE: fast }\nstages: []", &[("MODE", "fast"), ("TAG", "v1")])]
+    fn document_vars_returns_defined_variables(
+        #[case] yaml: &str,
+        #[case] expected: &[(&str, &str)],
+    ) {
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+        let vars = document_vars(&value).unwrap();
+        assert_eq!(
+            expected.to_vec(),
+            vars.iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[rstest]
+    #[case("vars: [TAG]")]
+    #[case("vars: { TAG: [v1] }")]
+    fn document_vars_returns_error_when_invalid_block_is_passed(#[case] yaml: &str) {
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+        assert!(document_vars(&value).is_err());
+    }
+
+    #[rstest]
+    fn redact_secrets_replaces_secret_values_and_their_occurrences() {
+        let mut value: Value = serde_yaml::from_str(
+            r#"
+            vars: { DB_PASSWORD: hunter2, Api_Token: 1234, USER: admin, EMPTY_SECRET: '' }
+            stages:
+                - name: deploy
+                  steps:
+                    - run: "login admin:hunter2 --token 1234"
+                      env: { secret_list: [a, b] }
+        "#,
+        )
+        .unwrap();
+        redact_secrets(&mut value);
+
+        let expected: Value = serde_yaml::from_str(
+            r#"
+            vars: { DB_PASSWORD: "***", Api_Token: "***", USER: admin, EMPTY_SECRET: "***" }
+            stages:
+                - name: deploy
+                  steps:
+                    - run: "login admin:*** --token ***"
+                      env: { secret_list: [a, b] }
+        "#,
+        )
+        .unwrap();
+        assert_eq!(expected, value);
+    }
+
+    #[rstest]
+    fn secret_values_are_sorted_from_longest() {
+        let value: Value = serde_yaml::from_str(
+            "{ token: abc, nested: [{ db_password: abcdef }, { other_token: abc }], name: x }",
+        )
+        .unwrap();
+        assert_eq!(vec!["abcdef", "abc"], secret_values(&value));
+    }
+
+    #[rstest]
+    fn validate_accepts_same_step_names_in_different_stages() {
+        let value: Value = serde_yaml::from_str(
+            "stages: [{ name: a, steps: [{ name: x, run: make }] }, { name: b, steps: [{ name: x, run: make }] }]",
+        )
+        .unwrap();
+        assert!(PipelineDef::from_value(&value).is_ok());
+    }
+
+    #[rstest]
+    fn validate_reports_offending_stage_and_step() {
+        let value: Value = serde_yaml::from_str(
+            "stages: [{ name: build, steps: [{ run: make }, { name: '', run: make }] }]",
+        )
+        .unwrap();
+        let err = PipelineDef::from_value(&value).unwrap_err();
+        assert_eq!(
+            "Step #2 of stage `build` has an empty name",
+            err.to_string()
+        );
+    }
+
+    #[rstest]
+    fn validate_checks_steps_of_hooks() {
+        let value: Value = serde_yaml::from_str(
+            "stages: []\non_complete: [{ name: notify, run: a }, { name: notify, run: b }]",
+        )
+        .unwrap();
+        let err = PipelineDef::from_value(&value).unwrap_err();
+        assert_eq!(
+            "Hook `on_complete` contains more than one step named `notify`",
+            err.to_string()
+        );
+    }
+
+    #[rstest]
+    #[case(true, vec!["on_success", "on_complete"])]
+    #[case(false, vec!["on_complete"])]
+    fn hooks_are_selected_by_outcome(#[case] succeeded: bool, #[case] expected: Vec<&str>) {
+        let value: Value =
+            serde_yaml::from_str("stages: []\non_success: [{ run: a }]\non_complete: [{ run: b }]")
+                .unwrap();
+        let def = PipelineDef::from_value(&value).unwrap();
+        let hooks = def.hooks(succeeded);
+        assert_eq!(
+            expected,
+            hooks.iter().map(|(name, _)| *name).collect::<Vec<_>>()
+        );
+    }
+
+    #[rstest]
+    fn from_value_parses_settings_at_every_level() {
+        let value: Value = serde_yaml::from_str(
+            r#"
+            defaults:
+                shell: "bash -c"
+                env: { A: "1" }
+            stages:
+                - name: "build"
+                  workdir: "src"
+                  steps:
+                    - run: "make"
+                      timeout_secs: 10
+                      env: { B: "2" }
+        "#,
+        )
+        .unwrap();
+        let def = PipelineDef::from_value(&value).unwrap();
+
+        assert_eq!(Some("bash -c"), def.defaults.shell.as_deref());
+        assert_eq!("1", def.defaults.env["A"]);
+        assert_eq!(
+            Some(Path::new("src")),
+            def.stages[0].settings.workdir.as_deref()
+        );
+        assert_eq!(Some(10), def.stages[0].steps[0].settings.timeout_secs);
+        assert_eq!("2", def.stages[0].steps[0].settings.env["B"]);
+    }
+
+    #[rstest]
+    fn overlay_prefers_more_specific_settings() {
+        let env = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let defaults = Settings {
+            shell: Some("bash -c".to_string()),
+            env: env(&[("A", "pipeline"), ("B", "pipeline")]),
+            workdir: Some(PathBuf::from("root")),
+            timeout_secs: Some(60),
+            max_output_bytes: Some(1024),
+        };
+        let stage = Settings {
+            env: env(&[("B", "stage"), ("C", "stage")]),
+            timeout_secs: Some(30),
+            ..Settings::default()
+        };
+        let step = Settings {
+            env: env(&[("C", "step")]),
+            workdir: Some(PathBuf::from("step")),
+            ..Settings::default()
+        };
+
+        assert_eq!(
+            Settings {
+                shell: Some("bash -c".to_string()),
+                env: env(&[("A", "pipeline"), ("B", "stage"), ("C", "step")]),
+                workdir: Some(PathBuf::from("step")),
+                timeout_secs: Some(30),
+                max_output_bytes: Some(1024),
+            },
+            defaults.overlay(&stage).overlay(&step)
+        );
+    }
+
+    #[rstest]
+    fn collect_warnings_returns_nothing_for_known_keys(test_yaml: Value) {
+        assert!(collect_warnings(&test_yaml).is_empty());
+    }
+
+    #[rstest]
+    fn collect_warnings_reports_unknown_keys_at_every_level() {
+        let value: Value = serde_yaml::from_str(
+            r#"
+            stages:
+                - name: "build"
+                  image: "alpine"
+                  steps:
+                    - run: "make"
+                      retry: 3
+                - steps: []
+                  timeout: 5
+            on_failure:
+                - run: "notify"
+                  channel: "ci"
+            version: 2
+            defaults:
+                image: "debian"
+        "#,
+        )
+        .unwrap();
+        let warnings = collect_warnings(&value);
+
+        assert!(warnings.iter().all(Pipeline::is_warning));
+        assert_eq!(
+            vec![
+                "Unknown key `version` in the pipeline is ignored",
+                "Unknown key `image` in pipeline defaults is ignored",
+                "Unknown key `image` in stage `build` is ignored",
+                "Unknown key `retry` in step #1 of stage `build` is ignored",
+                "Unknown key `timeout` in stage #2 is ignored",
+                "Unknown key `channel` in step #1 of hook `on_failure` is ignored",
+            ],
+            warnings.iter().map(ToString::to_string).collect::<Vec<_>>()
+        );
+    }
+
+    #[rstest]
+    fn collect_warnings_accepts_keys_holding_loop_items() {
+        let value: Value = serde_yaml::from_str(
+            "regions: [eu]\nmatrix: { os: [linux] }\nchannels: [ci]\nother: 1\nstages: [{ name: a, steps: [{ run: x, for_each: regions }, { run: y, for_each: matrix.os }] }]\non_failure: [{ run: z, for_each: channels }]",
+        )
+        .unwrap();
+        assert_eq!(
+            vec!["Unknown key `other` in the pipeline is ignored"],
+            collect_warnings(&value)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[rstest]
+    fn expand_for_each_repeats_step_for_inline_items() {
+        let mut value: Value = serde_yaml::from_str(
+            r#"
+            stages:
+                - name: deploy
+                  steps:
+                    - run: make
+                    - name: "deploy ${item}"
+                      run: './deploy.sh ${item} ${TAG} \${item}'
+                      env: { REGION: "${item}" }
+                      for_each: [eu, us]
+                    - run: never
+                      for_each: []
+        "#,
+        )
+        .unwrap();
+        expand_for_each(&mut value).unwrap();
+
+        let expected: Value = serde_yaml::from_str(
+            r#"
+            stages:
+                - name: deploy
+                  steps:
+                    - run: make
+                    - name: "deploy eu"
+                      run: './deploy.sh eu ${TAG} \${item}'
+                      env: { REGION: "eu" }
+                    - name: "deploy us"
+                      run: './deploy.sh us ${TAG} \${item}'
+                      env: { REGION: "us" }
+        "#,
+        )
+        .unwrap();
+        assert_eq!(expected, value);
+    }
+
+    #[rstest]
+    fn expand_for_each_exposes_nested_values_of_items_found_by_path() {
+        let value: Value = serde_yaml::from_str(
+            r#"
+            targets:
+                hosts:
+                    - { name: web, port: 80, tags: [a, b], primary: true }
+                    - { name: db, port: 5432, tags: [c], primary: ~ }
+            stages:
+                - name: deploy
+                  steps:
+                    - name: "${item.name}"
+                      run: "ping ${item.name}:${item.port} ${item.tags.0} ${item.tags.1} ${item.primary} ${item}"
+                      for_each: targets.hosts
+        "#,
+        )
+        .unwrap();
+        let def = PipelineDef::from_value(&value).unwrap();
+
+        let steps = &def.stages[0].steps;
+        assert_eq!(2, steps.len());
+        assert_eq!(Some("web"), steps[0].name.as_deref());
+        assert_eq!("ping web:80 a b true ${item}", steps[0].run);
+        assert_eq!("ping db:5432 c ${item.tags.1}  ${item}", steps[1].run);
+        assert_eq!(None, steps[0].for_each);
+    }
+
+    #[rstest]
+    fn expand_for_each_repeats_steps_of_hooks() {
+        let value: Value = serde_yaml::from_str(
+            r#"
+            channels: [ci, ops]
+            stages: [{ name: a, steps: [{ run: make }] }]
+            on_failure:
+                - name: "notify ${item}"
+                  run: "./notify.sh ${item} ${pipeline.status}"
+                  for_each: channels
+            on_complete:
+                - run: "rm -rf ${item}"
+                  for_each: [out]
+        "#,
+        )
+        .unwrap();
+        let def = PipelineDef::from_value(&value).unwrap();
+
+        let runs = |steps: &[Step]| steps.iter().map(|s| s.run.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "./notify.sh ci ${pipeline.status}",
+                "./notify.sh ops ${pipeline.status}"
+            ],
+            runs(&def.on_failure)
+        );
+        assert_eq!(vec!["rm -rf out"], runs(&def.on_complete));
+
+        let mut value: Value =
+            serde_yaml::from_str("on_success: [{ run: x, for_each: missing }]").unwrap();
+        let err = expand_for_each(&mut value).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Invalid `for_each` of step #1 of hook `on_success`: "));
+    }
+
+    #[rstest]
+    #[case("regions: eu", "for_each: regions", "found string")]
+    #[case("", "for_each: missing", "")]
+    #[case("", "for_each: { a: 1 }", "found mapping")]
+    #[case("", "for_each: 3", "found number")]
+    fn expand_for_each_returns_error_when_items_are_not_sequence(
+        #[case] extra: &str,
+        #[case] for_each: &str,
+        #[case] expected: &str,
+    ) {
+        let yaml = format!(
+            "{}\nstages: [{{ name: deploy, steps: [{{ run: make }}, {{ run: x, {} }}] }}]",
+            extra, for_each
+        );
+        let mut value: Value = serde_yaml::from_str(&yaml).unwrap();
+        let err = expand_for_each(&mut value).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Invalid `for_each` of step #2 of stage `deploy`: "),
+            "{}",
+            err
+        );
+        assert!(err.to_string().ends_with(expected), "{}", err);
+    }
+
+    #[rstest]
+    fn from_value_returns_error_when_expanded_names_are_not_unique() {
+        let value: Value = serde_yaml::from_str(
+            "stages: [{ name: a, steps: [{ name: deploy, run: 'x ${item}', for_each: [1, 2] }] }]",
+        )
+        .unwrap();
+        assert!(PipelineDef::from_value(&value).is_err());
+    }
+
+    #[rstest]
+    fn from_reader_returns_error_when_step_has_loop() {
+        let yaml = "stages: [{ name: a, steps: [{ run: 'x ${item}', for_each: [1, 2] }] }]";
+        let err = PipelineDef::from_reader(yaml.as_bytes()).unwrap_err();
+        assert_eq!(
+            "Step `x ${item}` of stage `a` has a `for_each` loop which was not expanded",
+            err.to_string()
+        );
+    }
+
+    #[rstest]
+    fn load_value_with_warnings_returns_error_when_file_does_not_exist() {
+        assert!(load_value_with_warnings(Path::new("/non/existing/pipeline.yaml")).is_err());
+    }
+
+    #[rstest]
+    fn load_from_file_returns_error_when_file_does_not_exist() {
+        assert!(load_from_file(Path::new("/non/existing/pipeline.yaml")).is_err());
+    }
+}
@@ -0,0 +1,2138 @@
+//! Pipeline execution.
+//!
+//! Stages are executed sequentially in the order of their definition, as are steps within a
+//! stage. A failing step fails its stage: remaining steps of the stage are reported as skipped.
+//! By default the run stops at the first failing stage and remaining stages are reported as
+//! skipped, see [`RunOptions::fail_fast`]. A run can be cancelled from another thread with a
+//! [`CancelToken`].
+
+use crate::error::Pipeline;
+use crate::error::Result;
+use crate::pipeline::{step_label, PipelineDef, Settings, Stage, Step, REDACTED};
+use crate::subst::Placeholder;
+use log::{error, info, warn};
+use serde::Serialize;
+use serde_yaml::Value;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Options controlling a pipeline run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunOptions {
+    /// Log commands instead of executing them. All steps are reported as skipped.
+    pub dry_run: bool,
+    /// Stop at the first failing stage. If disabled, all stages are attempted regardless of
+    /// failures. Enabled by default.
+    pub fail_fast: bool,
+    /// Token cancelling the run. A running command is killed and reported as failed, remaining
+    /// steps and stages are reported as skipped.
+    pub cancel: CancelToken,
+    /// Values masked in commands and captured output of steps in the report, e.g. passwords.
+    /// Each occurrence is replaced with [`REDACTED`].
+    pub secrets: Vec<String>,
+    /// Maximum number of bytes of output kept per stream of a step unless the step sets its own
+    /// limit, see [`StepOutcome::stdout`]. [`DEFAULT_MAX_OUTPUT_BYTES`] by default, `None` means
+    /// no limit.
+    pub max_output_bytes: Option<usize>,
+    /// Scratch directory of the run, see [`ScratchDir`]. Its path is exported to steps as the
+    /// [`SCRATCH_DIR_VAR`] environment variable, unless the pipeline sets the variable itself.
+    pub scratch_dir: Option<PathBuf>,
+    /// Environment variables exported to every step, e.g. given on the command line. Variables
+    /// set by the pipeline, its stages or steps win over these.
+    pub env: BTreeMap<String, String>,
+    /// Copy output of steps to the standard output and error of the process as it is produced,
+    /// in addition to capturing it, see [`StepOutcome::stdout`]. Disabled by default.
+    pub forward_output: bool,
+}
+
+/// Default limit of captured output of a step stream, see [`RunOptions::max_output_bytes`].
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// Maximum length of a line of output reported by [`RunEvent::StepOutputLine`]. Longer lines are
+/// reported in parts.
+pub const MAX_OUTPUT_LINE_BYTES: usize = 8 * 1024;
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            fail_fast: true,
+            cancel: CancelToken::default(),
+            secrets: Vec::new(),
+            max_output_bytes: Some(DEFAULT_MAX_OUTPUT_BYTES),
+            scratch_dir: None,
+            env: BTreeMap::new(),
+            forward_output: false,
+        }
+    }
+}
+
+/// Name of the variable holding the path of the scratch directory of a run.
+pub const SCRATCH_DIR_VAR: &str = "PIPELINE_TMP";
+
+/// Unique temporary directory of a pipeline run.
+///
+/// The directory is created in the system temporary directory and removed, with its content,
+/// when the handle is dropped, including when the run fails or is cancelled. Use
+/// [`ScratchDir::keep`] to retain it, e.g. for debugging.
+#[derive(Debug)]
+pub struct ScratchDir {
+    path: PathBuf,
+    keep: bool,
+}
+
+impl ScratchDir {
+    /// Create a new, empty scratch directory.
+    ///
+    /// # Errors
+    /// The function returns an error if the directory cannot be created.
+    pub fn create() -> Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        loop {
+            let name = format!(
+                "pipeline-{}-{}-{}",
+                std::process::id(),
+                nanos,
+                COUNTER.fetch_add(1, Ordering::SeqCst)
+            );
+            let path = std::env::temp_dir().join(name);
+            match std::fs::create_dir(&path) {
+                Ok(()) => return Ok(Self { path, keep: false }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(Pipeline::new_debug(
+                        &format!("Could not create scratch directory `{}`", path.display()),
+                        &e.to_string(),
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Obtain the path of the directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Retain the directory when the handle is dropped, returning its path.
+    pub fn keep(mut self) -> PathBuf {
+        self.keep = true;
+        self.path.clone()
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        if self.keep {
+            return;
+        }
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            error!(
+                "Could not remove scratch directory `{}`: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Handle cancelling a pipeline run, possibly from another thread.
+///
+/// Clones share the cancellation state, so a clone can be passed in [`RunOptions`] while the
+/// original one is kept to cancel the run. Tokens are equal if they share the state.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Construct a token which is not cancelled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel runs using the token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Check whether the token was cancelled.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl PartialEq for CancelToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CancelToken {}
+
+/// Status of an executed stage or step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Status {
+    /// Executed successfully.
+    Succeeded,
+    /// Executed unsuccessfully.
+    Failed,
+    /// Not executed.
+    Skipped,
+}
+
+/// Outcome of a single step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepOutcome {
+    /// Step name, if any.
+    pub name: Option<String>,
+    /// Command which was run.
+    pub run: String,
+    /// Step status.
+    pub status: Status,
+    /// Exit code of the command, if it ran to completion.
+    pub exit_code: Option<i32>,
+    /// Reason of a failure, if any.
+    pub error: Option<String>,
+    /// Captured standard output of an executed step.
+    ///
+    /// Output is captured and, if [`RunOptions::forward_output`] is enabled, forwarded to the
+    /// standard output of the process at the same time. Output exceeding the limit of the step (see [`Settings::max_output_bytes`] and
+    /// [`RunOptions::max_output_bytes`]) is truncated in the middle: the head and the tail, each
+    /// taking a half of the limit, are kept and separated by a `... [truncated N bytes] ...`
+    /// marker. The same applies to forwarded output, the head being forwarded as it is produced
+    /// and the rest once the command ends. Secrets listed in [`RunOptions::secrets`] are masked
+    /// in the report.
+    pub stdout: Option<String>,
+    /// Captured standard error of an executed step, see [`StepOutcome::stdout`].
+    pub stderr: Option<String>,
+    /// Execution time.
+    pub duration: Duration,
+}
+
+impl StepOutcome {
+    /// Obtain a label identifying the step: its name if given, a name derived from the command
+    /// otherwise (see [`crate::pipeline::derive_step_name`]).
+    #[must_use]
+    pub fn label(&self) -> Cow<'_, str> {
+        step_label(self.name.as_deref(), &self.run)
+    }
+}
+
+/// Outcome of a single stage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageOutcome {
+    /// Stage name.
+    pub name: String,
+    /// Stage status.
+    pub status: Status,
+    /// Outcomes of steps in the order of definition.
+    pub steps: Vec<StepOutcome>,
+    /// Execution time.
+    pub duration: Duration,
+}
+
+/// Outcome of a pipeline run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunReport {
+    /// Outcomes of stages in the order of definition.
+    pub stages: Vec<StageOutcome>,
+    /// Outcomes of hooks run after the stages (see [`crate::pipeline::HOOK_VARS`]) in the order
+    /// of execution, each reported as a stage named after the hook, e.g. `on_failure`.
+    pub hooks: Vec<StageOutcome>,
+}
+
+impl RunReport {
+    /// Check whether no stage failed. Failures of hooks are not taken into account.
+    #[must_use]
+    pub fn succeeded(&self) -> bool {
+        self.stages.iter().all(|s| s.status != Status::Failed)
+    }
+
+    /// Obtain names of failed stages in the order of execution.
+    #[must_use]
+    pub fn failed_stages(&self) -> Vec<&str> {
+        self.stages
+            .iter()
+            .filter(|s| s.status == Status::Failed)
+            .map(|s| s.name.as_str())
+            .collect()
+    }
+
+    /// Obtain counts of stages by status along with the total execution time of the stages.
+    /// Hooks are not taken into account.
+    #[must_use]
+    pub fn summary(&self) -> RunSummary {
+        let count = |status| self.stages.iter().filter(|s| s.status == status).count();
+        RunSummary {
+            succeeded: count(Status::Succeeded),
+            failed: count(Status::Failed),
+            skipped: count(Status::Skipped),
+            duration: self.stages.iter().map(|s| s.duration).sum(),
+        }
+    }
+}
+
+/// Counts of stages of a run by status, see [`RunReport::summary`].
+///
+/// The summary is logged at the end of every run and is displayed as a single line, e.g.
+/// `3 stages: 2 succeeded, 1 failed, 0 skipped in 45.2s`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RunSummary {
+    /// Number of succeeded stages.
+    pub succeeded: usize,
+    /// Number of failed stages.
+    pub failed: usize,
+    /// Number of skipped stages.
+    pub skipped: usize,
+    /// Total execution time of the stages.
+    pub duration: Duration,
+}
+
+impl RunSummary {
+    /// Obtain the total number of stages.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.succeeded + self.failed + self.skipped
+    }
+}
+
+impl std::fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} stage{}: {} succeeded, {} failed, {} skipped in {:.1}s",
+            self.total(),
+            if self.total() == 1 { "" } else { "s" },
+            self.succeeded,
+            self.failed,
+            self.skipped,
+            self.duration.as_secs_f64()
+        )
+    }
+}
+
+/// Predicted status of a node of an [`ExecutionPlan`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlannedStatus {
+    /// The node is going to be executed, provided that no stage before it fails.
+    Run,
+    /// The node is not going to be executed, e.g. in a dry run.
+    Skip,
+    /// Execution of the node depends on the outcome of the stages, e.g. an `on_failure` hook.
+    Conditional,
+}
+
+/// Kind of a node of an [`ExecutionPlan`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanNodeKind {
+    /// A stage of the pipeline.
+    Stage,
+    /// A hook run after the stages, see [`crate::pipeline::HOOK_VARS`].
+    Hook,
+}
+
+/// Step of a node of an [`ExecutionPlan`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlanStep {
+    /// Label of the step, see [`Step::label`].
+    pub label: String,
+    /// Command of the step, with secrets masked.
+    pub run: String,
+    /// Predicted status of the step, the same as of its node.
+    pub status: PlannedStatus,
+}
+
+/// Stage or hook of an [`ExecutionPlan`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlanNode {
+    /// Identifier of the node: `stages.<index>` for stages, as stage names need not be unique,
+    /// and the hook name for hooks, e.g. `on_failure`.
+    pub id: String,
+    /// Name of the stage or the hook.
+    pub name: String,
+    /// Kind of the node.
+    pub kind: PlanNodeKind,
+    /// Identifiers of nodes which are run before this one.
+    pub depends_on: Vec<String>,
+    /// Predicted status of the node.
+    pub status: PlannedStatus,
+    /// Steps in the order of execution.
+    pub steps: Vec<PlanStep>,
+}
+
+/// Graph of stages and hooks of a pipeline as they would be run, see [`PipelineDef::plan`].
+///
+/// Stages depend on the stage before them, hooks on the last stage and `on_complete` also on the
+/// other hooks. Loops of steps are expanded by the time a definition is constructed, so every
+/// item is a separate step. The plan can be serialized, e.g. to JSON, to draw the pipeline before
+/// it runs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExecutionPlan {
+    /// Stages followed by hooks, in the order of execution.
+    pub nodes: Vec<PlanNode>,
+}
+
+/// Stream of standard output or standard error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputStream {
+    /// Standard output.
+    Stdout,
+    /// Standard error.
+    Stderr,
+}
+
+/// Progress event of a pipeline run, see [`PipelineDef::run_events`].
+///
+/// Stages and steps are identified by their names and labels (see [`StepOutcome::label`]).
+/// Stages and steps which are not executed are reported with a finished event only, steps of
+/// a skipped stage are reported as part of the outcome of the stage. Hooks are reported as
+/// stages named after them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunEvent {
+    /// A stage started.
+    StageStarted {
+        /// Stage name.
+        stage: String,
+    },
+    /// A step started.
+    StepStarted {
+        /// Name of the stage of the step.
+        stage: String,
+        /// Step label.
+        step: String,
+    },
+    /// A step printed a line of output. Lines are reported as they are produced, without the
+    /// line terminator, regardless of the output limit. Lines longer than
+    /// [`MAX_OUTPUT_LINE_BYTES`] are split into several events. Secrets are masked.
+    StepOutputLine {
+        /// Name of the stage of the step.
+        stage: String,
+        /// Step label.
+        step: String,
+        /// Stream the line was printed to.
+        stream: OutputStream,
+        /// Line of output.
+        line: String,
+    },
+    /// A step finished or was skipped.
+    StepFinished {
+        /// Name of the stage of the step.
+        stage: String,
+        /// Outcome of the step.
+        outcome: StepOutcome,
+    },
+    /// A stage finished or was skipped.
+    StageFinished {
+        /// Outcome of the stage.
+        outcome: StageOutcome,
+    },
+    /// The run finished. This is the last event of a run.
+    RunFinished {
+        /// Report of the run, as returned by [`PipelineDef::run`].
+        report: RunReport,
+    },
+}
+
+impl PipelineDef {
+    /// Run the pipeline.
+    ///
+    /// On Unix, every step runs in its own process group, which is killed as a whole when the
+    /// step times out or the run is cancelled. Steps therefore do not receive signals sent by a
+    /// terminal, e.g. on Ctrl-C, which should cancel the run instead.
+    #[must_use]
+    pub fn run(&self, opts: &RunOptions) -> RunReport {
+        self.run_with_events(opts, None)
+    }
+
+    /// Predict the execution of the pipeline without running anything.
+    ///
+    /// All nodes are predicted to be skipped in a dry run or if the run is already cancelled.
+    /// Otherwise stages are predicted to run, `on_success` and `on_failure` hooks are
+    /// conditional and `on_complete` hooks run. Commands are masked with [`RunOptions::secrets`].
+    #[must_use]
+    pub fn plan(&self, opts: &RunOptions) -> ExecutionPlan {
+        let skip = opts.dry_run || opts.cancel.is_cancelled();
+        let node = |id: String, name: &str, kind, depends_on, status, steps: &[Step]| {
+            let status = if skip { PlannedStatus::Skip } else { status };
+            PlanNode {
+                id,
+                name: name.to_string(),
+                kind,
+                depends_on,
+                status,
+                steps: steps
+                    .iter()
+                    .map(|step| PlanStep {
+                        label: step.label().into_owned(),
+                        run: mask(&step.run, &opts.secrets),
+                        status,
+                    })
+                    .collect(),
+            }
+        };
+
+        let mut nodes: Vec<PlanNode> = Vec::new();
+        for (idx, stage) in self.stages.iter().enumerate() {
+            let depends_on = nodes.last().map(|n| n.id.clone()).into_iter().collect();
+            let id = format!("stages.{}", idx);
+            let stage_node = node(
+                id,
+                &stage.name,
+                PlanNodeKind::Stage,
+                depends_on,
+                PlannedStatus::Run,
+                &stage.steps,
+            );
+            nodes.push(stage_node);
+        }
+        let last_stage: Vec<String> = nodes.last().map(|n| n.id.clone()).into_iter().collect();
+        let mut outcome_hooks = Vec::new();
+        for (name, steps, status) in [
+            ("on_success", &self.on_success, PlannedStatus::Conditional),
+            ("on_failure", &self.on_failure, PlannedStatus::Conditional),
+            ("on_complete", &self.on_complete, PlannedStatus::Run),
+        ] {
+            if steps.is_empty() {
+                continue;
+            }
+            let mut depends_on = last_stage.clone();
+            if name == "on_complete" {
+                depends_on.append(&mut outcome_hooks);
+            } else {
+                outcome_hooks.push(name.to_string());
+            }
+            nodes.push(node(
+                name.to_string(),
+                name,
+                PlanNodeKind::Hook,
+                depends_on,
+                status,
+                steps,
+            ));
+        }
+        ExecutionPlan { nodes }
+    }
+
+    /// Run the pipeline in the background, streaming progress events.
+    ///
+    /// The run works as [`PipelineDef::run`] and starts immediately on a separate thread. Events
+    /// are delivered in the order they occur, the last one being [`RunEvent::RunFinished`] with
+    /// the report of the run, after which the iterator ends. Dropping the iterator does not stop
+    /// the run, use [`RunOptions::cancel`] to cancel it.
+    pub fn run_events(&self, opts: &RunOptions) -> impl Iterator<Item = RunEvent> {
+        let (sender, receiver) = mpsc::channel();
+        let def = self.clone();
+        let opts = opts.clone();
+        std::thread::spawn(move || def.run_with_events(&opts, Some(&sender)));
+        receiver.into_iter()
+    }
+
+    // Run the pipeline, sending events if requested
+    fn run_with_events(&self, opts: &RunOptions, events: Option<&Sender<RunEvent>>) -> RunReport {
+        let mut defaults = Settings {
+            env: opts.env.clone(),
+            ..Settings::default()
+        };
+        if let Some(dir) = &opts.scratch_dir {
+            let dir = dir.to_string_lossy().into_owned();
+            defaults.env.insert(SCRATCH_DIR_VAR.to_string(), dir);
+        }
+        let defaults = defaults.overlay(&self.defaults);
+
+        let mut failed = false;
+        let stages = self
+            .stages
+            .iter()
+            .map(|stage| {
+                let outcome =
+                    if (failed && opts.fail_fast) || opts.dry_run || opts.cancel.is_cancelled() {
+                        skip_stage(stage, opts)
+                    } else {
+                        run_stage(stage, &defaults, opts, events, false)
+                    };
+                failed |= outcome.status == Status::Failed;
+                emit(events, || RunEvent::StageFinished {
+                    outcome: outcome.clone(),
+                });
+                outcome
+            })
+            .collect();
+
+        let mut report = RunReport {
+            stages,
+            hooks: Vec::new(),
+        };
+        if !report.succeeded() {
+            error!("Failed stages: {}", report.failed_stages().join(", "));
+        }
+
+        let vars = hook_vars(&report);
+        for (name, steps) in self.hooks(report.succeeded()) {
+            let hook = Stage {
+                name: name.to_string(),
+                steps: steps.iter().map(|step| hook_step(step, &vars)).collect(),
+                ..Stage::default()
+            };
+            let outcome = if opts.dry_run || opts.cancel.is_cancelled() {
+                skip_stage(&hook, opts)
+            } else {
+                run_stage(&hook, &defaults, opts, events, true)
+            };
+            emit(events, || RunEvent::StageFinished {
+                outcome: outcome.clone(),
+            });
+            report.hooks.push(outcome);
+        }
+        info!("{}", report.summary());
+        emit(events, || RunEvent::RunFinished {
+            report: report.clone(),
+        });
+        report
+    }
+}
+
+// Obtain values of variables describing the outcome of a run for hooks
+fn hook_vars(report: &RunReport) -> BTreeMap<String, String> {
+    let status = if report.succeeded() {
+        "succeeded"
+    } else {
+        "failed"
+    };
+    let failed_stage = report.failed_stages().first().copied().unwrap_or_default();
+    BTreeMap::from([
+        ("pipeline.status".to_string(), status.to_string()),
+        (
+            "pipeline.failed_stage".to_string(),
+            failed_stage.to_string(),
+        ),
+    ])
+}
+
+// Substitute variables describing the outcome of a run in a hook step. Other placeholders are
+// kept and escapes are removed, so hooks of a document should be substituted partially beforehand
+fn hook_step(step: &Step, vars: &BTreeMap<String, String>) -> Step {
+    let substitute = |text: &str| {
+        let mut value = Value::from(text);
+        match Placeholder::default().substitute(&mut value, vars) {
+            Ok(()) => value.as_str().unwrap_or(text).to_string(),
+            Err(_) => text.to_string(),
+        }
+    };
+    let mut step = step.clone();
+    step.run = substitute(&step.run);
+    step.name = step.name.as_deref().map(substitute);
+    for value in step.settings.env.values_mut() {
+        *value = substitute(value);
+    }
+    step
+}
+
+// Send an event if events are requested. A receiver which is gone is ignored
+fn emit(events: Option<&Sender<RunEvent>>, event: impl FnOnce() -> RunEvent) {
+    if let Some(sender) = events {
+        let _ = sender.send(event());
+    }
+}
+
+// Create an outcome of a step which was not executed
+fn skipped_step(step: &Step, opts: &RunOptions) -> StepOutcome {
+    StepOutcome {
+        name: step.name.clone(),
+        // Commands may contain substituted secrets, which must not leak into reports
+        run: mask(&step.run, &opts.secrets),
+        status: Status::Skipped,
+        exit_code: None,
+        error: None,
+        stdout: None,
+        stderr: None,
+        duration: Duration::ZERO,
+    }
+}
+
+// Report a stage as not executed
+fn skip_stage(stage: &Stage, opts: &RunOptions) -> StageOutcome {
+    if opts.dry_run {
+        info!("Would run stage `{}`", stage.name);
+        for step in &stage.steps {
+            info!("Would run `{}`", mask(&step.run, &opts.secrets));
+        }
+    } else {
+        info!("Skipping stage `{}`", stage.name);
+    }
+    StageOutcome {
+        name: stage.name.clone(),
+        status: Status::Skipped,
+        steps: stage
+            .steps
+            .iter()
+            .map(|step| skipped_step(step, opts))
+            .collect(),
+        duration: Duration::ZERO,
+    }
+}
+
+// Execute all steps of a stage, stopping at the first failure. All steps of a hook are executed
+// and its failure is only warned about
+fn run_stage(
+    stage: &Stage,
+    defaults: &Settings,
+    opts: &RunOptions,
+    events: Option<&Sender<RunEvent>>,
+    hook: bool,
+) -> StageOutcome {
+    info!("Running stage `{}`", stage.name);
+    emit(events, || RunEvent::StageStarted {
+        stage: stage.name.clone(),
+    });
+    let start = Instant::now();
+    let mut status = Status::Succeeded;
+    let steps = stage
+        .steps
+        .iter()
+        .map(|step| {
+            let outcome = if (status == Status::Failed && !hook) || opts.cancel.is_cancelled() {
+                skipped_step(step, opts)
+            } else {
+                let settings = defaults.overlay(&stage.settings).overlay(&step.settings);
+                run_step(step, &settings, opts, &stage.name, events)
+            };
+            if outcome.status == Status::Failed {
+                status = Status::Failed;
+            }
+            emit(events, || RunEvent::StepFinished {
+                stage: stage.name.clone(),
+                outcome: outcome.clone(),
+            });
+            outcome
+        })
+        .collect();
+
+    if status == Status::Failed && hook {
+        warn!("Hook `{}` failed", stage.name);
+    } else if status == Status::Failed {
+        error!("Stage `{}` failed", stage.name);
+    }
+    StageOutcome {
+        name: stage.name.clone(),
+        status,
+        steps,
+        duration: start.elapsed(),
+    }
+}
+
+// Execute a single step in a shell, capturing its output
+fn run_step(
+    step: &Step,
+    settings: &Settings,
+    opts: &RunOptions,
+    stage_name: &str,
+    events: Option<&Sender<RunEvent>>,
+) -> StepOutcome {
+    let mut outcome = skipped_step(step, opts);
+    // The label of an unnamed step is its command, which may contain secrets
+    info!("Running step `{}`", outcome.label());
+    let lines = events
+        .map(|sender| LineSink::new(sender.clone(), stage_name, &outcome.label(), &opts.secrets));
+    emit(events, || RunEvent::StepStarted {
+        stage: stage_name.to_string(),
+        step: outcome.label().into_owned(),
+    });
+    let start = Instant::now();
+
+    let timeout = settings.timeout_secs.map(Duration::from_secs);
+    let max_output_bytes = settings.max_output_bytes.or(opts.max_output_bytes);
+    let result = step_command(&step.run, settings).and_then(|cmd| {
+        let limit = max_output_bytes;
+        run_command(cmd, timeout, limit, opts, lines)
+    });
+    outcome.duration = start.elapsed();
+
+    let (ending, output) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            outcome.status = Status::Failed;
+            outcome.error = Some(format!("Could not start command: {}", e));
+            error!("Step `{}` failed: {}", outcome.label(), e);
+            return outcome;
+        }
+    };
+    outcome.stdout = Some(output.stdout.into_text());
+    outcome.stderr = Some(output.stderr.into_text());
+
+    let expected = step.expected_exit.unwrap_or(0);
+    let error = match ending {
+        Ending::Exited(status) => {
+            outcome.exit_code = status.code();
+            (status.code() != Some(expected)).then(|| {
+                status.code().map_or_else(
+                    || "Command was terminated by a signal".to_string(),
+                    |code| format!("Command exited with code {}, expected {}", code, expected),
+                )
+            })
+        }
+        Ending::TimedOut => Some(format!(
+            "Command timed out after {} seconds",
+            settings.timeout_secs.unwrap_or_default()
+        )),
+        Ending::Cancelled => Some("Command was cancelled".to_string()),
+    };
+
+    outcome.status = if error.is_some() {
+        Status::Failed
+    } else {
+        Status::Succeeded
+    };
+    if let Some(err) = &error {
+        error!("Step `{}` failed: {}", outcome.label(), err);
+    }
+    outcome.error = error;
+    outcome
+}
+
+// Construct a command running a script according to step settings
+fn step_command(script: &str, settings: &Settings) -> std::io::Result<Command> {
+    let default_shell = if cfg!(windows) { "cmd /C" } else { "sh -c" };
+    let shell = settings.shell.as_deref().unwrap_or(default_shell);
+    let mut words = shell.split_whitespace();
+    let program = words.next().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "shell must not be empty")
+    })?;
+
+    let mut cmd = Command::new(program);
+    cmd.args(words).arg(script).envs(&settings.env);
+    if let Some(workdir) = &settings.workdir {
+        cmd.current_dir(workdir);
+    }
+    Ok(cmd)
+}
+
+// The way a command ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ending {
+    Exited(ExitStatus),
+    TimedOut,
+    Cancelled,
+}
+
+// Captured output of a command
+#[derive(Debug, Default)]
+struct Output {
+    stdout: OutputBuffer,
+    stderr: OutputBuffer,
+}
+
+// Output of a single stream limited to a number of bytes. When the limit is exceeded, the head
+// and the tail of the output are kept, each taking a half of the limit. Output is forwarded as
+// it comes until the limit is exceeded, the rest is known only when the output ends. Secrets
+// are masked before output is stored, so that truncation cannot cut a secret in half
+#[derive(Debug, Default)]
+struct OutputBuffer {
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    total: usize,
+    forwarded: usize,
+    limit: Option<usize>,
+    secrets: Vec<String>,
+    // Output not masked yet as it might end with the beginning of a secret
+    carry: Vec<u8>,
+    closed: bool,
+}
+
+impl OutputBuffer {
+    fn new(limit: Option<usize>, secrets: &[String]) -> Self {
+        let mut secrets: Vec<_> = secrets.iter().filter(|s| !s.is_empty()).cloned().collect();
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        Self {
+            limit,
+            secrets,
+            ..Self::default()
+        }
+    }
+
+    // Append a chunk of output. Returns the part which can be forwarded immediately
+    fn push(&mut self, chunk: &[u8]) -> Vec<u8> {
+        if self.closed {
+            return Vec::new();
+        }
+        // Every secret starting before the last `longest - 1` bytes is complete, so it is known
+        // whether these bytes start a secret
+        let longest = self.secrets.first().map_or(0, String::len);
+        let bytes = [&self.carry, chunk].concat();
+        let end = bytes.len().saturating_sub(longest.saturating_sub(1));
+        let (mut masked, consumed) = mask_until(&bytes, end, &self.secrets);
+        self.carry = bytes[consumed..].to_vec();
+        let forwarded = self.store(&masked);
+        self.forwarded += forwarded;
+        masked.truncate(forwarded);
+        masked
+    }
+
+    // Store masked output. Returns the number of leading bytes which fit within the limit
+    fn store(&mut self, bytes: &[u8]) -> usize {
+        let start = self.total;
+        self.total += bytes.len();
+        let Some(limit) = self.limit else {
+            self.head.extend_from_slice(bytes);
+            return bytes.len();
+        };
+
+        let (within, beyond) = bytes.split_at(limit.saturating_sub(start).min(bytes.len()));
+        self.head.extend_from_slice(within);
+        if self.total > limit {
+            // The head was allowed to take the whole limit so far, its second half moves to the
+            // tail once the limit is exceeded
+            let head_limit = limit - limit / 2;
+            if self.head.len() > head_limit {
+                self.tail.extend(self.head.drain(head_limit..));
+            }
+            self.tail.extend(beyond);
+            let excess = self.tail.len().saturating_sub(limit / 2);
+            self.tail.drain(..excess);
+        }
+        within.len()
+    }
+
+    // Take the captured output, ignoring any output pushed afterwards
+    fn finish(&mut self) -> Self {
+        let carry = std::mem::take(&mut self.carry);
+        let (masked, _) = mask_until(&carry, carry.len(), &self.secrets);
+        self.store(&masked);
+        self.closed = true;
+        Self {
+            head: std::mem::take(&mut self.head),
+            tail: std::mem::take(&mut self.tail),
+            total: self.total,
+            forwarded: self.forwarded,
+            limit: self.limit,
+            secrets: std::mem::take(&mut self.secrets),
+            carry: Vec::new(),
+            closed: true,
+        }
+    }
+
+    // Part of the output which was not forwarded yet, including a truncation marker if output
+    // between the forwarded part and the tail was dropped
+    fn unforwarded(&self) -> Vec<u8> {
+        let tail_start = self.total - self.tail.len();
+        let mut bytes = self.head.get(self.forwarded..).unwrap_or_default().to_vec();
+        let shown = self.forwarded.max(self.head.len());
+        bytes.extend(marker(tail_start.saturating_sub(shown)).into_bytes());
+        bytes.extend(self.tail.iter().skip(shown.saturating_sub(tail_start)));
+        bytes
+    }
+
+    // Convert the output into text
+    fn into_text(self) -> String {
+        format!(
+            "{}{}{}",
+            String::from_utf8_lossy(&self.head),
+            marker(self.total - self.head.len() - self.tail.len()),
+            String::from_utf8_lossy(&Vec::from(self.tail)),
+        )
+    }
+}
+
+// Marker of truncated output, empty if nothing was truncated
+fn marker(truncated: usize) -> String {
+    if truncated > 0 {
+        format!("\n... [truncated {} bytes] ...\n", truncated)
+    } else {
+        String::new()
+    }
+}
+
+// Splitter of forwarded output into lines reported as events
+#[derive(Debug, Clone)]
+struct LineSink {
+    sender: Sender<RunEvent>,
+    stage: String,
+    step: String,
+    stream: OutputStream,
+    secrets: Vec<String>,
+    pending: Vec<u8>,
+}
+
+impl LineSink {
+    // Create a sink of the standard output of a step
+    fn new(sender: Sender<RunEvent>, stage: &str, step: &str, secrets: &[String]) -> Self {
+        let mut secrets: Vec<_> = secrets.iter().filter(|s| !s.is_empty()).cloned().collect();
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        Self {
+            sender,
+            stage: stage.to_string(),
+            step: step.to_string(),
+            stream: OutputStream::Stdout,
+            secrets,
+            pending: Vec::new(),
+        }
+    }
+
+    // Report complete lines of a chunk of output, keeping an incomplete one for later. Lines
+    // exceeding the length limit are reported in parts, so that output without line breaks is
+    // not accumulated
+    fn push(&mut self, chunk: &[u8]) {
+        self.pending.extend_from_slice(chunk);
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            self.send(&line[..end]);
+        }
+        // A part is cut only once every secret starting in it has been received completely
+        let longest = self.secrets.first().map_or(0, String::len);
+        while self.pending.len() > MAX_OUTPUT_LINE_BYTES + longest {
+            let (part, consumed) = mask_until(&self.pending, MAX_OUTPUT_LINE_BYTES, &self.secrets);
+            self.pending.drain(..consumed);
+            self.send(&part);
+        }
+    }
+
+    // Report the last line of output if it is not terminated
+    fn finish(&mut self) {
+        if !self.pending.is_empty() {
+            let line = std::mem::take(&mut self.pending);
+            self.send(&line);
+        }
+    }
+
+    fn send(&self, line: &[u8]) {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let _ = self.sender.send(RunEvent::StepOutputLine {
+            stage: self.stage.clone(),
+            step: self.step.clone(),
+            stream: self.stream,
+            line: mask(&String::from_utf8_lossy(line), &self.secrets),
+        });
+    }
+}
+
+// Mask secrets in raw output up to an index, the longest secret matching at a position being
+// replaced. Returns the masked output and the number of bytes consumed, which may exceed `end`
+// when a secret spans it
+fn mask_until(bytes: &[u8], end: usize, secrets: &[String]) -> (Vec<u8>, usize) {
+    let mut masked = Vec::with_capacity(end);
+    let mut idx = 0;
+    while idx < end {
+        match secrets
+            .iter()
+            .find(|s| bytes[idx..].starts_with(s.as_bytes()))
+        {
+            Some(secret) => {
+                masked.extend_from_slice(REDACTED.as_bytes());
+                idx += secret.len();
+            }
+            None => {
+                masked.push(bytes[idx]);
+                idx += 1;
+            }
+        }
+    }
+    (masked, idx)
+}
+
+// Replace occurrences of secrets in text
+fn mask(text: &str, secrets: &[String]) -> String {
+    let mut text = text.to_string();
+    for secret in secrets.iter().filter(|s| !s.is_empty()) {
+        if text.contains(secret.as_str()) {
+            text = text.replace(secret.as_str(), REDACTED);
+        }
+    }
+    text
+}
+
+// Run a command capturing its output
+fn run_command(
+    mut cmd: Command,
+    timeout: Option<Duration>,
+    max_output_bytes: Option<usize>,
+    opts: &RunOptions,
+    lines: Option<LineSink>,
+) -> std::io::Result<(Ending, Output)> {
+    // Steps lead their own process groups, so that processes they spawn are killed along with them
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+    let mut child = cmd
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Pipes are drained in separate threads so that a chatty command cannot block on them
+    let stdout = Arc::new(Mutex::new(OutputBuffer::new(
+        max_output_bytes,
+        &opts.secrets,
+    )));
+    let stderr = Arc::new(Mutex::new(OutputBuffer::new(
+        max_output_bytes,
+        &opts.secrets,
+    )));
+    let stderr_lines = lines.clone().map(|lines| LineSink {
+        stream: OutputStream::Stderr,
+        ..lines
+    });
+    let readers = [
+        forward(
+            child.stdout.take(),
+            opts.forward_output.then_some(std::io::stdout as fn() -> _),
+            Arc::clone(&stdout),
+            lines,
+        ),
+        forward(
+            child.stderr.take(),
+            opts.forward_output.then_some(std::io::stderr as fn() -> _),
+            Arc::clone(&stderr),
+            stderr_lines,
+        ),
+    ];
+
+    let ending = wait_for_ending(&mut child, timeout, &opts.cancel)?;
+    // Processes spawned by a killed command may still hold the pipes open, so only the output
+    // produced so far is collected instead of waiting for the end of the streams
+    if matches!(ending, Ending::Exited(_)) {
+        for reader in readers {
+            let _ = reader.join();
+        }
+    }
+    let finish = |buffer: &Mutex<OutputBuffer>| {
+        buffer
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .finish()
+    };
+    let output = Output {
+        stdout: finish(&stdout),
+        stderr: finish(&stderr),
+    };
+    if opts.forward_output {
+        let _ = std::io::stdout().write_all(&output.stdout.unforwarded());
+        let _ = std::io::stderr().write_all(&output.stderr.unforwarded());
+    }
+    Ok((ending, output))
+}
+
+// Capture a pipe into a buffer while forwarding it to a stream of the process and reporting its
+// lines, if requested. Nothing is reported once the buffer is finished
+fn forward<R, W>(
+    pipe: Option<R>,
+    stream: Option<fn() -> W>,
+    buffer: Arc<Mutex<OutputBuffer>>,
+    mut lines: Option<LineSink>,
+) -> std::thread::JoinHandle<()>
+where
+    R: Read + Send + 'static,
+    W: Write + 'static,
+{
+    std::thread::spawn(move || {
+        let Some(mut pipe) = pipe else {
+            return;
+        };
+        let mut chunk = [0_u8; 8192];
+        while let Ok(n @ 1..) = pipe.read(&mut chunk) {
+            if let Ok(mut buffer) = buffer.lock() {
+                if let Some(lines) = lines.as_mut().filter(|_| !buffer.closed) {
+                    lines.push(&chunk[..n]);
+                }
+                let shown = buffer.push(&chunk[..n]);
+                if let Some(stream) = stream {
+                    let _ = stream().write_all(&shown);
+                }
+            }
+        }
+        if let Ok(buffer) = buffer.lock() {
+            if let Some(lines) = lines.as_mut().filter(|_| !buffer.closed) {
+                lines.finish();
+            }
+        }
+    })
+}
+
+// Wait for a child process, killing it once the timeout elapses or the run is cancelled
+fn wait_for_ending(
+    child: &mut Child,
+    timeout: Option<Duration>,
+    cancel: &CancelToken,
+) -> std::io::Result<Ending> {
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Ending::Exited(status));
+        }
+        let ending = if cancel.is_cancelled() {
+            Ending::Cancelled
+        } else if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+            Ending::TimedOut
+        } else {
+            std::thread::sleep(Duration::from_millis(10));
+            continue;
+        };
+        kill_process_group(child)?;
+        child.wait()?;
+        return Ok(ending);
+    }
+}
+
+// Kill a command along with processes it spawned, which share its process group unless they
+// created their own
+fn kill_process_group(child: &mut Child) -> std::io::Result<()> {
+    #[cfg(unix)]
+    if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: the call only sends a signal, the group being led by the child not reaped yet
+        if unsafe { libc::kill(-pid, libc::SIGKILL) } == 0 {
+            return Ok(());
+        }
+    }
+    child.kill()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    /* ------------------ */
+    /* ---- Fixtures ---- */
+    /* ------------------ */
+
+    fn step(run: &str) -> Step {
+        Step {
+            run: run.to_string(),
+            ..Step::default()
+        }
+    }
+
+    fn stage(name: &str, steps: &[&str]) -> Stage {
+        Stage {
+            name: name.to_string(),
+            steps: steps.iter().map(|run| step(run)).collect(),
+            ..Stage::default()
+        }
+    }
+
+    // Logger capturing messages of all tests, installed once per test process
+    struct CapturingLogger(Mutex<Vec<String>>);
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            if let Ok(mut messages) = self.0.lock() {
+                messages.push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger(Mutex::new(Vec::new()));
+
+    // Obtain messages logged so far, by every test running concurrently
+    fn logged() -> Vec<String> {
+        static INIT: std::sync::Once = std::sync::Once::new();
+        INIT.call_once(|| {
+            let _ = log::set_logger(&LOGGER);
+            log::set_max_level(log::LevelFilter::Info);
+        });
+        LOGGER.0.lock().unwrap().clone()
+    }
+
+    fn statuses(report: &RunReport) -> Vec<(Status, Vec<Status>)> {
+        report
+            .stages
+            .iter()
+            .map(|s| (s.status, s.steps.iter().map(|s| s.status).collect()))
+            .collect()
+    }
+
+    /* -------------------------- */
+    /* ---- Test definitions ---- */
+    /* -------------------------- */
+
+    #[rstest]
+    fn plan_lists_stages_and_hooks_with_dependencies() {
+        let def = PipelineDef {
+            stages: vec![
+                stage("build", &["make token=s3cret"]),
+                stage("test", &["make test"]),
+            ],
+            on_failure: vec![step("./notify.sh")],
+            on_complete: vec![step("./cleanup.sh")],
+            ..PipelineDef::default()
+        };
+        let opts = RunOptions {
+            secrets: vec!["s3cret".to_string()],
+            ..RunOptions::default()
+        };
+        let plan = def.plan(&opts);
+
+        let summary: Vec<_> = plan
+            .nodes
+            .iter()
+            .map(|n| (n.id.as_str(), n.kind, n.depends_on.join(","), n.status))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "stages.0",
+                    PlanNodeKind::Stage,
+                    String::new(),
+                    PlannedStatus::Run
+                ),
+                (
+                    "stages.1",
+                    PlanNodeKind::Stage,
+                    "stages.0".to_string(),
+                    PlannedStatus::Run
+                ),
+                (
+                    "on_failure",
+                    PlanNodeKind::Hook,
+                    "stages.1".to_string(),
+                    PlannedStatus::Conditional
+                ),
+                (
+                    "on_complete",
+                    PlanNodeKind::Hook,
+                    "stages.1,on_failure".to_string(),
+                    PlannedStatus::Run
+                ),
+            ],
+            summary
+        );
+        assert_eq!(
+            format!("make token={}", REDACTED),
+            plan.nodes[0].steps[0].run
+        );
+
+        let value = serde_yaml::to_value(&plan).unwrap();
+        assert_eq!("conditional", value["nodes"][2]["status"]);
+        assert_eq!("build", value["nodes"][0]["name"]);
+        assert_eq!("hook", value["nodes"][3]["kind"]);
+    }
+
+    #[rstest]
+    fn plan_skips_everything_in_dry_run() {
+        let def = PipelineDef {
+            stages: vec![stage("build", &["make"])],
+            on_success: vec![step("./notify.sh")],
+            ..PipelineDef::default()
+        };
+        let plan = def.plan(&RunOptions {
+            dry_run: true,
+            ..RunOptions::default()
+        });
+
+        assert!(plan.nodes.iter().all(|n| n.status == PlannedStatus::Skip));
+        assert_eq!(PlannedStatus::Skip, plan.nodes[0].steps[0].status);
+    }
+
+    #[rstest]
+    fn run_executes_all_stages_when_steps_succeed() {
+        let def = PipelineDef {
+            stages: vec![stage("a", &["exit 0", "exit 0"]), stage("b", &["exit 0"])],
+            ..PipelineDef::default()
+        };
+        let report = def.run(&RunOptions::default());
+
+        assert!(report.succeeded());
+        assert_eq!(
+            vec![
+                (
+                    Status::Succeeded,
+                    vec![Status::Succeeded, Status::Succeeded]
+                ),
+                (Status::Succeeded, vec![Status::Succeeded]),
+            ],
+            statuses(&report)
+        );
+        assert_eq!(Some(0), report.stages[0].steps[0].exit_code);
+    }
+
+    #[rstest]
+    fn run_stops_at_first_failing_step() {
+        let def = PipelineDef {
+            stages: vec![
+                stage("a", &["exit 0", "exit 3", "exit 0"]),
+                stage("b", &["exit 0"]),
+            ],
+            ..PipelineDef::default()
+        };
+        let report = def.run(&RunOptions::default());
+
+        assert!(!report.succeeded());
+        assert_eq!(
+            vec![
+                (
+                    Status::Failed,
+                    vec![Status::Succeeded, Status::Failed, Status::Skipped]
+                ),
+                (Status::Skipped, vec![Status::Skipped]),
+            ],
+            statuses(&report)
+        );
+        assert_eq!(Some(3), report.stages[0].steps[1].exit_code);
+        assert!(report.stages[0].steps[1].error.is_some());
+        assert_eq!(Some(""), report.stages[0].steps[0].stderr.as_deref());
+        assert_eq!(None, report.stages[0].steps[2].stderr);
+    }
+
+    #[rstest]
+    fn run_captures_output_streams_separately() {
+        let def = PipelineDef {
+            stages: vec![stage("a", &["echo out && echo err 1>&2"])],
+            ..PipelineDef::default()
+        };
+        let report = def.run(&RunOptions::default());
+        let step = &report.stages[0].steps[0];
+
+        assert_eq!(Status::Succeeded, step.status);
+        assert_eq!(Some("out\n"), step.stdout.as_deref());
+        assert_eq!(Some("err\n"), step.stderr.as_deref());
+    }
+
+    #[rstest]
+    fn run_masks_secrets_in_captured_output() {
+        let def = PipelineDef {
+            stages: vec![stage("a", &["echo login hunter2 && echo hunter2 1>&2"])],
+            ..PipelineDef::default()
+        };
+        let report = def.run(&RunOptions {
+            secrets: vec!["hunter2".to_string(), String::new()],
+            ..RunOptions::default()
+        });
+        let step = &report.stages[0].steps[0];
+
+        assert_eq!("echo login *** && echo *** 1>&2", step.run);
+        assert_eq!(Some("login ***\n"), step.stdout.as_deref());
+        assert_eq!(Some("***\n"), step.stderr.as_deref());
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn run_masks_secrets_in_logs(#[case] dry_run: bool) {
+        logged();
+        let secret = format!("log-secret-{}", dry_run);
+        let def = PipelineDef {
+            stages: vec![stage("a", &[&format!("echo {} && exit 3", secret)])],
+            ..PipelineDef::default()
+        };
+        let report = def.run(&RunOptions {
+            dry_run,
+            secrets: vec![secret.clone()],
+            ..RunOptions::default()
+        });
+        assert_eq!(!dry_run, report.stages[0].status == Status::Failed);
+
+        let messages = logged();
+        let expected = if dry_run {
+            "Would run `echo *** && exit 3`"
+        } else {
+            "Step `echo *** && exit 3` failed: Command exited with code 3, expected 0"
+        };
+        assert!(messages.iter().any(|m| m == expected));
+        assert!(messages.iter().all(|m| !m.contains(&secret)));
+    }
+
+    #[rstest]
+    #[case(Some(4), "01\n... [truncated 7 bytes] ...\n9\n")]
+    #[case(Some(5), "012\n... [truncated 6 bytes] ...\n9\n")]
+    #[case(Some(11), "0123456789\n")]
+    #[case(Some(10), "01234\n... [truncated 1 bytes] ...\n6789\n")]
+    #[case(None, "0123456789\n")]
+    fn run_limits_captured_output(#[case] max_output_bytes: Option<usize>, #[case] expected: &str) {
+        let def = PipelineDef {
+            stages: vec![stage("a", &["echo 0123456789"])],
+            ..PipelineDef::default()
+        };
+        let report = def.run(&RunOptions {
+            max_output_bytes,
+            ..RunOptions::default()
+        });
+        assert_eq!(Some(expected), report.stages[0].steps[0].stdout.as_deref());
+    }
+
+    #[rstest]
+    fn output_buffer_forwards_output_within_limit() {
+        let mut buffer = OutputBuffer::new(Some(10), &[]);
+        let mut forwarded = buffer.push(b"01234");
+        forwarded.extend(buffer.push(b"5678"));
+
+        assert_eq!(b"012345678", forwarded.as_slice());
+        assert!(buffer.finish().unforwarded().is_empty());
+    }
+
+    #[rstest]
+    #[case(&["0123456789abcdef"], "0123456789", "\n... [truncated 1 bytes] ...\nbcdef")]
+    #[case(&["01234567", "89abc"], "0123456789", "abc")]
+    #[case(&["0123456789", "abcd", "ef"], "0123456789", "\n... [truncated 1 bytes] ...\nbcdef")]
+    fn output_buffer_releases_tail_beyond_limit(
+        #[case] chunks: &[&str],
+        #[case] expected_forwarded: &str,
+        #[case] expected_unforwarded: &str,
+    ) {
+        let mut buffer = OutputBuffer::new(Some(10), &[]);
+        let mut forwarded = Vec::new();
+        for chunk in chunks {
+            forwarded.extend(buffer.push(chunk.as_bytes()));
+        }
+        let buffer = buffer.finish();
+
+        assert_eq!(expected_forwarded.as_bytes(), forwarded.as_slice());
+        assert_eq!(
+            expected_unforwarded.as_bytes(),
+            buffer.unforwarded().as_slice()
+        );
+        let expected_text = format!(
+            "01234\n... [truncated {} bytes] ...\n{}",
+            buffer.total - 10,
+            &"0123456789abcdef"[buffer.total - 5..buffer.total]
+        );
+        assert_eq!(expected_text, buffer.into_text());
+    }
+
+    #[rstest]
+    #[case(None, &["login hun", "ter2 ok\n"], "login *** ok\n")]
+    #[case(None, &["h", "u", "n", "t", "e", "r", "2"], "***")]
+    #[case(Some(10), &["abchun", "ter2defgh"], "abc**\n... [truncated 1 bytes] ...\ndefgh")]
+    #[case(Some(10), &["0123456789ab", "hunter2"], "01234\n... [truncated 5 bytes] ...\nab***")]
+    fn output_buffer_masks_secrets_split_across_chunks(
+        #[case] limit: Option<usize>,
+        #[case] chunks: &[&str],
+        #[case] expected: &str,
+    ) {
+        let secrets = ["hunter2".to_string(), "ter".to_string()];
+        let mut buffer = OutputBuffer::new(limit, &secrets);
+        let mut shown = Vec::new();
+        for chunk in chunks {
+            shown.extend(buffer.push(chunk.as_bytes()));
+        }
+        let buffer = buffer.finish();
+        shown.extend(buffer.unforwarded());
+
+        assert!(!String::from_utf8_lossy(&shown).contains("hun"));
+        assert!(!String::from_utf8_lossy(&shown).contains("ter"));
+        assert_eq!(expected, buffer.into_text());
+    }
+
+    #[rstest]
+    #[case(MAX_OUTPUT_LINE_BYTES, 1)]
+    #[case(MAX_OUTPUT_LINE_BYTES + 1, 2)]
+    #[case(3 * MAX_OUTPUT_LINE_BYTES + 5, 4)]
+    fn line_sink_splits_lines_exceeding_limit(#[case] len: usize, #[case] expected_parts: usize) {
+        let (sender, receiver) = mpsc::channel();
+        let mut sink = LineSink::new(sender, "a", "b", &[]);
+        sink.push(&vec![b'x'; len]);
+        assert!(sink.pending.len() <= MAX_OUTPUT_LINE_BYTES);
+        sink.finish();
+        drop(sink);
+
+        let parts: Vec<_> = receiver
+            .iter()
+            .map(|event| match event {
+                RunEvent::StepOutputLine { line, .. } => line,
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        assert_eq!(expected_parts, parts.len());
+        assert!(parts.iter().all(|part| part.len() <= MAX_OUTPUT_LINE_BYTES));
+        assert_eq!("x".repeat(len), parts.concat());
+    }
+
+    #[rstest]
+    fn line_sink_masks_secrets_spanning_parts_of_long_line() {
+        let (sender, receiver) = mpsc::channel();
+        let mut sink = LineSink::new(sender, "a", "b", &["hunter2".to_string()]);
+        let mut chunk = vec![b'x'; MAX_OUTPUT_LINE_BYTES - 3];
+        chunk.extend_from_slice(b"hun");
+        sink.push(&chunk);
+        sink.push(b"ter2");
+        sink.push(&vec![b'y'; MAX_OUTPUT_LINE_BYTES]);
+        sink.finish();
+        drop(sink);
+
+        let text: String = receiver
+            .iter()
+            .map(|event| match event {
+                RunEvent::StepOutputLine { line, .. } => line,
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        assert!(!text.contains("hun"));
+        assert!(text.contains(&format!("xxx{}yyy", REDACTED)));
+    }
+
+    #[rstest]
+    fn run_prefers_output_limit_of_step() {
+        let mut def = PipelineDef {
+            stages: vec![stage("a", &["echo 0123456789", "echo 0123456789"])],
+            ..PipelineDef::default()
+        };
+        def.stages[0].settings.max_output_bytes = Some(2);
+        def.stages[0].steps[1].settings.max_output_bytes = Some(1024);
+        let report = def.run(&RunOptions {
+            max_output_bytes: None,
+            ..RunOptions::default()
+        });
+
+        assert_eq!(
+            Some("0\n... [truncated 9 bytes] ...\n\n"),
+            report.stages[0].steps[0].stdout.as_deref()
+        );
+        assert_eq!(
+            Some("0123456789\n"),
+            report.stages[0].steps[1].stdout.as_deref()
+        );
+    }
+
+    #[rstest]
+    fn run_captures_stderr_of_failed_step() {
+        let def = PipelineDef {
+            stages: vec![stage("a", &["echo oops 1>&2 && exit 1"])],
+            ..PipelineDef::default()
+        };
+        let report = def.run(&RunOptions::default());
+
+        assert_eq!(
+            "oops",
+            report.stages[0].steps[0].stderr.as_deref().unwrap().trim()
+        );
+    }
+
+    #[rstest]
+    fn summary_counts_stages_by_status() {
+        let def = PipelineDef {
+            stages: vec![
+                stage("a", &["exit 0"]),
+                stage("b", &["exit 1"]),
+                stage("c", &["exit 0"]),
+            ],
+            ..PipelineDef::default()
+        };
+        let mut report = def.run(&RunOptions::default());
+        let summary = report.summary();
+
+        assert_eq!(
+            (1, 1, 1, 3),
+            (
+                summary.succeeded,
+                summary.failed,
+                summary.skipped,
+                summary.total()
+            )
+        );
+        for stage in &mut report.stages {
+            stage.duration = Duration::from_millis(15_020);
+        }
+        assert_eq!(
+            "3 stages: 1 succeeded, 1 failed, 1 skipped in 45.1s",
+            report.summary().to_string()
+        );
+        report.stages.truncate(1);
+        assert_eq!(
+            "1 stage: 1 succeeded, 0 failed, 0 skipped in 15.0s",
+            report.summary().to_string()
+        );
+    }
+
+    #[rstest]
+    fn run_attempts_all_stages_when_fail_fast_is_disabled() {
+        let def = PipelineDef {
+            stages: vec![
+                stage("a", &["exit 1", "exit 0"]),
+                stage("b", &["exit 0"]),
+                stage("c", &["exit 2"]),
+            ],
+            ..PipelineDef::default()
+        };
+        let report = def.run(&RunOptions {
+            fail_fast: false,
+            ..RunOptions::default()
+        });
+
+        assert!(!report.succeeded());
+        assert_eq!(vec!["a", "c"], report.failed_stages());
+        assert_eq!(
+            vec![
+                (Status::Failed, vec![Status::Failed, Status::Skipped]),
+                (Status::Succeeded, vec![Status::Succeeded]),
+                (Status::Failed, vec![Status::Failed]),
+            ],
+            statuses(&report)
+        );
+    }
+
+    #[rstest]
+    #[case("exit 1", Some(1), Status::Succeeded, None)]
+    #[case(
+        "exit 0",
+        Some(1),
+        Status::Failed,
+        Some("Command exited with code 0, expected 1")
+    )]
+    #[case(
+        "exit 2",
+        Some(1),
+        Status::Failed,
+        Some("Command exited with code 2, expected 1")
+    )]
+    #[case(
+        "exit 3",
+        None,
+        Status::Failed,
+        Some("Command exited with code 3, expected 0")
+    )]
+    #[case("exit 0", Some(0), Status::Succeeded, None)]
+    fn run_compares_exit_code_with_expected_one(
+        #[case] run: &str,
+        #[case] expected_exit: Option<i32>,
+        #[case] status: Status,
+        #[case] error: Option<&str>,
+    ) {
+        let mut def = PipelineDef {
+            stages: vec![stage("a", &[run])],
+            ..PipelineDef::default()
+        };
+        def.stages[0].steps[0].expected_exit = expected_exit;
+        let report = def.run(&RunOptions::default());
+
+        assert_eq!(status, report.stages[0].steps[0].status);
+        assert_eq!(error, report.stages[0].steps[0].error.as_deref());
+    }
+
+    #[rstest]
+    fn run_applies_settings_with_step_precedence() {
+        let mut def = PipelineDef {
+            stages: vec![stage("a", &["test \"$A$B$C\" = pipelinestagestep"])],
+            ..PipelineDef::default()
+        };
+        def.defaults.env = [("A", "pipeline"), ("B", "x"), ("C", "x")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        def.stages[0].settings.env = [("B", "stage"), ("C", "x")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        def.stages[0].steps[0].settings.env = [("C".to_string(), "step".to_string())].into();
+
+        assert!(def.run(&RunOptions::default()).succeeded());
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn run_exports_scratch_dir_unless_set_by_pipeline() {
+        let scratch = ScratchDir::create().unwrap();
+        let mut def = PipelineDef {
+            stages: vec![stage(
+                "a",
+                &["echo \"$PIPELINE_TMP\"; touch \"$PIPELINE_TMP/file\""],
+            )],
+            ..PipelineDef::default()
+        };
+        let opts = RunOptions {
+            scratch_dir: Some(scratch.path().to_path_buf()),
+            ..RunOptions::default()
+        };
+        let report = def.run(&opts);
+
+        assert!(report.succeeded());
+        let expected = format!("{}\n", scratch.path().display());
+        assert_eq!(Some(expected), report.stages[0].steps[0].stdout);
+        assert!(scratch.path().join("file").exists());
+
+        def.defaults.env = [(SCRATCH_DIR_VAR.to_string(), "/own".to_string())].into();
+        def.stages[0].steps[0].run = "echo \"$PIPELINE_TMP\"".to_string();
+        let report = def.run(&opts);
+        assert_eq!(Some("/own\n"), report.stages[0].steps[0].stdout.as_deref());
+    }
+
+    #[rstest]
+    fn run_exports_option_env_unless_set_by_pipeline() {
+        let mut def = PipelineDef {
+            stages: vec![stage("a", &["test \"$A$B\" = optionpipeline"])],
+            ..PipelineDef::default()
+        };
+        def.defaults.env = [("B".to_string(), "pipeline".to_string())].into();
+        let opts = RunOptions {
+            env: [("A", "option"), ("B", "option")]
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..RunOptions::default()
+        };
+
+        assert!(def.run(&opts).succeeded());
+    }
+
+    #[rstest]
+    fn scratch_dir_is_unique_and_removed_when_dropped() {
+        let first = ScratchDir::create().unwrap();
+        let second = ScratchDir::create().unwrap();
+        assert_ne!(first.path(), second.path());
+        assert!(first.path().is_dir());
+
+        let path = first.path().to_path_buf();
+        std::fs::write(path.join("file"), "content").unwrap();
+        drop(first);
+        assert!(!path.exists());
+    }
+
+    #[rstest]
+    fn scratch_dir_is_retained_when_kept() {
+        let path = ScratchDir::create().unwrap().keep();
+        assert!(path.is_dir());
+        std::fs::remove_dir(path).unwrap();
+    }
+
+    #[rstest]
+    fn run_uses_configured_workdir() {
+        let mut def = PipelineDef {
+            stages: vec![stage("a", &["cd"])],
+            ..PipelineDef::default()
+        };
+        def.stages[0].steps[0].settings.workdir = Some(std::env::temp_dir());
+        assert!(def.run(&RunOptions::default()).succeeded());
+
+        def.stages[0].steps[0].settings.workdir = Some("/non/existing/dir".into());
+        assert!(!def.run(&RunOptions::default()).succeeded());
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn run_uses_configured_shell() {
+        let mut def = PipelineDef {
+            stages: vec![stage("a", &["-n", "-c"])],
+            ..PipelineDef::default()
+        };
+        // `echo -n -c` succeeds whereas the default shell fails on an unknown option
+        def.defaults.shell = Some("echo".to_string());
+        assert!(def.run(&RunOptions::default()).succeeded());
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn run_kills_step_after_timeout() {
+        let mut def = PipelineDef {
+            stages: vec![stage("a", &["sleep 5"])],
+            ..PipelineDef::default()
+        };
+        def.stages[0].settings.timeout_secs = Some(0);
+        let start = Instant::now();
+        let report = def.run(&RunOptions::default());
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(Status::Failed, report.stages[0].steps[0].status);
+        assert_eq!(
+            Some("Command timed out after 0 seconds"),
+            report.stages[0].steps[0].error.as_deref()
+        );
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn run_kills_processes_spawned_by_step_when_cancelled() {
+        let marker = std::env::temp_dir().join(format!("pipeline-orphan-{}", std::process::id()));
+        let def = PipelineDef {
+            stages: vec![stage(
+                "a",
+                &[&format!(
+                    "(sleep 1 && touch {}) & sleep 5",
+                    marker.display()
+                )],
+            )],
+            ..PipelineDef::default()
+        };
+        let opts = RunOptions::default();
+        let cancel = opts.cancel.clone();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            cancel.cancel();
+        });
+        let report = def.run(&opts);
+        canceller.join().unwrap();
+        std::thread::sleep(Duration::from_millis(1500));
+
+        assert_eq!(Status::Failed, report.stages[0].steps[0].status);
+        assert!(!marker.exists());
+    }
+
+    #[rstest]
+    fn run_skips_all_steps_when_cancelled_before_start() {
+        let def = PipelineDef {
+            stages: vec![stage("a", &["exit 0"]), stage("b", &["exit 0"])],
+            ..PipelineDef::default()
+        };
+        let opts = RunOptions::default();
+        opts.cancel.cancel();
+        let report = def.run(&opts);
+
+        assert_eq!(
+            vec![
+                (Status::Skipped, vec![Status::Skipped]),
+                (Status::Skipped, vec![Status::Skipped])
+            ],
+            statuses(&report)
+        );
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn run_kills_step_when_cancelled() {
+        let def = PipelineDef {
+            stages: vec![stage("a", &["sleep 5", "exit 0"]), stage("b", &["exit 0"])],
+            ..PipelineDef::default()
+        };
+        let opts = RunOptions::default();
+        let cancel = opts.cancel.clone();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            cancel.cancel();
+        });
+        let start = Instant::now();
+        let report = def.run(&opts);
+        canceller.join().unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(
+            vec![
+                (Status::Failed, vec![Status::Failed, Status::Skipped]),
+                (Status::Skipped, vec![Status::Skipped])
+            ],
+            statuses(&report)
+        );
+        assert_eq!(
+            Some("Command was cancelled"),
+            report.stages[0].steps[0].error.as_deref()
+        );
+    }
+
+    #[rstest]
+    fn cancel_token_clones_share_state() {
+        let token = CancelToken::new();
+        let clone = token.clone();
+        assert_eq!(token, clone);
+        assert_ne!(token, CancelToken::new());
+
+        clone.cancel();
+        assert!(token.is_cancelled());
+    }
+
+    #[rstest]
+    fn run_events_reports_progress_in_order() {
+        let def = PipelineDef {
+            stages: vec![
+                stage(
+                    "a",
+                    &["echo one && echo two 1>&2 && printf 3", "exit 1", "exit 0"],
+                ),
+                stage("b", &["exit 0"]),
+            ],
+            ..PipelineDef::default()
+        };
+        let events: Vec<_> = def.run_events(&RunOptions::default()).collect();
+
+        let line = |stream, line: &str| RunEvent::StepOutputLine {
+            stage: "a".to_string(),
+            step: "echo one && echo two 1>&2 && printf 3".to_string(),
+            stream,
+            line: line.to_string(),
+        };
+        let mut lines: Vec<_> = events
+            .iter()
+            .filter(|e| matches!(e, RunEvent::StepOutputLine { .. }))
+            .cloned()
+            .collect();
+        lines.sort_by_key(|e| matches!(e, RunEvent::StepOutputLine { stream, .. } if *stream == OutputStream::Stderr));
+        assert_eq!(
+            vec![
+                line(OutputStream::Stdout, "one"),
+                line(OutputStream::Stdout, "3"),
+                line(OutputStream::Stderr, "two"),
+            ],
+            lines
+        );
+
+        let kinds: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                RunEvent::StageStarted { stage } => Some(format!("stage started {}", stage)),
+                RunEvent::StepStarted { step, .. } => Some(format!("step started {}", step)),
+                RunEvent::StepOutputLine { .. } => None,
+                RunEvent::StepFinished { outcome, .. } => {
+                    Some(format!("step {:?} {}", outcome.status, outcome.label()))
+                }
+                RunEvent::StageFinished { outcome } => {
+                    Some(format!("stage {:?} {}", outcome.status, outcome.name))
+                }
+                RunEvent::RunFinished { report } => Some(format!("run {}", report.succeeded())),
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                "stage started a",
+                "step started echo one && echo two 1>&2 && printf 3",
+                "step Succeeded echo one && echo two 1>&2 && printf 3",
+                "step started exit 1",
+                "step Failed exit 1",
+                "step Skipped exit 0",
+                "stage Failed a",
+                "stage Skipped b",
+                "run false",
+            ],
+            kinds
+        );
+        assert!(matches!(events.last(), Some(RunEvent::RunFinished { .. })));
+    }
+
+    #[rstest]
+    fn run_events_masks_secrets() {
+        let def = PipelineDef {
+            stages: vec![stage("a", &["echo hunter2"])],
+            ..PipelineDef::default()
+        };
+        let events: Vec<_> = def
+            .run_events(&RunOptions {
+                secrets: vec!["hunter2".to_string()],
+                ..RunOptions::default()
+            })
+            .collect();
+
+        assert_eq!(
+            RunEvent::StepStarted {
+                stage: "a".to_string(),
+                step: "echo ***".to_string(),
+            },
+            events[1]
+        );
+        assert_eq!(
+            RunEvent::StepOutputLine {
+                stage: "a".to_string(),
+                step: "echo ***".to_string(),
+                stream: OutputStream::Stdout,
+                line: "***".to_string(),
+            },
+            events[2]
+        );
+    }
+
+    #[rstest]
+    fn run_executes_hooks_with_outcome_variables() {
+        let def = PipelineDef {
+            stages: vec![stage("build", &["exit 0"]), stage("test", &["exit 1"])],
+            on_success: vec![step("echo success")],
+            on_failure: vec![
+                step("echo ${pipeline.status} ${pipeline.failed_stage}; exit 1"),
+                step("echo ${pipeline.status:-unknown} ${OTHER}"),
+                step("echo '\\${pipeline.status}' ${pipeline.failed_stage:-none}"),
+            ],
+            on_complete: vec![step("echo done")],
+            ..PipelineDef::default()
+        };
+        let report = def.run(&RunOptions {
+            fail_fast: false,
+            ..RunOptions::default()
+        });
+
+        assert!(!report.succeeded());
+        assert_eq!(vec!["test"], report.failed_stages());
+        let hooks: Vec<_> = report.hooks.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(vec!["on_failure", "on_complete"], hooks);
+        assert_eq!(
+            vec![
+                (
+                    Status::Failed,
+                    vec![Status::Failed, Status::Succeeded, Status::Succeeded]
+                ),
+                (Status::Succeeded, vec![Status::Succeeded]),
+            ],
+            report
+                .hooks
+                .iter()
+                .map(|s| (s.status, s.steps.iter().map(|s| s.status).collect()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some("failed test\n"),
+            report.hooks[0].steps[0].stdout.as_deref()
+        );
+        assert_eq!("echo failed ${OTHER}", report.hooks[0].steps[1].run);
+        assert_eq!(
+            "echo '${pipeline.status}' test",
+            report.hooks[0].steps[2].run
+        );
+    }
+
+    #[rstest]
+    fn run_executes_hooks_regardless_of_fail_fast() {
+        let def = PipelineDef {
+            stages: vec![stage("a", &["exit 1"]), stage("b", &["exit 0"])],
+            on_complete: vec![step("exit 0")],
+            ..PipelineDef::default()
+        };
+        let report = def.run(&RunOptions::default());
+
+        assert_eq!(Status::Skipped, report.stages[1].status);
+        assert_eq!(Status::Succeeded, report.hooks[0].status);
+    }
+
+    #[rstest]
+    fn run_succeeds_when_only_hook_fails() {
+        let def = PipelineDef {
+            stages: vec![stage("a", &["exit 0"])],
+            on_success: vec![step("exit 1")],
+            ..PipelineDef::default()
+        };
+        let report = def.run(&RunOptions::default());
+
+        assert!(report.succeeded());
+        assert_eq!(Status::Failed, report.hooks[0].status);
+    }
+
+    #[rstest]
+    fn run_skips_hooks_in_dry_run_mode() {
+        let def = PipelineDef {
+            on_success: vec![step("exit 1")],
+            ..PipelineDef::default()
+        };
+        let report = def.run(&RunOptions {
+            dry_run: true,
+            ..RunOptions::default()
+        });
+
+        assert!(report.succeeded());
+        assert_eq!(Status::Skipped, report.hooks[0].status);
+    }
+
+    #[rstest]
+    fn run_skips_all_steps_in_dry_run_mode() {
+        let def = PipelineDef {
+            stages: vec![stage("a", &["exit 1"])],
+            ..PipelineDef::default()
+        };
+        let report = def.run(&RunOptions {
+            dry_run: true,
+            ..RunOptions::default()
+        });
+
+        assert!(report.succeeded());
+        assert_eq!(
+            vec![(Status::Skipped, vec![Status::Skipped])],
+            statuses(&report)
+        );
+    }
+}
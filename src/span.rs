@@ -0,0 +1,348 @@
+//! Source locations of YAML nodes.
+//!
+//! `serde_yaml` does not expose locations of parsed nodes, so the module performs a lightweight
+//! second pass over the source text to recover them. Paths use the same dotted notation as
+//! [`crate::yutil::walk`], sequence elements being identified by their index.
+
+use crate::error::Pipeline;
+use crate::error::Result;
+use std::collections::HashMap;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+
+/// Location of a node within the source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// Line number, starting from 1.
+    pub line: usize,
+    /// Column number, starting from 1.
+    pub column: usize,
+    /// Byte offset from the beginning of the source text.
+    pub offset: usize,
+}
+
+/// Obtain locations of all nodes within a YAML document.
+///
+/// The returned map is keyed by node paths, the root node having an empty path. Mapping entries
+/// are located at their keys, so the span points at the place where an entry is defined.
+/// Sequence elements are located at the beginning of the element.
+///
+/// Only the first document of a stream is processed. Aliases are reported as single nodes, i.e.
+/// paths reachable only through an alias are not present in the map. Entries with complex
+/// (non-scalar) keys are skipped.
+///
+/// # Errors
+/// The function returns an error if the text is not a valid YAML document.
+pub fn spans_by_path(text: &str) -> Result<HashMap<String, Span>> {
+    let mut collector = EventCollector::default();
+    Parser::new(text.chars())
+        .load(&mut collector, false)
+        .map_err(|e| Pipeline::new_debug("Could not parse YAML input", &e.to_string()))?;
+
+    // Markers count characters, so a lookup table is needed to obtain byte offsets
+    let offsets: Vec<usize> = text
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(text.len()))
+        .collect();
+    let span_of = |marker: &Marker| Span {
+        line: marker.line(),
+        column: marker.col() + 1,
+        offset: offsets[marker.index().min(offsets.len() - 1)],
+    };
+
+    let mut spans = HashMap::new();
+    let first_node = collector
+        .events
+        .iter()
+        .position(|(ev, _)| is_node_start(ev));
+    if let Some(pos) = first_node {
+        let mut walker = SpanWalker {
+            events: &collector.events,
+            pos,
+            spans: &mut spans,
+            span_of: &span_of,
+        };
+        let span = walker.node_span();
+        walker.node(String::new(), Some(span));
+    }
+    Ok(spans)
+}
+
+/// Number of lines shown before and after the offending line by [`snippet`].
+pub const SNIPPET_CONTEXT_LINES: usize = 2;
+
+/// Render lines of source text around a location, with a caret under the column.
+///
+/// Lines are numbered in a gutter, as in compiler diagnostics. Up to [`SNIPPET_CONTEXT_LINES`]
+/// lines are shown before and after the offending line. Line and column numbers start from 1,
+/// as in [`Span`]; a location just past the last line (e.g. an unexpected end of input) is shown
+/// as an empty line.
+///
+/// ```
+/// use pipeline::span::snippet;
+///
+/// let expected = "  |\n1 | name: [a\n  |       ^";
+/// assert_eq!(expected, snippet("name: [a", 1, 7));
+/// ```
+#[must_use]
+pub fn snippet(text: &str, line: usize, column: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let line = line.max(1);
+    let first = line.saturating_sub(SNIPPET_CONTEXT_LINES).max(1);
+    let last = (line + SNIPPET_CONTEXT_LINES).min(lines.len()).max(line);
+    let width = last.to_string().len();
+    let source_line = |number: usize| lines.get(number - 1).copied().unwrap_or_default();
+
+    let mut rendered = vec![format!("{:width$} |", "", width = width)];
+    for number in first..=last {
+        rendered.push(format!(
+            "{:>width$} | {}",
+            number,
+            source_line(number),
+            width = width
+        ));
+        if number == line {
+            // Tabs are kept so that the caret lines up with the offending character
+            let indent: String = source_line(number)
+                .chars()
+                .take(column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let padding = " ".repeat(
+                column
+                    .saturating_sub(1)
+                    .saturating_sub(indent.chars().count()),
+            );
+            rendered.push(format!(
+                "{:width$} | {}{}^",
+                "",
+                indent,
+                padding,
+                width = width
+            ));
+        }
+    }
+    rendered.join("\n")
+}
+
+// Receiver storing all parser events along with their markers
+#[derive(Default)]
+struct EventCollector {
+    events: Vec<(Event, Marker)>,
+}
+
+impl MarkedEventReceiver for EventCollector {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        self.events.push((ev, mark));
+    }
+}
+
+// Check whether an event starts a node
+fn is_node_start(ev: &Event) -> bool {
+    matches!(
+        ev,
+        Event::Scalar(..) | Event::Alias(_) | Event::SequenceStart(_) | Event::MappingStart(_)
+    )
+}
+
+// Join a child segment to a parent path
+fn join(path: &str, segment: &str) -> String {
+    if path.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{}.{}", path, segment)
+    }
+}
+
+// Recursive descent over collected parser events
+struct SpanWalker<'a, F: Fn(&Marker) -> Span> {
+    events: &'a [(Event, Marker)],
+    pos: usize,
+    spans: &'a mut HashMap<String, Span>,
+    span_of: &'a F,
+}
+
+impl<'a, F: Fn(&Marker) -> Span> SpanWalker<'a, F> {
+    // Span of the node starting at the current position
+    fn node_span(&self) -> Span {
+        let (ev, mark) = &self.events[self.pos];
+        // Block mappings are reported after their first key, so the key location is used instead
+        if let (Event::MappingStart(_), Some((next, next_mark))) =
+            (ev, self.events.get(self.pos + 1))
+        {
+            if is_node_start(next) && next_mark.index() < mark.index() {
+                return (self.span_of)(next_mark);
+            }
+        }
+        (self.span_of)(mark)
+    }
+
+    // Process the node at the current position. Spans are not recorded if `span` is `None`
+    fn node(&mut self, path: String, span: Option<Span>) {
+        let Some((ev, _)) = self.events.get(self.pos) else {
+            return;
+        };
+        let record = span.is_some();
+        if let Some(span) = span {
+            self.spans.insert(path.clone(), span);
+        }
+        self.pos += 1;
+
+        match ev {
+            Event::SequenceStart(_) => {
+                let mut idx = 0_usize;
+                while self.pos < self.events.len() && is_node_start(&self.events[self.pos].0) {
+                    let child_span = record.then(|| self.node_span());
+                    self.node(join(&path, &idx.to_string()), child_span);
+                    idx += 1;
+                }
+                self.pos += 1; // SequenceEnd
+            }
+            Event::MappingStart(_) => {
+                while self.pos < self.events.len() && is_node_start(&self.events[self.pos].0) {
+                    let (key_ev, key_mark) = &self.events[self.pos];
+                    if let Event::Scalar(key, ..) = key_ev {
+                        let key_span = (self.span_of)(key_mark);
+                        self.pos += 1;
+                        self.node(join(&path, key), record.then_some(key_span));
+                    } else {
+                        // Complex keys are skipped along with their values
+                        self.node(String::new(), None);
+                        self.node(String::new(), None);
+                    }
+                }
+                self.pos += 1; // MappingEnd
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    /* ------------------ */
+    /* ---- Fixtures ---- */
+    /* ------------------ */
+
+    #[fixture]
+    fn test_yaml() -> &'static str {
+        "name: \"John Doe\"\n\
+         cars_owned:\n  \
+           - name: \"Ford Mustang\"\n    \
+             last_inspection: { date: \"2020-01-05\" }\n  \
+           - [ü, x]\n"
+    }
+
+    /* -------------------------- */
+    /* ---- Test definitions ---- */
+    /* -------------------------- */
+
+    #[rstest]
+    #[case("", 1, 1, 0)]
+    #[case("name", 1, 1, 0)]
+    #[case("cars_owned", 2, 1, 17)]
+    #[case("cars_owned.0", 3, 5, 33)]
+    #[case("cars_owned.0.name", 3, 5, 33)]
+    #[case("cars_owned.0.last_inspection", 4, 5, 58)]
+    #[case("cars_owned.0.last_inspection.date", 4, 24, 77)]
+    #[case("cars_owned.1", 5, 5, 102)]
+    #[case("cars_owned.1.0", 5, 6, 103)]
+    #[case("cars_owned.1.1", 5, 9, 107)]
+    fn spans_by_path_locates_nodes(
+        #[case] path: &str,
+        #[case] line: usize,
+        #[case] column: usize,
+        #[case] offset: usize,
+        test_yaml: &str,
+    ) {
+        let spans = spans_by_path(test_yaml).unwrap();
+        assert_eq!(
+            Span {
+                line,
+                column,
+                offset
+            },
+            spans[path]
+        );
+    }
+
+    #[rstest]
+    fn spans_by_path_offset_points_at_node(test_yaml: &str) {
+        let spans = spans_by_path(test_yaml).unwrap();
+        assert!(test_yaml[spans["cars_owned.1.1"].offset..].starts_with('x'));
+        assert!(test_yaml[spans["cars_owned.0.last_inspection.date"].offset..].starts_with("date"));
+    }
+
+    #[rstest]
+    fn spans_by_path_skips_complex_keys() {
+        let spans = spans_by_path("? [a, b]\n: { c: 1 }\nd: 2\n").unwrap();
+        assert_eq!(vec!["", "d"], {
+            let mut keys: Vec<_> = spans.keys().map(String::as_str).collect();
+            keys.sort_unstable();
+            keys
+        });
+    }
+
+    #[rstest]
+    fn spans_by_path_returns_empty_map_for_empty_document() {
+        assert!(spans_by_path("").unwrap().is_empty());
+    }
+
+    #[rstest]
+    fn snippet_shows_surrounding_lines_with_caret(test_yaml: &str) {
+        let expected = "  |
+1 | name: \"John Doe\"
+2 | cars_owned:
+3 |   - name: \"Ford Mustang\"
+  |     ^
+4 |     last_inspection: { date: \"2020-01-05\" }
+5 |   - [ü, x]";
+        assert_eq!(expected, snippet(test_yaml, 3, 5));
+
+        let expected = "  |
+3 |   - name: \"Ford Mustang\"
+4 |     last_inspection: { date: \"2020-01-05\" }
+5 |   - [ü, x]
+  |         ^";
+        assert_eq!(expected, snippet(test_yaml, 5, 9));
+    }
+
+    #[rstest]
+    #[case("a: 1\nb: [\n", 3, 1, "  |\n1 | a: 1\n2 | b: [\n3 | \n  | ^")]
+    #[case("\tkey: [", 1, 7, "  |\n1 | \tkey: [\n  | \t     ^")]
+    #[case("key", 1, 10, "  |\n1 | key\n  |          ^")]
+    #[case("", 1, 1, "  |\n1 | \n  | ^")]
+    fn snippet_handles_edge_locations(
+        #[case] text: &str,
+        #[case] line: usize,
+        #[case] column: usize,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(expected, snippet(text, line, column));
+    }
+
+    #[rstest]
+    fn snippet_widens_gutter_for_long_documents() {
+        let text = (1..=12)
+            .map(|i| format!("k{}: v", i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let expected = "   |
+ 8 | k8: v
+ 9 | k9: v
+10 | k10: v
+   | ^
+11 | k11: v
+12 | k12: v";
+        assert_eq!(expected, snippet(&text, 10, 1));
+    }
+
+    #[rstest]
+    fn spans_by_path_returns_error_when_invalid_document_is_passed() {
+        assert!(spans_by_path("key: [").is_err());
+    }
+}
@@ -0,0 +1,911 @@
+//! Variable substitution.
+//!
+//! String values of a document may refer to variables with `${NAME}` placeholders, e.g.
+//! `run: docker build -t app:${TAG} .`. Variables are looked up with a [`Resolver`]. Placeholders
+//! which cannot be resolved are left untouched, so shell variables defined by the commands
+//! themselves keep working.
+//!
+//! Other placeholder styles, e.g. `%NAME%`, can be configured with [`Placeholder`], which also
+//! describes how delimiters are escaped.
+//!
+//! Leaving unknown placeholders untouched also hides typos, e.g. `${TGA}` instead of `${TAG}`.
+//! In strict mode, enabled with [`Placeholder::strict`], every placeholder must be resolved and
+//! any other one is an error. Recognized forms are:
+//!  - `${NAME}`, where `NAME` is a variable known to the resolver,
+//!  - `${NAME:-default}`, see [`Placeholder`].
+//!
+//! Placeholders meant literally, like shell variables of commands, must be escaped as
+//! `\${NAME}`.
+//!
+//! Variables usually come from two sources: the `vars` block of a pipeline and the process
+//! environment. When both define the same name, the [`Precedence`] policy decides which one is
+//! used. By default explicit `vars` win over the ambient environment, so that e.g. a variable
+//! injected by a CI system cannot silently change the meaning of a pipeline:
+//!
+//! ```
+//! use pipeline::subst::{substitute_str, Precedence};
+//! use std::collections::BTreeMap;
+//!
+//! let vars = BTreeMap::from([("TAG".to_string(), "v1".to_string())]);
+//! let env = BTreeMap::from([("TAG".to_string(), "v2".to_string())]);
+//!
+//! let resolver = Precedence::VarsWin.compose(&vars, &env);
+//! assert_eq!("app:v1", substitute_str("app:${TAG}", &resolver)?);
+//!
+//! let resolver = Precedence::EnvWin.compose(&vars, &env);
+//! assert_eq!("app:v2", substitute_str("app:${TAG}", &resolver)?);
+//! # Ok::<(), pipeline::error::Pipeline>(())
+//! ```
+//!
+//! Substitution splices values into text as they are. For `run` commands this means a value is
+//! interpreted by the shell: a value with spaces, quotes or `;` breaks the command or runs
+//! something else entirely, which matters when values come from untrusted inputs, e.g. branch
+//! names. Wrapping a resolver with [`ShellEscaped`] quotes every resolved value with
+//! [`shell_escape`], so it always becomes a single literal shell word. The price is that values
+//! can no longer carry shell syntax on purpose, e.g. `ARGS="--release --locked"` becomes a single
+//! argument, and that escaping must only be applied to text read by a POSIX shell: other values,
+//! like environment variables or names, would get literal quotes.
+//!
+//! ```
+//! use pipeline::subst::{substitute_str, ShellEscaped};
+//! use std::collections::BTreeMap;
+//!
+//! let vars = BTreeMap::from([("BRANCH".to_string(), "main; rm -rf ~".to_string())]);
+//! let run = substitute_str("git checkout ${BRANCH}", &ShellEscaped(&vars))?;
+//! assert_eq!("git checkout 'main; rm -rf ~'", run);
+//! # Ok::<(), pipeline::error::Pipeline>(())
+//! ```
+//!
+//! Placeholders of commands written with escaping in mind must not be quoted by the command
+//! itself. A placeholder within double quotes, e.g. `echo "${MSG}"` as commonly written for
+//! plain substitution, gets the quotes of the escaped value literally: with `MSG` being
+//! `hello world` the command prints `'hello world'`, quotes included.
+
+use crate::error::Pipeline;
+use crate::error::Result;
+use crate::yutil::{check_depth, get_owned_value_by_path, walk, PathDisplay, DEFAULT_MAX_DEPTH};
+use serde_yaml::{Mapping, Value};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+
+/// Source of variable values.
+pub trait Resolver {
+    /// Obtain value of a variable or `None` if the variable is not defined.
+    fn resolve(&self, name: &str) -> Option<String>;
+}
+
+impl<R: Resolver + ?Sized> Resolver for &R {
+    fn resolve(&self, name: &str) -> Option<String> {
+        (**self).resolve(name)
+    }
+}
+
+impl Resolver for BTreeMap<String, String> {
+    fn resolve(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+}
+
+impl Resolver for HashMap<String, String> {
+    fn resolve(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+}
+
+/// Resolver of process environment variables.
+///
+/// Variables which are not valid unicode are considered undefined.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvResolver;
+
+impl Resolver for EnvResolver {
+    fn resolve(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+}
+
+/// Resolver consulting two resolvers in order, the first one taking precedence.
+#[derive(Debug, Clone, Copy)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: Resolver, B: Resolver> Chain<A, B> {
+    /// Construct a resolver falling back to `second` for variables not defined by `first`.
+    #[must_use]
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A: Resolver, B: Resolver> Resolver for Chain<A, B> {
+    fn resolve(&self, name: &str) -> Option<String> {
+        self.first
+            .resolve(name)
+            .or_else(|| self.second.resolve(name))
+    }
+}
+
+/// Resolver quoting values of another resolver for use in POSIX shell commands, see
+/// [`shell_escape`].
+#[derive(Debug, Clone, Copy)]
+pub struct ShellEscaped<R>(pub R);
+
+impl<R: Resolver> Resolver for ShellEscaped<R> {
+    fn resolve(&self, name: &str) -> Option<String> {
+        self.0
+            .resolve(name)
+            .map(|value| shell_escape(&value).into_owned())
+    }
+}
+
+/// Policy deciding which source wins when a variable is defined both explicitly and in the
+/// environment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Precedence {
+    /// Explicitly defined variables win over the environment. This is the default.
+    #[default]
+    VarsWin,
+    /// Environment variables win over explicitly defined ones.
+    EnvWin,
+}
+
+impl Precedence {
+    /// Compose explicit variables and environment variables into a single resolver.
+    ///
+    /// The environment is passed explicitly so that the composition can be tested without
+    /// touching the process environment, [`EnvResolver`] being used in the real setup.
+    #[must_use]
+    pub fn compose<'a>(
+        self,
+        vars: &'a dyn Resolver,
+        env: &'a dyn Resolver,
+    ) -> Chain<&'a dyn Resolver, &'a dyn Resolver> {
+        match self {
+            Self::VarsWin => Chain::new(vars, env),
+            Self::EnvWin => Chain::new(env, vars),
+        }
+    }
+}
+
+/// Delimiters of placeholders.
+///
+/// Placeholders are `${NAME}` by default, but other styles such as `%NAME%` or `{{NAME}}` can be
+/// used for templates written for other tools. An opening delimiter preceded by a backslash does
+/// not start a placeholder: the backslash is removed and the delimiter is kept literally, e.g.
+/// `\${NAME}` becomes `${NAME}`. An opening delimiter without a matching closing one is an
+/// error, so literal opening delimiters must always be escaped. When both delimiters are the
+/// same, every literal occurrence is an opening delimiter, e.g. `\%NAME\%` becomes `%NAME%` and
+/// `100\%` becomes `100%`.
+///
+/// A placeholder may give a default value after `:-`, e.g. `${TAG:-latest}`, which is used when
+/// the variable is not defined or is empty. The default is taken literally up to the closing
+/// delimiter, so it cannot contain the closing delimiter and placeholders within it are not
+/// substituted, e.g. `${A:-${B}}` becomes the literal `${B}` when `A` is not defined: the default
+/// is `${B`, followed by the remaining `}`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Placeholder {
+    open: String,
+    close: String,
+    strict: bool,
+    keys: bool,
+}
+
+impl Default for Placeholder {
+    fn default() -> Self {
+        Self {
+            open: "${".to_string(),
+            close: "}".to_string(),
+            strict: false,
+            keys: false,
+        }
+    }
+}
+
+impl Placeholder {
+    /// Construct placeholder delimiters.
+    ///
+    /// # Errors
+    /// The function returns an error if any of the delimiters is empty.
+    pub fn new(open: &str, close: &str) -> Result<Self> {
+        if open.is_empty() || close.is_empty() {
+            return Err(Pipeline::new("Placeholder delimiters must not be empty"));
+        }
+        Ok(Self {
+            open: open.to_string(),
+            close: close.to_string(),
+            strict: false,
+            keys: false,
+        })
+    }
+
+    /// Enable or disable strict mode, in which placeholders not known to the resolver are an
+    /// error rather than being left untouched. Escaped delimiters are not placeholders and are
+    /// accepted in strict mode as well.
+    ///
+    /// Strict mode does not apply to [`Placeholder::substitute_partial`], which resolves a subset
+    /// of variables by design.
+    #[must_use]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Enable or disable substitution of string keys of mappings, in addition to values.
+    ///
+    /// Entries keep their position under the substituted key. Two keys of a mapping resolving to
+    /// the same name, e.g. `${ENV}_settings` and `prod_settings` with `ENV=prod`, are an error.
+    #[must_use]
+    pub fn substitute_keys(mut self, keys: bool) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Substitute placeholders in a string.
+    ///
+    /// Placeholders not known to the resolver are left untouched, unless strict mode is enabled.
+    ///
+    /// # Errors
+    /// The function returns an error if a placeholder is not terminated or, in strict mode, is
+    /// not known to the resolver.
+    pub fn substitute_str(&self, text: &str, resolver: &dyn Resolver) -> Result<String> {
+        self.substitute_str_inner(text, resolver, false, None)
+    }
+
+    // Substitute placeholders in a string, keeping escape characters if requested. Names of
+    // unresolved placeholders are collected into `missing` if given, in which case strict mode
+    // does not apply
+    fn substitute_str_inner(
+        &self,
+        text: &str,
+        resolver: &dyn Resolver,
+        keep_escapes: bool,
+        mut missing: Option<&mut Vec<String>>,
+    ) -> Result<String> {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(&self.open) {
+            let after = &rest[start + self.open.len()..];
+            if let Some(prefix) = rest[..start].strip_suffix('\\') {
+                result.push_str(if keep_escapes { &rest[..start] } else { prefix });
+                result.push_str(&self.open);
+                rest = after;
+                continue;
+            }
+            result.push_str(&rest[..start]);
+
+            let end = after
+                .find(&self.close)
+                .ok_or_else(|| Pipeline::new(&format!("Unterminated placeholder in `{}`", text)))?;
+            let content = &after[..end];
+            let resolved = match content.split_once(":-") {
+                // Defaults of unknown variables are not applied by partial substitution, as the
+                // variable may still be resolved by a later one
+                Some((name, default)) => match resolver.resolve(name) {
+                    Some(value) if value.is_empty() => Some(default.to_string()),
+                    Some(value) => Some(value),
+                    None => (!keep_escapes).then(|| default.to_string()),
+                },
+                None => resolver.resolve(content),
+            };
+            match resolved {
+                Some(value) => {
+                    result.push_str(&value);
+                    rest = &after[end + self.close.len()..];
+                }
+                None => {
+                    if let Some(missing) = missing.as_mut() {
+                        missing.push(content.to_string());
+                    } else if self.strict && !keep_escapes {
+                        return Err(Pipeline::new(&format!(
+                            "Unknown variable `{}` in `{}`",
+                            content, text
+                        )));
+                    }
+                    // Scanning is resumed right after the delimiter, as unresolved text might
+                    // contain another placeholder e.g. when the delimiters are the same
+                    result.push_str(&self.open);
+                    rest = after;
+                }
+            }
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+
+    /// Substitute placeholders in all string values of a document.
+    ///
+    /// Non-string scalars are not modified, neither are mapping keys unless enabled with
+    /// [`Placeholder::substitute_keys`].
+    ///
+    /// # Errors
+    /// The function returns an error if a placeholder is not terminated, is not known to the
+    /// resolver in strict mode, substituted keys collide, or the document is nested deeper than
+    /// [`DEFAULT_MAX_DEPTH`]. The document may be partially substituted in such a case.
+    pub fn substitute(&self, value: &mut Value, resolver: &dyn Resolver) -> Result<()> {
+        self.substitute_with_max_depth(value, resolver, DEFAULT_MAX_DEPTH)
+    }
+
+    /// Substitute placeholders in all string values of a document nested at most `max_depth`
+    /// levels deep.
+    ///
+    /// The function works as [`Placeholder::substitute`] with a custom depth limit, the root
+    /// node having depth 0. Substituted values are not substituted again, so variables referring
+    /// to each other cannot cause an endless loop.
+    ///
+    /// # Errors
+    /// The function returns an error if a placeholder is not terminated or the document is nested
+    /// deeper than `max_depth`. The document may be partially substituted in such a case.
+    pub fn substitute_with_max_depth(
+        &self,
+        value: &mut Value,
+        resolver: &dyn Resolver,
+        max_depth: usize,
+    ) -> Result<()> {
+        self.substitute_inner(value, resolver, 0, max_depth, false)
+    }
+
+    /// Substitute placeholders known to the resolver in all string values of a document, keeping
+    /// everything else verbatim.
+    ///
+    /// Unlike [`Placeholder::substitute`], escape characters of escaped delimiters are kept, so
+    /// the document can be substituted once more with another resolver and produce the same
+    /// result as if both resolvers were applied at once. This allows resolving a subset of
+    /// variables early, e.g. loop variables of [`crate::pipeline::expand_for_each`].
+    ///
+    /// # Errors
+    /// The function returns an error if a placeholder is not terminated or the document is nested
+    /// deeper than [`DEFAULT_MAX_DEPTH`]. The document may be partially substituted in such a
+    /// case.
+    pub fn substitute_partial(&self, value: &mut Value, resolver: &dyn Resolver) -> Result<()> {
+        self.substitute_inner(value, resolver, 0, DEFAULT_MAX_DEPTH, true)
+    }
+
+    /// Find placeholders of a document which the resolver cannot resolve.
+    ///
+    /// Every occurrence is reported as a pair of the path of the string value containing it, as
+    /// rendered by [`PathDisplay`], and the variable name, in document order. Escaped delimiters
+    /// are not placeholders and are not reported. Strings with an unterminated placeholder are
+    /// reported up to that placeholder, and nodes nested deeper than [`DEFAULT_MAX_DEPTH`] are
+    /// not inspected; substitution reports both as errors.
+    #[must_use]
+    pub fn unresolved(&self, value: &Value, resolver: &dyn Resolver) -> Vec<(String, String)> {
+        let mut unresolved = Vec::new();
+        let _ = walk(value, &mut |path, node| {
+            let Some(text) = node.as_str().filter(|text| text.contains(&self.open)) else {
+                return;
+            };
+            let mut missing = Vec::new();
+            let _ = self.substitute_str_inner(text, resolver, false, Some(&mut missing));
+            let path = PathDisplay(path).to_string();
+            unresolved.extend(missing.into_iter().map(|name| (path.clone(), name)));
+        });
+        unresolved
+    }
+
+    // Recursive part of the substitution tracking the depth of the current node
+    fn substitute_inner(
+        &self,
+        value: &mut Value,
+        resolver: &dyn Resolver,
+        depth: usize,
+        max_depth: usize,
+        keep_escapes: bool,
+    ) -> Result<()> {
+        check_depth(depth, max_depth)?;
+        let substitute_child =
+            |item| self.substitute_inner(item, resolver, depth + 1, max_depth, keep_escapes);
+        match value {
+            Value::String(s) => {
+                if s.contains(&self.open) {
+                    *s = self.substitute_str_inner(s, resolver, keep_escapes, None)?;
+                }
+                Ok(())
+            }
+            Value::Sequence(seq) => seq.iter_mut().try_for_each(substitute_child),
+            Value::Mapping(map) => {
+                map.iter_mut()
+                    .try_for_each(|(_, item)| substitute_child(item))?;
+                if self.keys {
+                    self.substitute_map_keys(map, resolver, keep_escapes)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Substitute string keys of a mapping, keeping the order of entries. The mapping is not
+    // modified if keys collide
+    fn substitute_map_keys(
+        &self,
+        map: &mut Mapping,
+        resolver: &dyn Resolver,
+        keep_escapes: bool,
+    ) -> Result<()> {
+        let has_placeholder = |key: &Value| key.as_str().is_some_and(|k| k.contains(&self.open));
+        if !map.iter().any(|(key, _)| has_placeholder(key)) {
+            return Ok(());
+        }
+
+        let mut keys = Vec::with_capacity(map.len());
+        let mut origins = HashMap::new();
+        for (key, _) in map.iter() {
+            let new = match key.as_str() {
+                Some(text) if has_placeholder(key) => {
+                    Value::String(self.substitute_str_inner(text, resolver, keep_escapes, None)?)
+                }
+                _ => key.clone(),
+            };
+            if let Some(first) = origins.insert(new.clone(), key) {
+                return Err(Pipeline::new(&format!(
+                    "Keys `{}` and `{}` both resolve to `{}`",
+                    first.as_str().unwrap_or_default(),
+                    key.as_str().unwrap_or_default(),
+                    new.as_str().unwrap_or_default()
+                )));
+            }
+            keys.push(new);
+        }
+        *map = std::mem::take(map)
+            .into_iter()
+            .zip(keys)
+            .map(|((_, item), key)| (key, item))
+            .collect();
+        Ok(())
+    }
+}
+
+/// Substitute `${NAME}` placeholders in a string.
+///
+/// See [`Placeholder::substitute_str`].
+///
+/// # Errors
+/// The function returns an error if a placeholder is not terminated.
+pub fn substitute_str(text: &str, resolver: &dyn Resolver) -> Result<String> {
+    Placeholder::default().substitute_str(text, resolver)
+}
+
+/// Substitute `${NAME}` placeholders in all string values of a document.
+///
+/// See [`Placeholder::substitute`].
+///
+/// # Errors
+/// The function returns an error if a placeholder is not terminated or the document is nested
+/// too deeply. The document may be partially substituted in such a case.
+pub fn substitute_with(value: &mut Value, resolver: &dyn Resolver) -> Result<()> {
+    Placeholder::default().substitute(value, resolver)
+}
+
+/// Find `${NAME}` placeholders of a document which the resolver cannot resolve.
+///
+/// See [`Placeholder::unresolved`].
+#[must_use]
+pub fn unresolved_placeholders(value: &Value, resolver: &dyn Resolver) -> Vec<(String, String)> {
+    Placeholder::default().unresolved(value, resolver)
+}
+
+/// Substitute placeholders in a document with explicit variables and the process environment.
+///
+/// Sources are composed according to `precedence`, see [`Precedence::compose`]. Only values are
+/// substituted, use [`Placeholder::substitute_keys`] to substitute mapping keys as well.
+///
+/// # Errors
+/// The function returns an error if a placeholder is not terminated or the document is nested
+/// too deeply.
+pub fn substitute_vars(
+    value: &mut Value,
+    vars: &BTreeMap<String, String>,
+    precedence: Precedence,
+) -> Result<()> {
+    substitute_with(value, &precedence.compose(vars, &EnvResolver))
+}
+
+/// Obtain an owned copy of a YAML value found by a path with placeholders substituted.
+///
+/// The input object is not modified, which allows resolving different parts of a document with
+/// different variable scopes, e.g. with per-stage overrides.
+///
+/// # Errors
+/// The function returns an error in case specified path was not found inside an input object or
+/// a placeholder within the obtained value is not terminated.
+pub fn get_resolved_owned(value: &Value, path: &str, resolver: &dyn Resolver) -> Result<Value> {
+    let mut resolved = get_owned_value_by_path(value, path)?;
+    substitute_with(&mut resolved, resolver)?;
+    Ok(resolved)
+}
+
+/// Quote text so that a POSIX shell reads it as a single literal word.
+///
+/// Text consisting only of characters without a special meaning to the shell, e.g. `v1.2` or
+/// `/usr/bin`, is returned as it is. Other text is enclosed in single quotes, within which the
+/// shell interprets nothing, and single quotes of the text are written as `'\''`. Empty text
+/// becomes `''`. The quoting is not suitable for `cmd` on Windows.
+#[must_use]
+pub fn shell_escape(text: &str) -> Cow<'_, str> {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-./=:,+@%".contains(c);
+    if !text.is_empty() && text.chars().all(safe) {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(format!("'{}'", text.replace('\'', r"'\''")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    /* ------------------ */
+    /* ---- Fixtures ---- */
+    /* ------------------ */
+
+    fn map(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[fixture]
+    fn vars() -> BTreeMap<String, String> {
+        map(&[("TAG", "v1"), ("ONLY_VARS", "vars")])
+    }
+
+    #[fixture]
+    fn env() -> BTreeMap<String, String> {
+        map(&[("TAG", "v2"), ("ONLY_ENV", "env")])
+    }
+
+    /* -------------------------- */
+    /* ---- Test definitions ---- */
+    /* -------------------------- */
+
+    #[rstest]
+    #[case("plain", "plain")]
+    #[case("${TAG}", "v1")]
+    #[case("app:${TAG}-${ONLY_VARS}!", "app:v1-vars!")]
+    #[case("${MISSING} ${TAG}", "${MISSING} v1")]
+    #[case("$TAG $ {TAG} $", "$TAG $ {TAG} $")]
+    #[case("${}", "${}")]
+    fn substitute_str_replaces_known_placeholders(
+        #[case] text: &str,
+        #[case] expected: &str,
+        vars: BTreeMap<String, String>,
+    ) {
+        assert_eq!(expected, substitute_str(text, &vars).unwrap());
+    }
+
+    #[rstest]
+    #[case("v1.2", "v1.2")]
+    #[case("/usr/local/bin:-x,y=z+@%", "/usr/local/bin:-x,y=z+@%")]
+    #[case("", "''")]
+    #[case("two words", "'two words'")]
+    #[case("$(reboot)", "'$(reboot)'")]
+    #[case("it's", r"'it'\''s'")]
+    #[case("a\nb", "'a\nb'")]
+    #[case("żółć", "'żółć'")]
+    fn shell_escape_quotes_special_characters(#[case] text: &str, #[case] expected: &str) {
+        assert_eq!(expected, shell_escape(text));
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    #[case("it's a \"test\" $HOME `id` \\ ;|&")]
+    #[case("")]
+    fn shell_escape_is_read_back_literally_by_shell(#[case] text: &str) {
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("printf %s {}", shell_escape(text)))
+            .output()
+            .unwrap();
+        assert_eq!(text, String::from_utf8(output.stdout).unwrap());
+    }
+
+    #[rstest]
+    fn shell_escaped_quotes_resolved_values_only() {
+        let vars = map(&[("MSG", "hello world")]);
+        let resolver = ShellEscaped(&vars);
+        assert_eq!(
+            "echo 'hello world' ${MISSING} default",
+            substitute_str("echo ${MSG} ${MISSING} ${MISSING:-default}", &resolver).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case("${TAG:-latest}", "v1")]
+    #[case("${MISSING:-latest}", "latest")]
+    #[case("${EMPTY:-latest}", "latest")]
+    #[case("${MISSING:-}", "")]
+    #[case("${MISSING:-a:-b c}", "a:-b c")]
+    #[case("${MISSING:-${TAG}}", "${TAG}")]
+    #[case("\\${MISSING:-latest}", "${MISSING:-latest}")]
+    fn substitute_str_uses_default_when_variable_is_unset_or_empty(
+        #[case] text: &str,
+        #[case] expected: &str,
+        mut vars: BTreeMap<String, String>,
+    ) {
+        vars.insert("EMPTY".to_string(), String::new());
+        let placeholder = Placeholder::default().strict(true);
+        assert_eq!(expected, placeholder.substitute_str(text, &vars).unwrap());
+    }
+
+    #[rstest]
+    fn substitute_partial_keeps_placeholders_with_defaults(mut vars: BTreeMap<String, String>) {
+        vars.insert("EMPTY".to_string(), String::new());
+        let mut value = Value::from("${TAG:-latest} ${MISSING:-latest} ${EMPTY:-latest}");
+        Placeholder::default()
+            .substitute_partial(&mut value, &vars)
+            .unwrap();
+        assert_eq!(Value::from("v1 ${MISSING:-latest} latest"), value);
+    }
+
+    #[rstest]
+    #[case("\\${TAG}", "${TAG}")]
+    #[case("\\${TAG} ${TAG}", "${TAG} v1")]
+    #[case("\\\\${TAG}", "\\${TAG}")]
+    #[case("a\\b ${TAG}", "a\\b v1")]
+    fn substitute_str_keeps_escaped_delimiters(
+        #[case] text: &str,
+        #[case] expected: &str,
+        vars: BTreeMap<String, String>,
+    ) {
+        assert_eq!(expected, substitute_str(text, &vars).unwrap());
+    }
+
+    #[rstest]
+    #[case("%", "%", "app:%TAG%", "app:v1")]
+    #[case("%", "%", "100\\% %MISSING% %TAG%", "100% %MISSING% v1")]
+    #[case("%", "%", "\\%TAG\\%", "%TAG%")]
+    #[case("{{", "}}", "{{TAG}}-{{ONLY_VARS}} ${TAG}", "v1-vars ${TAG}")]
+    #[case("{{", "}}", "\\{{TAG}}", "{{TAG}}")]
+    fn substitute_str_supports_custom_delimiters(
+        #[case] open: &str,
+        #[case] close: &str,
+        #[case] text: &str,
+        #[case] expected: &str,
+        vars: BTreeMap<String, String>,
+    ) {
+        let placeholder = Placeholder::new(open, close).unwrap();
+        assert_eq!(expected, placeholder.substitute_str(text, &vars).unwrap());
+    }
+
+    #[rstest]
+    #[case("", "}")]
+    #[case("%", "")]
+    fn placeholder_returns_error_when_delimiter_is_empty(#[case] open: &str, #[case] close: &str) {
+        assert!(Placeholder::new(open, close).is_err());
+    }
+
+    #[rstest]
+    fn substitute_with_custom_delimiters_returns_error_when_placeholder_is_not_terminated(
+        vars: BTreeMap<String, String>,
+    ) {
+        let placeholder = Placeholder::new("%", "%").unwrap();
+        assert!(placeholder.substitute_str("100% done", &vars).is_err());
+    }
+
+    #[rstest]
+    #[case("${TAG} \\${shell_var}", "v1 ${shell_var}")]
+    #[case("plain", "plain")]
+    fn substitute_str_in_strict_mode_accepts_known_and_escaped_placeholders(
+        #[case] text: &str,
+        #[case] expected: &str,
+        vars: BTreeMap<String, String>,
+    ) {
+        let placeholder = Placeholder::default().strict(true);
+        assert_eq!(expected, placeholder.substitute_str(text, &vars).unwrap());
+    }
+
+    #[rstest]
+    fn substitute_in_strict_mode_returns_error_when_variable_is_unknown(
+        vars: BTreeMap<String, String>,
+    ) {
+        let mut value: Value =
+            serde_yaml::from_str("run: ./build ${TAG} ${fil:script.sh}").unwrap();
+        let err = Placeholder::default()
+            .strict(true)
+            .substitute(&mut value, &vars)
+            .unwrap_err();
+        assert_eq!(
+            "Unknown variable `fil:script.sh` in `./build ${TAG} ${fil:script.sh}`",
+            err.to_string()
+        );
+    }
+
+    #[rstest]
+    fn substitute_partial_ignores_strict_mode(vars: BTreeMap<String, String>) {
+        let mut value = Value::from("${TAG} ${MISSING}");
+        Placeholder::default()
+            .strict(true)
+            .substitute_partial(&mut value, &vars)
+            .unwrap();
+        assert_eq!(Value::from("v1 ${MISSING}"), value);
+    }
+
+    #[rstest]
+    fn unresolved_placeholders_lists_occurrences_with_paths(vars: BTreeMap<String, String>) {
+        let value: Value = serde_yaml::from_str(
+            r#"
+            image: app:${TAG}
+            stages:
+              - steps:
+                  - run: deploy ${REGION} ${TAG} ${REGION}
+                  - run: echo \${HOME} ${MISSING
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                ("stages.0.steps.0.run".to_string(), "REGION".to_string()),
+                ("stages.0.steps.0.run".to_string(), "REGION".to_string()),
+            ],
+            unresolved_placeholders(&value, &vars)
+        );
+    }
+
+    #[rstest]
+    fn unresolved_placeholders_supports_custom_delimiters(vars: BTreeMap<String, String>) {
+        let value = Value::from("{{TAG}} {{NAME}} ${OTHER}");
+        let placeholder = Placeholder::new("{{", "}}").unwrap();
+        assert_eq!(
+            vec![(String::new(), "NAME".to_string())],
+            placeholder.unresolved(&value, &vars)
+        );
+    }
+
+    #[rstest]
+    fn substitute_keys_rewrites_keys_in_place(vars: BTreeMap<String, String>) {
+        let mut value: Value = serde_yaml::from_str(
+            "first: 1\n${TAG}_settings:\n  ${ONLY_VARS}: ${TAG}\n5: x\nlast: \\${TAG}",
+        )
+        .unwrap();
+        Placeholder::default()
+            .substitute_keys(true)
+            .substitute(&mut value, &vars)
+            .unwrap();
+
+        let expected: Value =
+            serde_yaml::from_str("first: 1\nv1_settings: { vars: v1 }\n5: x\nlast: ${TAG}")
+                .unwrap();
+        assert_eq!(expected, value);
+        let keys: Vec<_> = value.as_mapping().unwrap().iter().map(|(k, _)| k).collect();
+        assert_eq!(Value::from("v1_settings"), *keys[1]);
+    }
+
+    #[rstest]
+    fn substitute_keys_returns_error_when_keys_collide(vars: BTreeMap<String, String>) {
+        let original: Value = serde_yaml::from_str("v1_settings: 1\n${TAG}_settings: 2").unwrap();
+        let mut value = original.clone();
+        let err = Placeholder::default()
+            .substitute_keys(true)
+            .substitute(&mut value, &vars)
+            .unwrap_err();
+
+        assert_eq!(
+            "Keys `v1_settings` and `${TAG}_settings` both resolve to `v1_settings`",
+            err.to_string()
+        );
+        assert_eq!(original, value);
+    }
+
+    #[rstest]
+    fn substitute_leaves_keys_by_default(vars: BTreeMap<String, String>) {
+        let mut value: Value = serde_yaml::from_str("${TAG}: ${TAG}").unwrap();
+        substitute_with(&mut value, &vars).unwrap();
+        assert_eq!(serde_yaml::from_str::<Value>("${TAG}: v1").unwrap(), value);
+    }
+
+    #[rstest]
+    fn substitute_str_returns_error_when_placeholder_is_not_terminated(
+        vars: BTreeMap<String, String>,
+    ) {
+        let err = substitute_str("app:${TAG", &vars).unwrap_err();
+        assert_eq!("Unterminated placeholder in `app:${TAG`", err.to_string());
+    }
+
+    #[rstest]
+    #[case(Precedence::VarsWin, "v1 vars env")]
+    #[case(Precedence::EnvWin, "v2 vars env")]
+    fn compose_applies_precedence(
+        #[case] precedence: Precedence,
+        #[case] expected: &str,
+        vars: BTreeMap<String, String>,
+        env: BTreeMap<String, String>,
+    ) {
+        let resolver = precedence.compose(&vars, &env);
+        assert_eq!(
+            expected,
+            substitute_str("${TAG} ${ONLY_VARS} ${ONLY_ENV}", &resolver).unwrap()
+        );
+    }
+
+    #[rstest]
+    fn precedence_defaults_to_vars_win() {
+        assert_eq!(Precedence::VarsWin, Precedence::default());
+    }
+
+    #[rstest]
+    fn substitute_with_replaces_nested_string_values(vars: BTreeMap<String, String>) {
+        let mut value: Value =
+            serde_yaml::from_str("'${TAG}': ['${TAG}', { image: 'app:${TAG}', n: 1 }]").unwrap();
+        substitute_with(&mut value, &vars).unwrap();
+
+        let expected: Value =
+            serde_yaml::from_str("'${TAG}': [v1, { image: 'app:v1', n: 1 }]").unwrap();
+        assert_eq!(expected, value);
+    }
+
+    #[rstest]
+    fn substitute_with_max_depth_limits_nesting(vars: BTreeMap<String, String>) {
+        let nested =
+            |depth| (0..depth).fold(Value::from("${TAG}"), |acc, _| Value::Sequence(vec![acc]));
+        let placeholder = Placeholder::default();
+
+        let mut value = nested(2);
+        placeholder
+            .substitute_with_max_depth(&mut value, &vars, 2)
+            .unwrap();
+        assert_eq!(Value::from("v1"), value[0][0]);
+
+        let err = placeholder
+            .substitute_with_max_depth(&mut nested(3), &vars, 2)
+            .unwrap_err();
+        assert_eq!("Maximum nesting depth of 2 exceeded", err.to_string());
+        assert!(substitute_with(&mut nested(DEFAULT_MAX_DEPTH + 1), &vars).is_err());
+    }
+
+    #[rstest]
+    #[case("${item}-${TAG}", "eu-v1")]
+    #[case("\\${item}-\\${TAG}-${item}", "${item}-${TAG}-eu")]
+    #[case("${item}-${UNDEFINED}", "eu-${UNDEFINED}")]
+    fn substitute_partial_matches_substitution_with_both_resolvers(
+        #[case] text: &str,
+        #[case] expected: &str,
+        vars: BTreeMap<String, String>,
+    ) {
+        let items = map(&[("item", "eu")]);
+        let placeholder = Placeholder::default();
+        let mut value = Value::from(text);
+
+        placeholder.substitute_partial(&mut value, &items).unwrap();
+        placeholder.substitute(&mut value, &vars).unwrap();
+        assert_eq!(Value::from(expected), value);
+
+        let mut at_once = Value::from(text);
+        placeholder
+            .substitute(&mut at_once, &Chain::new(&items, &vars))
+            .unwrap();
+        assert_eq!(at_once, value);
+    }
+
+    #[rstest]
+    fn get_resolved_owned_substitutes_copy_of_subtree(vars: BTreeMap<String, String>) {
+        let value: Value =
+            serde_yaml::from_str("build: { image: 'app:${TAG}' }\ntest: { image: 'app:${TAG}' }")
+                .unwrap();
+        let original = value.clone();
+        let overrides = map(&[("TAG", "v3")]);
+
+        let build = get_resolved_owned(&value, "build", &vars).unwrap();
+        let test = get_resolved_owned(&value, "test", &Chain::new(&overrides, &vars)).unwrap();
+
+        assert_eq!(Value::from("app:v1"), build["image"]);
+        assert_eq!(Value::from("app:v3"), test["image"]);
+        assert_eq!(original, value);
+    }
+
+    #[rstest]
+    fn get_resolved_owned_returns_error_when_path_is_not_found(vars: BTreeMap<String, String>) {
+        assert!(get_resolved_owned(&Value::Null, "build", &vars).is_err());
+    }
+
+    #[rstest]
+    fn substitute_vars_resolves_environment_variables() {
+        let mut value = Value::from("${CARGO_PKG_NAME} ${PIPELINE_TEST_UNDEFINED}");
+        substitute_vars(&mut value, &BTreeMap::new(), Precedence::default()).unwrap();
+        assert_eq!(Value::from("pipeline ${PIPELINE_TEST_UNDEFINED}"), value);
+    }
+}
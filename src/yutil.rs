@@ -5,8 +5,13 @@
 
 use crate::error::Pipeline;
 use crate::error::Result;
+use log::warn;
 use serde_yaml::{Mapping, Sequence, Value};
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
 
 /// Trait for converting a generic YAML value into an underlying constituent.
 pub trait FromYaml<'a> {
@@ -24,16 +29,7 @@ pub trait FromYaml<'a> {
     /// # Errors
     /// The function returns an error if the value cannot be represented as the desired type.
     fn try_from(value: &'a Value) -> Result<Self::Output> {
-        match Self::parse(value) {
-            Some(cv) => Ok(cv),
-            None => Err(Pipeline::new_debug(
-                &format!(
-                    "Could not parse requested yaml value as {}",
-                    Self::type_str()
-                ),
-                &format!("Input object: {:?}", value),
-            )),
-        }
+        Self::parse(value).ok_or_else(|| type_mismatch(Self::type_str(), value))
     }
 }
 
@@ -69,17 +65,570 @@ macro_rules! impl_from_yaml_cp {
             }
 
             fn type_str() -> &'static str {
-                concat!("$", stringify!($type))
+                stringify!($type)
             }
         }
     };
 }
 
 impl_from_yaml_cp!(bool);
-impl_from_yaml_cp!(i64);
-impl_from_yaml_cp!(u64);
 impl_from_yaml_cp!(f64);
 
+// Impl block generator for integer types, which are also accepted as prefixed radix strings
+macro_rules! impl_from_yaml_int {
+    ($type:ty) => {
+        impl<'a> FromYaml<'a> for $type {
+            type Output = Self;
+
+            fn parse(value: &'a Value) -> Option<Self::Output> {
+                <Self as FromYaml>::try_from(value).ok()
+            }
+
+            fn type_str() -> &'static str {
+                stringify!($type)
+            }
+
+            fn try_from(value: &'a Value) -> Result<Self::Output> {
+                if let Some(parsed) = value.as_str().and_then(parse_radix_int) {
+                    return parsed.and_then(|int| {
+                        <Self as TryFrom<i128>>::try_from(int).map_err(|_| {
+                            Pipeline::new(&format!(
+                                "Integer `{}` is out of range for {}",
+                                value.as_str().unwrap_or_default(),
+                                stringify!($type)
+                            ))
+                        })
+                    });
+                }
+                paste::paste! { value.[<as_ $type:lower>]() }
+                    .ok_or_else(|| type_mismatch(Self::type_str(), value))
+            }
+        }
+    };
+}
+
+impl_from_yaml_int!(i64);
+impl_from_yaml_int!(u64);
+
+impl<'a> FromYaml<'a> for String {
+    type Output = Self;
+
+    fn parse(value: &'a Value) -> Option<Self::Output> {
+        value.as_str().map(str::to_string)
+    }
+
+    fn type_str() -> &'static str {
+        "String"
+    }
+}
+
+/// A leading `~` is expanded to the home directory of the user, e.g. `~/bin/tool`, see
+/// [`get_path_resolved`]. Other forms, such as `~user/bin`, are taken literally.
+impl<'a> FromYaml<'a> for PathBuf {
+    type Output = Self;
+
+    fn parse(value: &'a Value) -> Option<Self::Output> {
+        <Self as FromYaml>::try_from(value).ok()
+    }
+
+    fn type_str() -> &'static str {
+        "PathBuf"
+    }
+
+    fn try_from(value: &'a Value) -> Result<Self::Output> {
+        let text = value
+            .as_str()
+            .ok_or_else(|| type_mismatch(Self::type_str(), value))?;
+        expand_home(text, home_dir())
+    }
+}
+
+// Obtain the home directory of the user from `HOME` or, on Windows, `USERPROFILE`
+fn home_dir() -> Option<PathBuf> {
+    ["HOME", "USERPROFILE"]
+        .into_iter()
+        .filter_map(std::env::var_os)
+        .find(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+// Expand a leading `~` of a path to the given home directory
+fn expand_home(text: &str, home: Option<PathBuf>) -> Result<PathBuf> {
+    let rest = match text.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(std::path::is_separator) => rest,
+        _ => return Ok(PathBuf::from(text)),
+    };
+    let home = home.ok_or_else(|| {
+        Pipeline::new(&format!(
+            "Could not expand `{}`: the home directory is not known, set `HOME`",
+            text
+        ))
+    })?;
+    Ok(home.join(rest.trim_start_matches(std::path::is_separator)))
+}
+
+/// Date or date-time read from a string scalar (requires the `chrono` feature).
+///
+/// Following forms are accepted:
+///  - RFC 3339 date-times, e.g. `2020-01-05T10:30:00Z` or `2020-01-05T10:30:00+02:00`,
+///  - plain dates, e.g. `2020-01-05`.
+///
+/// ```
+/// use pipeline::yutil::{get_typed_value_by_path, DateSpec};
+/// use serde_yaml::Value;
+///
+/// let doc: Value = serde_yaml::from_str("last_inspection: { date: '2020-01-05' }").unwrap();
+/// let date = get_typed_value_by_path::<DateSpec>(&doc, "last_inspection.date")?;
+///
+/// assert_eq!("2020-01-05", date.date().to_string());
+/// # Ok::<(), pipeline::error::Pipeline>(())
+/// ```
+#[cfg(feature = "chrono")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DateSpec {
+    /// A calendar date without time.
+    Date(chrono::NaiveDate),
+    /// A date-time with an offset from UTC.
+    DateTime(chrono::DateTime<chrono::FixedOffset>),
+}
+
+#[cfg(feature = "chrono")]
+impl DateSpec {
+    /// Obtain the calendar date, in the offset of a date-time.
+    #[must_use]
+    pub fn date(&self) -> chrono::NaiveDate {
+        match self {
+            Self::Date(date) => *date,
+            Self::DateTime(date_time) => date_time.date_naive(),
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<'a> FromYaml<'a> for DateSpec {
+    type Output = Self;
+
+    fn parse(value: &'a Value) -> Option<Self::Output> {
+        <Self as FromYaml>::try_from(value).ok()
+    }
+
+    fn type_str() -> &'static str {
+        "DateSpec"
+    }
+
+    fn try_from(value: &'a Value) -> Result<Self::Output> {
+        let text = value
+            .as_str()
+            .ok_or_else(|| type_mismatch(Self::type_str(), value))?;
+        if let Ok(date_time) = chrono::DateTime::parse_from_rfc3339(text) {
+            return Ok(Self::DateTime(date_time));
+        }
+        chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .map(Self::Date)
+            .map_err(|e| {
+                Pipeline::new_debug(
+                    &format!(
+                        "Could not parse `{}` as a date: expected an RFC 3339 date-time or YYYY-MM-DD",
+                        text
+                    ),
+                    &e.to_string(),
+                )
+            })
+    }
+}
+
+/// Regular expression compiled from a string scalar (requires the `regex` feature).
+///
+/// The expression is compiled when the value is read, so a syntax error is reported together
+/// with the configuration rather than when the expression is first used. Requesting the type
+/// yields a compiled [`regex::Regex`]:
+///
+/// ```
+/// use pipeline::yutil::{get_typed_value_by_path, RegexSpec};
+/// use serde_yaml::Value;
+///
+/// let doc: Value = serde_yaml::from_str(r"match: '^v\d+$'").unwrap();
+/// let regex = get_typed_value_by_path::<RegexSpec>(&doc, "match")?;
+///
+/// assert!(regex.is_match("v12"));
+/// # Ok::<(), pipeline::error::Pipeline>(())
+/// ```
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone)]
+pub struct RegexSpec(pub regex::Regex);
+
+#[cfg(feature = "regex")]
+impl<'a> FromYaml<'a> for RegexSpec {
+    type Output = regex::Regex;
+
+    fn parse(value: &'a Value) -> Option<Self::Output> {
+        <Self as FromYaml>::try_from(value).ok()
+    }
+
+    fn type_str() -> &'static str {
+        "RegexSpec"
+    }
+
+    fn try_from(value: &'a Value) -> Result<Self::Output> {
+        let text = value
+            .as_str()
+            .ok_or_else(|| type_mismatch(Self::type_str(), value))?;
+        regex::Regex::new(text).map_err(|e| {
+            Pipeline::new_debug(
+                &format!("Could not compile `{}` as a regex: {}", text, e),
+                &format!("Input object: {:?}", value),
+            )
+        })
+    }
+}
+
+/// Binary data read from a base64 string scalar.
+///
+/// The standard alphabet (RFC 4648) is used. Padding is optional and whitespace is ignored, so
+/// long values can be wrapped, e.g. in YAML block scalars. Requesting the type yields the
+/// decoded bytes:
+///
+/// ```
+/// use pipeline::yutil::{get_typed_value_by_path, Base64};
+/// use serde_yaml::Value;
+///
+/// let doc: Value = serde_yaml::from_str("cert: aGVsbG8=").unwrap();
+/// assert_eq!(b"hello".to_vec(), get_typed_value_by_path::<Base64>(&doc, "cert")?);
+/// # Ok::<(), pipeline::error::Pipeline>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Base64(pub Vec<u8>);
+
+impl<'a> FromYaml<'a> for Base64 {
+    type Output = Vec<u8>;
+
+    fn parse(value: &'a Value) -> Option<Self::Output> {
+        <Self as FromYaml>::try_from(value).ok()
+    }
+
+    fn type_str() -> &'static str {
+        "Base64"
+    }
+
+    fn try_from(value: &'a Value) -> Result<Self::Output> {
+        let text = value
+            .as_str()
+            .ok_or_else(|| type_mismatch(Self::type_str(), value))?;
+        decode_base64(text).map_err(|reason| {
+            Pipeline::new_debug(
+                &format!("Could not decode base64 value: {}", reason),
+                &format!("Input object: {:?}", value),
+            )
+        })
+    }
+}
+
+// Decode base64 text, returning a reason of a failure
+fn decode_base64(text: &str) -> std::result::Result<Vec<u8>, String> {
+    let sextet = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+
+    let symbols: Vec<(usize, u8)> = text
+        .bytes()
+        .enumerate()
+        .filter(|(_, c)| !c.is_ascii_whitespace())
+        .collect();
+    let data_len = symbols
+        .iter()
+        .rposition(|(_, c)| *c != b'=')
+        .map_or(0, |idx| idx + 1);
+    let mut sextets = Vec::with_capacity(data_len);
+    for &(offset, c) in &symbols[..data_len] {
+        let value = sextet(c).ok_or_else(|| {
+            let c = text[offset..].chars().next().unwrap_or_default();
+            format!("invalid character `{}` at offset {}", c, offset)
+        })?;
+        sextets.push(u32::from(value));
+    }
+    let padding = symbols.len() - data_len;
+    if padding > 2 || (padding > 0 && !symbols.len().is_multiple_of(4)) || data_len % 4 == 1 {
+        return Err("invalid length or padding".to_string());
+    }
+    let mut bytes = Vec::with_capacity(data_len * 3 / 4);
+    for chunk in sextets.chunks(4) {
+        let bits = chunk
+            .iter()
+            .chain(std::iter::repeat(&0))
+            .take(4)
+            .fold(0, |acc, sextet| acc << 6 | sextet);
+        bytes.extend_from_slice(&bits.to_be_bytes()[1..chunk.len()]);
+    }
+    Ok(bytes)
+}
+
+/// Byte count read from a human readable size, e.g. `max_output: 10MB`.
+///
+/// A size is a non-negative number, optionally with a fraction, followed by an optional unit.
+/// Whitespace between the number and the unit is allowed and units are case-insensitive.
+/// Following units are supported:
+///  - `B` (bytes, the default when no unit is given),
+///  - `KB`, `MB`, `GB`, `TB` (decimal units, powers of 1000),
+///  - `KiB`, `MiB`, `GiB`, `TiB` (binary units, powers of 1024).
+///
+/// Fractional sizes are rounded down to whole bytes. Integer nodes are accepted as byte counts.
+/// Requesting the type yields the number of bytes:
+///
+/// ```
+/// use pipeline::yutil::{get_typed_value_by_path, ByteSize};
+/// use serde_yaml::Value;
+///
+/// let doc: Value = serde_yaml::from_str("{ max_output: 10MB, cache: 1.5 KiB }").unwrap();
+/// assert_eq!(10_000_000, get_typed_value_by_path::<ByteSize>(&doc, "max_output")?);
+/// assert_eq!(1536, get_typed_value_by_path::<ByteSize>(&doc, "cache")?);
+/// # Ok::<(), pipeline::error::Pipeline>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ByteSize(pub u64);
+
+impl<'a> FromYaml<'a> for ByteSize {
+    type Output = u64;
+
+    fn parse(value: &'a Value) -> Option<Self::Output> {
+        <Self as FromYaml>::try_from(value).ok()
+    }
+
+    fn type_str() -> &'static str {
+        "ByteSize"
+    }
+
+    fn try_from(value: &'a Value) -> Result<Self::Output> {
+        if let Some(bytes) = value.as_u64() {
+            return Ok(bytes);
+        }
+        let text = value
+            .as_str()
+            .ok_or_else(|| type_mismatch(Self::type_str(), value))?;
+        parse_byte_size(text).map_err(|reason| {
+            Pipeline::new_debug(
+                &format!("Could not parse `{}` as a byte size: {}", text, reason),
+                &format!("Input object: {:?}", value),
+            )
+        })
+    }
+}
+
+// Parse a human readable byte size, returning a reason of a failure
+fn parse_byte_size(text: &str) -> std::result::Result<u64, String> {
+    let text = text.trim();
+    let number_len = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(number_len);
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if whole.is_empty() || !digits(fraction) || number.ends_with('.') {
+        return Err("expected a number followed by an optional unit, e.g. `10MB`".to_string());
+    }
+    let multiplier: u128 = match unit.trim_start().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000_u128.pow(2),
+        "gb" => 1000_u128.pow(3),
+        "tb" => 1000_u128.pow(4),
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return Err(format!("unknown unit `{}`", unit.trim_start())),
+    };
+
+    // Fraction digits beyond the largest unit cannot affect whole bytes
+    let fraction = &fraction[..fraction.len().min(13)];
+    let out_of_range = || "the size is out of range".to_string();
+    let whole: u128 = whole.parse().map_err(|_| out_of_range())?;
+    let scale = 10_u128.pow(fraction.len() as u32);
+    let fraction: u128 = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse().map_err(|_| out_of_range())?
+    };
+    let bytes = whole
+        .checked_mul(multiplier)
+        .and_then(|bytes| bytes.checked_add(fraction * multiplier / scale))
+        .ok_or_else(out_of_range)?;
+    <u64 as TryFrom<u128>>::try_from(bytes).map_err(|_| out_of_range())
+}
+
+/// Fraction read from a percentage, e.g. `threshold: 85%`.
+///
+/// A percentage is a string with a finite number, optionally signed and with a fraction, followed
+/// by `%`, e.g. `85%` or `12.5 %`. Numeric nodes are taken as fractions directly, so
+/// `threshold: 0.85` is the same as `threshold: 85%`. Strings without `%` are rejected, as `85`
+/// could mean either. Requesting the type yields the fraction:
+///
+/// ```
+/// use pipeline::yutil::{get_typed_value_by_path, Percent};
+/// use serde_yaml::Value;
+///
+/// let doc: Value = serde_yaml::from_str("{ threshold: 85%, ratio: 0.5 }").unwrap();
+/// assert_eq!(0.85, get_typed_value_by_path::<Percent>(&doc, "threshold")?);
+/// assert_eq!(0.5, get_typed_value_by_path::<Percent>(&doc, "ratio")?);
+/// # Ok::<(), pipeline::error::Pipeline>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Percent(pub f64);
+
+impl<'a> FromYaml<'a> for Percent {
+    type Output = f64;
+
+    fn parse(value: &'a Value) -> Option<Self::Output> {
+        <Self as FromYaml>::try_from(value).ok()
+    }
+
+    fn type_str() -> &'static str {
+        "Percent"
+    }
+
+    fn try_from(value: &'a Value) -> Result<Self::Output> {
+        if let Some(fraction) = value.as_f64() {
+            return Ok(fraction);
+        }
+        let text = value
+            .as_str()
+            .ok_or_else(|| type_mismatch(Self::type_str(), value))?;
+        text.trim()
+            .strip_suffix('%')
+            .and_then(|number| number.trim_end().parse::<f64>().ok())
+            .filter(|percent| percent.is_finite())
+            .map(|percent| percent / 100.0)
+            .ok_or_else(|| {
+                Pipeline::new_debug(
+                    &format!(
+                        "Could not parse `{}` as a percentage: expected a number followed by `%`, e.g. `85%`",
+                        text
+                    ),
+                    &format!("Input object: {:?}", value),
+                )
+            })
+    }
+}
+
+// Parse an integer string with a `0x`, `0o` or `0b` radix prefix, optionally preceded by a sign
+// and with digits separated by underscores. Returns `None` if the string has no radix prefix
+fn parse_radix_int(text: &str) -> Option<Result<i128>> {
+    let (negative, unsigned) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let (radix, digits) = match unsigned.get(..2).map(str::to_ascii_lowercase).as_deref() {
+        Some("0x") => (16, &unsigned[2..]),
+        Some("0o") => (8, &unsigned[2..]),
+        Some("0b") => (2, &unsigned[2..]),
+        _ => return None,
+    };
+
+    let error = |detail: &str| {
+        Pipeline::new_debug(
+            &format!("Could not parse `{}` as a base {} integer", text, radix),
+            detail,
+        )
+    };
+    // `from_str_radix` accepts a sign of its own, which is not valid after the prefix
+    if digits.chars().any(|c| c != '_' && !c.is_digit(radix)) {
+        return Some(Err(error("invalid digit found in string")));
+    }
+    let digits = digits.replace('_', "");
+    Some(
+        i128::from_str_radix(&digits, radix)
+            .map(|int| if negative { -int } else { int })
+            .map_err(|e| error(&e.to_string())),
+    )
+}
+
+// Construct an error reporting that a value has an unexpected type
+fn type_mismatch(type_str: &str, value: &Value) -> Pipeline {
+    Pipeline::new_debug(
+        &format!(
+            "Could not parse requested yaml value: expected {}, found {}",
+            type_str,
+            value_kind(value)
+        ),
+        &format!("Input object: {:?}", value),
+    )
+}
+
+// Obtain a human readable kind of a YAML node. Used to enrich conversion error messages
+fn value_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Number(n) if n.is_f64() => "float",
+        Value::Number(_) => "integer",
+        other => value_type_name(other),
+    }
+}
+
+/// Obtain a type name of a YAML value.
+///
+/// The function returns one of `null`, `bool`, `number`, `string`, `mapping` or `sequence`. The
+/// names are the same as used by error messages of this crate, except for conversion errors which
+/// report numbers more precisely as `integer` or `float`.
+#[must_use]
+pub fn value_type_name(value: &Value) -> &'static str {
+    ValueKind::of(value).name()
+}
+
+/// Category of a YAML node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    /// A null value.
+    Null,
+    /// A boolean.
+    Bool,
+    /// An integer or a floating point number.
+    Number,
+    /// A string.
+    String,
+    /// A sequence.
+    Sequence,
+    /// A mapping.
+    Mapping,
+}
+
+impl ValueKind {
+    /// Obtain the category of a YAML value.
+    #[must_use]
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(_) => Self::Bool,
+            Value::Number(_) => Self::Number,
+            Value::String(_) => Self::String,
+            Value::Sequence(_) => Self::Sequence,
+            Value::Mapping(_) => Self::Mapping,
+        }
+    }
+
+    /// Obtain the name of the category, as returned by [`value_type_name`].
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Bool => "bool",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Sequence => "sequence",
+            Self::Mapping => "mapping",
+        }
+    }
+}
+
+impl std::fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 /// Obtain YAML value by a path.
 ///
 /// The path comprises a specified number of keys separated by a dot character e.g. `key.key2.key3`.
@@ -87,11 +636,19 @@ impl_from_yaml_cp!(f64);
 ///
 /// # Errors
 /// The function returns an error in case specified path was not found inside an input object.
+/// An empty path and a path with an empty segment (e.g. `key.`, `.key` or `key..key`) are
+/// reported with dedicated errors, as they indicate a mistake in building the path.
 pub fn get_value_by_path<'a>(value: &'a Value, path: &str) -> Result<&'a Value> {
+    check_path(path)?;
+    // A single lookup key is reused for every segment to avoid an allocation per segment
+    let mut lookup_key = Value::String(String::with_capacity(path.len()));
     let cf = path.split('.').try_fold(value, |acc, key| match acc {
         Value::Mapping(map) => {
-            let value_from_str = Value::String(key.to_string());
-            match map.get(&value_from_str) {
+            if let Value::String(buf) = &mut lookup_key {
+                buf.clear();
+                buf.push_str(key);
+            }
+            match map.get(&lookup_key) {
                 Some(value) => ControlFlow::Continue(value),
                 None => ControlFlow::Break(()),
             }
@@ -101,113 +658,3076 @@ pub fn get_value_by_path<'a>(value: &'a Value, path: &str) -> Result<&'a Value>
 
     match cf {
         ControlFlow::Continue(value) => Ok(value),
-        ControlFlow::Break(_) => Err(Pipeline::new_debug(
-            &format!("Path `{}` was not found within the input object", path),
-            &format!("Input object: {:?}", value),
-        )),
+        ControlFlow::Break(_) => Err(path_not_found(value, path)),
     }
 }
 
-/// Obtain a YAML value with a specific type.
+/// Obtain an owned copy of a YAML value found by a path.
 ///
-/// The function obtains a value similarly to [`get_value_by_path`] with additional type conversion
-/// afterwards.
+/// The function works as [`get_value_by_path`] but clones the resolved node, so the result does
+/// not borrow the input object. Cloning copies the whole subtree, which may be expensive for
+/// large sequences or mappings; prefer the borrowing getter when lifetimes permit.
 ///
-/// Following conversions are supported at the moment:
-///  - bool
-///  - i64
-///  - u64
-///  - f64
-///  - &str
-///  - &Mapping
-///  - &Sequence
+/// # Errors
+/// The function returns an error in case specified path was not found inside an input object.
+pub fn get_owned_value_by_path(value: &Value, path: &str) -> Result<Value> {
+    get_value_by_path(value, path).cloned()
+}
+
+/// Obtain YAML value by a path or a default value if the path was not found.
+///
+/// The default is evaluated eagerly, see [`get_value_by_path_or_else`] for the lazy variant.
 ///
 /// # Errors
-/// The function returns an error in case specified path was not found inside an input object
-/// or obtained value cannot be casted to a desired type.
-pub fn get_typed_value_by_path<'a, T>(value: &'a Value, path: &str) -> Result<T::Output>
+/// The function returns an error in case the path traverses a node other than a mapping, or
+/// the path is malformed (see [`get_value_by_path`]).
+pub fn get_value_by_path_or<'a>(
+    value: &'a Value,
+    path: &str,
+    default: Value,
+) -> Result<Cow<'a, Value>> {
+    get_value_by_path_or_else(value, path, || default)
+}
+
+/// Obtain YAML value by a path or compute a default value if the path was not found.
+///
+/// The closure is called only when a key along the path is missing, which makes the function a
+/// better fit than [`get_value_by_path_or`] when the default is expensive to compute. A path
+/// traversing a scalar or a sequence is not considered missing, e.g. `name.first` where `name`
+/// is a string, as it most likely indicates a mistake in the path or in the input object.
+///
+/// # Errors
+/// The function returns an error in case the path traverses a node other than a mapping, or
+/// the path is malformed (see [`get_value_by_path`]).
+pub fn get_value_by_path_or_else<'a, F>(
+    value: &'a Value,
+    path: &str,
+    f: F,
+) -> Result<Cow<'a, Value>>
 where
-    T: ?Sized + FromYaml<'a>,
+    F: FnOnce() -> Value,
 {
-    let v = get_value_by_path(value, path)?;
-    T::try_from(v)
+    check_path(path)?;
+    let mut lookup_key = Value::String(String::with_capacity(path.len()));
+    let mut current = value;
+    for (idx, key) in path.split('.').enumerate() {
+        let Value::Mapping(map) = current else {
+            return Err(non_mapping_traversed(value, path, idx, current));
+        };
+        if let Value::String(buf) = &mut lookup_key {
+            buf.clear();
+            buf.push_str(key);
+        }
+        match map.get(&lookup_key) {
+            Some(next) => current = next,
+            None => return Ok(Cow::Owned(f())),
+        }
+    }
+    Ok(Cow::Borrowed(current))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rstest::*;
+/// Obtain YAML value by the first of candidate paths which is found.
+///
+/// Paths are tried in order, which suits settings moved to another key: the current path is
+/// given first, followed by deprecated ones, e.g. `&["server.port", "port"]`.
+///
+/// # Errors
+/// The function returns an error listing all paths in case none of them was found inside an
+/// input object, in case any of the paths is malformed (see [`get_value_by_path`]) or no paths
+/// are given.
+pub fn get_first_present<'a>(value: &'a Value, paths: &[&str]) -> Result<&'a Value> {
+    if paths.is_empty() {
+        return Err(Pipeline::new("No candidate paths were given"));
+    }
+    for path in paths {
+        check_path(path)?;
+    }
+    paths
+        .iter()
+        .find_map(|path| get_value_by_path(value, path).ok())
+        .ok_or_else(|| {
+            let paths: Vec<_> = paths.iter().map(|path| format!("`{}`", path)).collect();
+            Pipeline::new_debug(
+                &format!(
+                    "None of paths {} was found within the input object",
+                    paths.join(", ")
+                ),
+                &format!("Input object: {:?}", value),
+            )
+        })
+}
 
-    /* ------------------ */
-    /* ---- Fixtures ---- */
-    /* ------------------ */
+/// Obtain YAML value by a path from a primary object, falling back to another one.
+///
+/// The path is looked up in `fallback` only when it cannot be resolved in `primary`, e.g. for
+/// user settings layered over system defaults. Unlike [`merge`], nothing is copied, which suits
+/// reading a handful of keys.
+///
+/// # Errors
+/// The function returns an error in case the path is malformed (see [`get_value_by_path`]) or
+/// it cannot be resolved in either object.
+pub fn get_value_with_fallback<'a>(
+    primary: &'a Value,
+    fallback: &'a Value,
+    path: &str,
+) -> Result<&'a Value> {
+    check_path(path)?;
+    get_value_by_path(primary, path)
+        .or_else(|_| get_value_by_path(fallback, path))
+        .map_err(|_| {
+            Pipeline::new_debug(
+                &format!(
+                    "Path `{}` was found neither within the primary nor within the fallback object",
+                    path
+                ),
+                &format!(
+                    "Primary object: {:?}\nFallback object: {:?}",
+                    primary, fallback
+                ),
+            )
+        })
+}
 
-    #[fixture]
-    fn test_yaml() -> Value {
-        serde_yaml::from_str(
-            r#"
-            name: "John Doe"
-            adult: true
-            age: 22
-            score: 214.67
-            rank_delta: -10
-            cars_owned:
-                - name: "Ford Mustang"
-                  age: 5
-                  last_inspection:
-                    date: "2020-01-05"
-        "#,
-        )
-        .unwrap()
+/// Obtain a mutable reference to a YAML value found by a path, inserting it if missing.
+///
+/// Missing intermediate nodes are created as empty mappings and a missing leaf is initialized
+/// with the value returned by `default`, which is called only in such a case. Null nodes along
+/// the path are replaced with mappings as well. This is the counterpart of the
+/// `entry().or_insert_with()` idiom of maps:
+///
+/// ```
+/// use pipeline::yutil::get_or_insert_mut;
+/// use serde_yaml::Value;
+///
+/// let mut doc = Value::Null;
+/// *get_or_insert_mut(&mut doc, "defaults.env.MODE", || Value::Null)? = Value::from("fast");
+/// let count = get_or_insert_mut(&mut doc, "defaults.retries", || Value::from(0))?;
+///
+/// assert_eq!(Value::from(0), *count);
+/// assert_eq!(Value::from("fast"), doc["defaults"]["env"]["MODE"]);
+/// # Ok::<(), pipeline::error::Pipeline>(())
+/// ```
+///
+/// # Errors
+/// The function returns an error in case the path traverses a node other than a mapping or
+/// null. The input object is left untouched in such a case.
+pub fn get_or_insert_mut<'a, F>(
+    value: &'a mut Value,
+    path: &str,
+    default: F,
+) -> Result<&'a mut Value>
+where
+    F: FnOnce() -> Value,
+{
+    // The whole path is checked first, so that no node is created if the path is invalid
+    check_path(path)?;
+    let mut current = &*value;
+    for (idx, key) in path.split('.').enumerate() {
+        match current {
+            Value::Mapping(map) => match map.get(&Value::from(key)) {
+                Some(next) => current = next,
+                None => break,
+            },
+            Value::Null => break,
+            other => return Err(non_mapping_traversed(value, path, idx, other)),
+        }
     }
 
-    /* -------------------------- */
-    /* ---- Test definitions ---- */
-    /* -------------------------- */
-
-    #[rstest]
-    fn get_value_by_path_returns_error_when_empty_path_is_passed(test_yaml: Value) {
-        assert!(get_value_by_path(&test_yaml, "").is_err());
+    let mut current = value;
+    let mut default = Some(default);
+    let segments: Vec<&str> = path.split('.').collect();
+    for (idx, key) in segments.iter().enumerate() {
+        if current.is_null() {
+            *current = Value::Mapping(Mapping::new());
+        }
+        let Value::Mapping(map) = current else {
+            unreachable!("the path was checked to traverse mappings only");
+        };
+        let key = Value::from(*key);
+        if !map.contains_key(&key) {
+            let leaf = idx + 1 == segments.len();
+            let new = if leaf {
+                default.take().map_or(Value::Null, |f| f())
+            } else {
+                Value::Mapping(Mapping::new())
+            };
+            map.insert(key.clone(), new);
+        }
+        current = map.get_mut(&key).expect("the key was inserted");
     }
+    Ok(current)
+}
 
-    #[rstest]
-    #[case(".")]
-    #[case("..")]
-    #[case(".key")]
-    #[case("key1.key2.")]
+// Check that a path is not empty and has no empty segments
+fn check_path(path: &str) -> Result<()> {
+    if path.is_empty() {
+        return Err(Pipeline::new("Path must not be empty"));
+    }
+    if let Some(idx) = path.split('.').position(str::is_empty) {
+        return Err(Pipeline::new(&format!(
+            "Path `{}` contains an empty segment at position {}",
+            path,
+            idx + 1
+        )));
+    }
+    Ok(())
+}
+
+// Construct an error reporting that a path traverses a node other than a mapping. `idx` is the
+// index of the path segment which was to be looked up in the node
+fn non_mapping_traversed(value: &Value, path: &str, idx: usize, node: &Value) -> Pipeline {
+    let parent = path.split('.').take(idx).collect::<Vec<_>>().join(".");
+    Pipeline::new_debug(
+        &format!(
+            "Path `{}` traverses a {} at `{}`",
+            path,
+            value_type_name(node),
+            parent
+        ),
+        &format!("Input object: {:?}", value),
+    )
+}
+
+// Construct an error reporting that a path was not found within an object
+fn path_not_found(value: &Value, path: &str) -> Pipeline {
+    Pipeline::new_debug(
+        &format!("Path `{}` was not found within the input object", path),
+        &format!("Input object: {:?}", value),
+    )
+}
+
+// Mutable counterpart of `get_value_by_path` returning `None` if the path was not found
+fn get_value_by_path_mut<'a>(value: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    let mut lookup_key = Value::String(String::with_capacity(path.len()));
+    path.split('.').try_fold(value, |acc, key| match acc {
+        Value::Mapping(map) => {
+            if let Value::String(buf) = &mut lookup_key {
+                buf.clear();
+                buf.push_str(key);
+            }
+            map.get_mut(&lookup_key)
+        }
+        _ => None,
+    })
+}
+
+/// Replace a YAML value found by a path and return the previous one.
+///
+/// Unlike setters creating missing nodes, the function requires the whole path to exist.
+///
+/// # Errors
+/// The function returns an error in case specified path was not found inside an input object.
+/// The input object is left untouched in such a case.
+pub fn replace_value_by_path(value: &mut Value, path: &str, new: Value) -> Result<Value> {
+    check_path(path)?;
+    match get_value_by_path_mut(value, path) {
+        Some(target) => Ok(std::mem::replace(target, new)),
+        None => Err(path_not_found(value, path)),
+    }
+}
+
+/// Chainable accessor of YAML values which never panics.
+///
+/// Unlike indexing (`value["key"][0]`), which panics on invalid access, the accessor records
+/// every step and reports an error once the value is requested with [`YamlRef::get`]. Mapping
+/// keys and sequence indices can be mixed freely.
+///
+/// ```
+/// use pipeline::yutil::YamlRef;
+/// use serde_yaml::Value;
+///
+/// let doc: Value = serde_yaml::from_str("cars: [{ name: Ford }]").unwrap();
+///
+/// assert_eq!("Ford", YamlRef::new(&doc).key("cars").index(0).key("name").get()?);
+/// assert!(YamlRef::new(&doc).key("cars").index(1).key("name").get().is_err());
+/// # Ok::<(), pipeline::error::Pipeline>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct YamlRef<'a> {
+    root: &'a Value,
+    current: Option<&'a Value>,
+    path: String,
+    missing: Option<String>,
+}
+
+impl<'a> YamlRef<'a> {
+    /// Construct an accessor pointing at a root value.
+    #[must_use]
+    pub fn new(value: &'a Value) -> Self {
+        Self {
+            root: value,
+            current: Some(value),
+            path: String::new(),
+            missing: None,
+        }
+    }
+
+    /// Descend into a mapping entry.
+    #[must_use]
+    pub fn key(mut self, key: &str) -> Self {
+        if !self.path.is_empty() {
+            self.path.push('.');
+        }
+        self.path.push_str(key);
+        let next = self
+            .current
+            .and_then(|v| v.as_mapping())
+            .and_then(|m| m.get(&Value::String(key.to_string())));
+        self.step(next)
+    }
+
+    /// Descend into a sequence element.
+    #[must_use]
+    pub fn index(mut self, index: usize) -> Self {
+        self.path.push_str(&format!("[{}]", index));
+        let next = self
+            .current
+            .and_then(Value::as_sequence)
+            .and_then(|s| s.get(index));
+        self.step(next)
+    }
+
+    /// Obtain the value the accessor points at.
+    ///
+    /// # Errors
+    /// The function returns an error if any of the steps was invalid, i.e. referred to a
+    /// non-existing key or index or descended into a node of a wrong type.
+    pub fn get(&self) -> Result<&'a Value> {
+        match (self.current, &self.missing) {
+            (Some(value), _) => Ok(value),
+            (None, Some(missing)) => Err(path_not_found(self.root, missing)),
+            (None, None) => Err(path_not_found(self.root, &self.path)),
+        }
+    }
+
+    // Move to the next node, remembering the first path which was not found
+    fn step(mut self, next: Option<&'a Value>) -> Self {
+        if self.current.is_some() && next.is_none() {
+            self.missing = Some(self.path.clone());
+        }
+        self.current = next;
+        self
+    }
+}
+
+/// Obtain a YAML value with a specific type.
+///
+/// The function obtains a value similarly to [`get_value_by_path`] with additional type conversion
+/// afterwards.
+///
+/// Following conversions are supported at the moment:
+///  - [`Base64`] (decoded bytes)
+///  - [`ByteSize`] (number of bytes)
+///  - [`Percent`] (fraction)
+///  - [`DateSpec`] (requires the `chrono` feature)
+///  - [`RegexSpec`] (requires the `regex` feature)
+///  - bool
+///  - i64
+///  - u64
+///  - f64
+///  - &str
+///  - String (owned copy of a string)
+///  - PathBuf (with a leading `~` expanded, see [`get_path_resolved`])
+///  - &Mapping
+///  - &Sequence
+///
+/// Strings with a `0x`, `0o` or `0b` prefix are accepted when i64 or u64 is requested and parsed
+/// as hexadecimal, octal or binary integers respectively, e.g. `flags: "0xFF"`. The prefix is
+/// case-insensitive, may be preceded by a sign, and digits may be separated by underscores. Other
+/// strings are rejected as before.
+///
+/// Integer nodes are accepted when f64 is requested and converted using `as f64` semantics.
+/// Integers with an absolute value greater than 2^53 cannot be represented exactly and are
+/// rounded to the nearest representable float.
+///
+/// # Errors
+/// The function returns an error in case specified path was not found inside an input object
+/// or obtained value cannot be casted to a desired type.
+pub fn get_typed_value_by_path<'a, T>(value: &'a Value, path: &str) -> Result<T::Output>
+where
+    T: ?Sized + FromYaml<'a>,
+{
+    let v = get_value_by_path(value, path)?;
+    try_typed::<T>(v)
+}
+
+/// Convert an already resolved YAML value into a desired type.
+///
+/// The function is a shorthand for [`FromYaml::try_from`] supporting the same types as
+/// [`get_typed_value_by_path`], e.g. `try_typed::<u64>(node)`.
+///
+/// # Errors
+/// The function returns an error if the value cannot be casted to a desired type.
+pub fn try_typed<'a, T>(value: &'a Value) -> Result<T::Output>
+where
+    T: ?Sized + FromYaml<'a>,
+{
+    T::try_from(value)
+}
+
+/// Obtain a typed value from an environment variable, falling back to a YAML value by a path.
+///
+/// If the environment variable is set, its text is converted to the desired type and the path
+/// is not looked up at all. The text is converted as a string first, so string based types like
+/// `String`, radix integers or [`Base64`] see it verbatim, and as a YAML scalar otherwise, so
+/// `true` or `8080` are accepted as bool and u64 respectively. Only types producing owned values
+/// are supported, use `String` rather than `str`.
+///
+/// ```
+/// use pipeline::yutil::get_with_env_override;
+/// use serde_yaml::Value;
+///
+/// let doc: Value = serde_yaml::from_str("server: { port: 8080 }").unwrap();
+/// let port = get_with_env_override::<u64, _>(&doc, "server.port", "EXAMPLE_SERVER_PORT")?;
+///
+/// assert_eq!(8080, port);
+/// # Ok::<(), pipeline::error::Pipeline>(())
+/// ```
+///
+/// # Errors
+/// The function returns an error in case the environment variable is set but is not valid
+/// unicode or cannot be converted to the desired type. Otherwise, errors of
+/// [`get_typed_value_by_path`] are returned.
+pub fn get_with_env_override<T, O>(value: &Value, path: &str, env_var: &str) -> Result<O>
+where
+    T: ?Sized + for<'b> FromYaml<'b, Output = O>,
+{
+    get_with_override_from::<T, O, _>(value, path, env_var, |name| std::env::var(name))
+}
+
+// Implementation of `get_with_env_override` looking variables up with a closure, so that it can
+// be tested without touching the process environment
+fn get_with_override_from<T, O, F>(value: &Value, path: &str, env_var: &str, lookup: F) -> Result<O>
+where
+    T: ?Sized + for<'b> FromYaml<'b, Output = O>,
+    F: Fn(&str) -> std::result::Result<String, std::env::VarError>,
+{
+    let text = match lookup(env_var) {
+        Ok(text) => text,
+        Err(std::env::VarError::NotPresent) => return get_typed_value_by_path::<T>(value, path),
+        Err(std::env::VarError::NotUnicode(_)) => {
+            return Err(Pipeline::new(&format!(
+                "Environment variable `{}` is not valid unicode",
+                env_var
+            )))
+        }
+    };
+    let string = Value::String(text.clone());
+    T::try_from(&string)
+        .or_else(|e| match serde_yaml::from_str::<Value>(&text) {
+            Ok(scalar) if scalar != string && !scalar.is_mapping() && !scalar.is_sequence() => {
+                T::try_from(&scalar)
+            }
+            _ => Err(e),
+        })
+        .map_err(|e| {
+            let message = format!(
+                "Invalid value `{}` of environment variable `{}`: {}",
+                text,
+                env_var,
+                e.message()
+            );
+            e.debug_detail().map_or_else(
+                || Pipeline::new(&message),
+                |detail| Pipeline::new_debug(&message, detail),
+            )
+        })
+}
+
+/// Obtain a filesystem path stored as a string by a path, resolved to an absolute canonical one.
+///
+/// Relative paths are resolved against `base_dir`, usually the directory of the file the value
+/// was loaded from, absolute paths are taken as they are. Symbolic links and `.`, `..`
+/// components are resolved as by [`std::fs::canonicalize`], so the target must exist.
+///
+/// A leading `~` followed by a path separator (or by nothing) stands for the home directory of
+/// the user, taken from the `HOME` environment variable or, if it is not set, `USERPROFILE` as
+/// on Windows, e.g. `~/bin/tool`. The `~user` form is not supported and such a path is relative.
+///
+/// # Errors
+/// The function returns an error in case specified path was not found inside an input object,
+/// the value is not a non-empty string, the path starts with `~` but the home directory is not
+/// known, or the target does not exist or cannot be resolved.
+pub fn get_path_resolved(value: &Value, path: &str, base_dir: &Path) -> Result<PathBuf> {
+    let text = get_typed_value_by_path::<str>(value, path)?;
+    if text.is_empty() {
+        return Err(Pipeline::new(&format!("Path at `{}` is empty", path)));
+    }
+    base_dir
+        .join(expand_home(text, home_dir())?)
+        .canonicalize()
+        .map_err(|e| {
+            Pipeline::new_debug(
+                &format!("Could not resolve path `{}` at `{}`: {}", text, path, e),
+                &format!("Base directory: {}", base_dir.display()),
+            )
+        })
+}
+
+/// Obtain YAML values matching a glob pattern.
+///
+/// The pattern uses the path notation of [`get_value_by_path`] where a `*` segment matches every
+/// value of a mapping and every element of a sequence, e.g. `cars_owned.*.age`. Other segments
+/// match mapping keys only. Matches are returned in document order; a pattern which matches
+/// nothing yields an empty list rather than an error.
+///
+/// # Errors
+/// The function returns an error if the pattern is empty or contains an empty segment.
+pub fn get_values_by_glob<'a>(value: &'a Value, pattern: &str) -> Result<Vec<&'a Value>> {
+    Ok(get_entries_by_glob(value, pattern)?
+        .into_iter()
+        .map(|(_, node)| node)
+        .collect())
+}
+
+/// Obtain YAML values matching a glob pattern along with their paths.
+///
+/// The function works as [`get_values_by_glob`], pairing every match with its concrete path,
+/// where `*` segments are replaced with the matched keys and sequence indices:
+///
+/// ```
+/// use pipeline::yutil::get_entries_by_glob;
+/// use serde_yaml::Value;
+///
+/// let doc: Value = serde_yaml::from_str("stages: [{ image: debian }, { image: alpine }]").unwrap();
+/// let entries = get_entries_by_glob(&doc, "stages.*.image")?;
+///
+/// assert_eq!("stages.1.image", entries[1].0);
+/// assert_eq!("alpine", entries[1].1);
+/// # Ok::<(), pipeline::error::Pipeline>(())
+/// ```
+///
+/// Paths are rendered as by [`PathDisplay`], so keys which cannot be written in the path notation
+/// are quoted.
+///
+/// # Errors
+/// The function returns an error if the pattern is empty or contains an empty segment.
+pub fn get_entries_by_glob<'a>(
+    value: &'a Value,
+    pattern: &str,
+) -> Result<Vec<(String, &'a Value)>> {
+    fn visit<'a>(
+        node: &'a Value,
+        segments: &[&str],
+        path: &mut Vec<String>,
+        matches: &mut Vec<(String, &'a Value)>,
+    ) {
+        let Some((segment, rest)) = segments.split_first() else {
+            let path: Vec<&str> = path.iter().map(String::as_str).collect();
+            matches.push((PathDisplay(&path).to_string(), node));
+            return;
+        };
+        let mut descend = |segment: String, child: &'a Value| {
+            path.push(segment);
+            visit(child, rest, path, matches);
+            path.pop();
+        };
+        match (node, *segment) {
+            (Value::Mapping(map), "*") => {
+                for (key, child) in map {
+                    descend(key_segment(key), child);
+                }
+            }
+            (Value::Sequence(seq), "*") => {
+                for (idx, child) in seq.iter().enumerate() {
+                    descend(idx.to_string(), child);
+                }
+            }
+            (Value::Mapping(map), key) => {
+                if let Some(child) = map.get(&Value::from(key)) {
+                    descend(key.to_string(), child);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    check_path(pattern)?;
+    let segments: Vec<&str> = pattern.split('.').collect();
+    let mut matches = Vec::new();
+    visit(value, &segments, &mut Vec::new(), &mut matches);
+    Ok(matches)
+}
+
+/// Obtain typed values of YAML nodes matching a glob pattern.
+///
+/// The function works as [`get_values_by_glob`], converting every match as
+/// [`get_typed_value_by_path`] does:
+///
+/// ```
+/// use pipeline::yutil::get_typed_values_by_glob;
+/// use serde_yaml::Value;
+///
+/// let doc: Value = serde_yaml::from_str("cars: [{ age: 5 }, { age: 7 }]").unwrap();
+/// assert_eq!(vec![5, 7], get_typed_values_by_glob::<u64>(&doc, "cars.*.age")?);
+/// # Ok::<(), pipeline::error::Pipeline>(())
+/// ```
+///
+/// # Errors
+/// The function returns an error if the pattern is malformed or a matched value cannot be
+/// casted to a desired type. The error names the path of the first such value.
+pub fn get_typed_values_by_glob<'a, T>(value: &'a Value, pattern: &str) -> Result<Vec<T::Output>>
+where
+    T: ?Sized + FromYaml<'a>,
+{
+    get_entries_by_glob(value, pattern)?
+        .into_iter()
+        .map(|(path, node)| {
+            T::try_from(node).map_err(|e| {
+                let message = format!("Invalid value at path `{}`: {}", path, e.message());
+                e.debug_detail().map_or_else(
+                    || Pipeline::new(&message),
+                    |detail| Pipeline::new_debug(&message, detail),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Obtain YAML value by a path, checking that it is of a given kind.
+///
+/// Unlike [`get_typed_value_by_path`], the node itself is returned, so it can be traversed
+/// further while its shape is guaranteed.
+///
+/// # Errors
+/// The function returns an error in case specified path was not found inside an input object
+/// or obtained value is of another kind.
+pub fn get_node_of_kind<'a>(value: &'a Value, path: &str, kind: ValueKind) -> Result<&'a Value> {
+    let node = get_value_by_path(value, path)?;
+    let found = ValueKind::of(node);
+    if found != kind {
+        return Err(Pipeline::new_debug(
+            &format!("Expected {} at path `{}`, found {}", kind, path, found),
+            &format!("Input object: {:?}", node),
+        ));
+    }
+    Ok(node)
+}
+
+/// Coerce a YAML value into a boolean using loose truthiness rules.
+///
+/// Following values are falsy:
+///  - `null`,
+///  - `false`,
+///  - numbers equal to zero (`0`, `0.0`, `-0.0`),
+///  - the empty string,
+///  - empty sequences and mappings.
+///
+/// Every other value is truthy, notably strings such as `"false"`, `"no"` or `"0"`, which are
+/// non-empty. Use [`get_typed_value_by_path`] with `bool` where only real booleans should be
+/// accepted.
+#[must_use]
+pub fn as_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Sequence(seq) => !seq.is_empty(),
+        Value::Mapping(map) => !map.is_empty(),
+    }
+}
+
+/// Accessor of values relative to a base node.
+///
+/// The base node is resolved once, so many sibling values can be read without traversing the
+/// common prefix for each of them. Relative paths use the same notation as
+/// [`get_value_by_path`], errors report full paths including the base path.
+///
+/// ```
+/// use pipeline::yutil::Cursor;
+/// use serde_yaml::Value;
+///
+/// let doc: Value = serde_yaml::from_str("server: { tls: { cert: a.pem, port: 443 } }").unwrap();
+/// let tls = Cursor::at(&doc, "server.tls")?;
+///
+/// assert_eq!("a.pem", tls.typed::<str>("cert")?);
+/// assert_eq!(443, tls.typed::<u64>("port")?);
+/// # Ok::<(), pipeline::error::Pipeline>(())
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Cursor<'a> {
+    base: &'a Value,
+    base_path: &'a str,
+}
+
+impl<'a> Cursor<'a> {
+    /// Construct a cursor pointing at the node found by a base path.
+    ///
+    /// # Errors
+    /// The function returns an error in case specified path was not found inside an input
+    /// object.
+    pub fn at(value: &'a Value, base_path: &'a str) -> Result<Self> {
+        Ok(Self {
+            base: get_value_by_path(value, base_path)?,
+            base_path,
+        })
+    }
+
+    /// Obtain the base node.
+    #[must_use]
+    pub fn node(&self) -> &'a Value {
+        self.base
+    }
+
+    /// Obtain YAML value by a path relative to the base node.
+    ///
+    /// # Errors
+    /// The function returns an error in case specified path was not found inside the base node.
+    pub fn get(&self, rel_path: &str) -> Result<&'a Value> {
+        check_path(rel_path)?;
+        get_value_by_path(self.base, rel_path)
+            .map_err(|_| path_not_found(self.base, &format!("{}.{}", self.base_path, rel_path)))
+    }
+
+    /// Obtain a YAML value with a specific type by a path relative to the base node.
+    ///
+    /// Conversions are the same as for [`get_typed_value_by_path`].
+    ///
+    /// # Errors
+    /// The function returns an error in case specified path was not found inside the base node
+    /// or obtained value cannot be casted to a desired type.
+    pub fn typed<T>(&self, rel_path: &str) -> Result<T::Output>
+    where
+        T: ?Sized + FromYaml<'a>,
+    {
+        T::try_from(self.get(rel_path)?)
+    }
+}
+
+/// Extract multiple typed values into a struct.
+///
+/// Each field is extracted with [`get_typed_value_by_path`] using the given type and path. All
+/// fields are attempted, so the returned error lists every field which could not be extracted
+/// rather than just the first one.
+///
+/// ```
+/// use pipeline::extract;
+/// use serde_yaml::Value;
+///
+/// struct Person<'a> {
+///     name: &'a str,
+///     age: u64,
+/// }
+///
+/// let doc: Value = serde_yaml::from_str("name: John Doe\nage: 22").unwrap();
+/// let person = extract!(&doc => Person {
+///     name: str = "name",
+///     age: u64 = "age",
+/// })?;
+///
+/// assert_eq!("John Doe", person.name);
+/// assert_eq!(22, person.age);
+/// # Ok::<(), pipeline::error::Pipeline>(())
+/// ```
+#[macro_export]
+macro_rules! extract {
+    ($value:expr => $target:ident { $($field:ident : $type:ty = $path:expr),* $(,)? }) => {{
+        let value: &::serde_yaml::Value = $value;
+        let mut errors: ::std::vec::Vec<::std::string::String> = ::std::vec::Vec::new();
+        $(
+            let $field = match $crate::yutil::get_typed_value_by_path::<$type>(value, $path) {
+                ::std::result::Result::Ok(v) => ::std::option::Option::Some(v),
+                ::std::result::Result::Err(e) => {
+                    errors.push(::std::format!("`{}` ({})", ::std::stringify!($field), e));
+                    ::std::option::Option::None
+                }
+            };
+        )*
+        match ($($field,)*) {
+            ($(::std::option::Option::Some($field),)*) => {
+                ::std::result::Result::Ok($target { $($field),* })
+            }
+            _ => ::std::result::Result::Err($crate::error::Pipeline::new(&::std::format!(
+                "Could not extract {} field(s): {}",
+                errors.len(),
+                errors.join(", ")
+            ))),
+        }
+    }};
+}
+
+/// A named conversion of YAML values stored in a [`ConverterRegistry`].
+pub type Converter = Box<dyn Fn(&Value) -> Result<Box<dyn Any>> + Send + Sync>;
+
+/// Runtime counterpart of [`FromYaml`] mapping type names to conversions.
+///
+/// The registry allows tooling driven by data, like a validator reading type names from a schema
+/// file, to convert values by name. Converted values are returned as [`Any`] and can be
+/// downcast to the concrete type:
+///
+/// ```
+/// use pipeline::error::Pipeline;
+/// use pipeline::yutil::ConverterRegistry;
+/// use std::net::IpAddr;
+///
+/// let mut registry = ConverterRegistry::with_builtin();
+/// registry.register("ipaddr", |value| {
+///     let text: &str = value.as_str().ok_or_else(|| Pipeline::new("expected string"))?;
+///     let addr: IpAddr = text.parse().map_err(|_| Pipeline::new("invalid address"))?;
+///     Ok(Box::new(addr))
+/// });
+///
+/// let value = serde_yaml::from_str("server: { host: 127.0.0.1, port: 8080 }").unwrap();
+/// let host = registry.get_value_as(&value, "server.host", "ipaddr")?;
+/// let port = registry.get_value_as(&value, "server.port", "u64")?;
+///
+/// assert!(host.downcast_ref::<IpAddr>().unwrap().is_loopback());
+/// assert_eq!(Some(&8080), port.downcast_ref::<u64>());
+/// # Ok::<(), Pipeline>(())
+/// ```
+#[derive(Default)]
+pub struct ConverterRegistry {
+    converters: HashMap<String, Converter>,
+}
+
+impl ConverterRegistry {
+    /// Construct an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Construct a registry with conversions of the types supported by
+    /// [`get_typed_value_by_path`].
+    ///
+    /// Following names are registered, producing owned values of the corresponding types:
+    /// `bool`, `i64`, `u64`, `f64`, `str` ([`String`]), `mapping` ([`Mapping`]), `sequence`
+    /// ([`Sequence`]), `base64` (`Vec<u8>`, see [`Base64`]) and `bytesize` (`u64`, see
+    /// [`ByteSize`]). With the `chrono` feature `date` ([`DateSpec`]) is registered as well, with
+    /// the `regex` feature `regex` ([`regex::Regex`], see [`RegexSpec`]).
+    #[must_use]
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register("bool", |v| Ok(Box::new(<bool as FromYaml>::try_from(v)?)));
+        registry.register("i64", |v| Ok(Box::new(<i64 as FromYaml>::try_from(v)?)));
+        registry.register("u64", |v| Ok(Box::new(<u64 as FromYaml>::try_from(v)?)));
+        registry.register("f64", |v| Ok(Box::new(<f64 as FromYaml>::try_from(v)?)));
+        registry.register("str", |v| {
+            Ok(Box::new(<str as FromYaml>::try_from(v)?.to_string()))
+        });
+        registry.register("mapping", |v| {
+            Ok(Box::new(<Mapping as FromYaml>::try_from(v)?.clone()))
+        });
+        registry.register("sequence", |v| {
+            Ok(Box::new(<Sequence as FromYaml>::try_from(v)?.clone()))
+        });
+        registry.register("base64", |v| {
+            Ok(Box::new(<Base64 as FromYaml>::try_from(v)?))
+        });
+        registry.register("bytesize", |v| {
+            Ok(Box::new(<ByteSize as FromYaml>::try_from(v)?))
+        });
+        registry.register("path", |v| {
+            Ok(Box::new(<PathBuf as FromYaml>::try_from(v)?))
+        });
+        registry.register("percent", |v| {
+            Ok(Box::new(<Percent as FromYaml>::try_from(v)?))
+        });
+        #[cfg(feature = "chrono")]
+        registry.register("date", |v| {
+            Ok(Box::new(<DateSpec as FromYaml>::try_from(v)?))
+        });
+        #[cfg(feature = "regex")]
+        registry.register("regex", |v| {
+            Ok(Box::new(<RegexSpec as FromYaml>::try_from(v)?))
+        });
+        registry
+    }
+
+    /// Register a conversion under a type name.
+    ///
+    /// A conversion previously registered under the same name is replaced and returned.
+    pub fn register<F>(&mut self, name: &str, converter: F) -> Option<Converter>
+    where
+        F: Fn(&Value) -> Result<Box<dyn Any>> + Send + Sync + 'static,
+    {
+        self.converters
+            .insert(name.to_string(), Box::new(converter))
+    }
+
+    /// Check whether a conversion is registered under a type name.
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.converters.contains_key(name)
+    }
+
+    /// Obtain type names of all registered conversions, sorted lexicographically.
+    #[must_use]
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<_> = self.converters.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Convert a YAML value with a conversion registered under a type name.
+    ///
+    /// # Errors
+    /// The function returns an error if no conversion is registered under the name or the
+    /// conversion fails.
+    pub fn convert(&self, value: &Value, name: &str) -> Result<Box<dyn Any>> {
+        let converter = self.converters.get(name).ok_or_else(|| {
+            Pipeline::new_debug(
+                &format!("Unknown type `{}`", name),
+                &format!("Known types: {}", self.names().join(", ")),
+            )
+        })?;
+        converter(value)
+    }
+
+    /// Obtain a YAML value by a path and convert it with a conversion registered under a type
+    /// name.
+    ///
+    /// # Errors
+    /// The function returns an error in case specified path was not found inside an input
+    /// object, no conversion is registered under the name or the conversion fails.
+    pub fn get_value_as(&self, value: &Value, path: &str, name: &str) -> Result<Box<dyn Any>> {
+        self.convert(get_value_by_path(value, path)?, name)
+    }
+}
+
+impl std::fmt::Debug for ConverterRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names: Vec<_> = self.converters.keys().collect();
+        names.sort_unstable();
+        f.debug_struct("ConverterRegistry")
+            .field("converters", &names)
+            .finish()
+    }
+}
+
+/// Obtain a list of strings which may be written either as a single string or a sequence.
+///
+/// A string scalar yields a single-element list, a sequence yields all of its elements.
+///
+/// # Errors
+/// The function returns an error in case specified path was not found inside an input object,
+/// obtained value is neither a string nor a sequence, or the sequence contains a non-string
+/// element.
+pub fn get_string_list(value: &Value, path: &str) -> Result<Vec<String>> {
+    match get_value_by_path(value, path)? {
+        Value::String(s) => Ok(vec![s.clone()]),
+        Value::Sequence(seq) => seq
+            .iter()
+            .map(|v| <str as FromYaml>::try_from(v).map(str::to_string))
+            .collect(),
+        other => Err(Pipeline::new_debug(
+            &format!(
+                "Could not parse `{}` as a string list: expected string or sequence, found {}",
+                path,
+                value_type_name(other)
+            ),
+            &format!("Input object: {:?}", other),
+        )),
+    }
+}
+
+/// Obtain a list of strings which may be written either as a comma-separated string or a
+/// sequence.
+///
+/// The function works as [`get_string_list`], except that a string scalar is split on commas,
+/// e.g. `tags: "web, api,db"` yields `web`, `api` and `db`. Items are trimmed of whitespace and
+/// empty items are dropped. This suits values which come from environment variables in some
+/// setups and from a file in others. Sequence elements are not split.
+///
+/// # Errors
+/// The function returns an error in case specified path was not found inside an input object,
+/// obtained value is neither a string nor a sequence, or the sequence contains a non-string
+/// element.
+pub fn get_csv_list(value: &Value, path: &str) -> Result<Vec<String>> {
+    if let Value::String(s) = get_value_by_path(value, path)? {
+        return Ok(s
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect());
+    }
+    get_string_list(value, path)
+}
+
+/// Obtain a number of elements of a sequence or entries of a mapping found by a path.
+///
+/// # Errors
+/// The function returns an error in case specified path was not found inside an input object
+/// or obtained value is neither a sequence nor a mapping.
+pub fn len_at_path(value: &Value, path: &str) -> Result<usize> {
+    match get_value_by_path(value, path)? {
+        Value::Sequence(seq) => Ok(seq.len()),
+        Value::Mapping(map) => Ok(map.len()),
+        other => Err(Pipeline::new_debug(
+            &format!(
+                "Could not obtain length of `{}`: expected sequence or mapping, found {}",
+                path,
+                value_type_name(other)
+            ),
+            &format!("Input object: {:?}", other),
+        )),
+    }
+}
+
+/// Iterate over entries of a mapping found by a path.
+///
+/// Entries are yielded in document order. Entries with non-string keys are skipped, consistently
+/// with [`get_value_by_path`] which can address string keys only.
+///
+/// # Errors
+/// The function returns an error in case specified path was not found inside an input object
+/// or obtained value is not a mapping.
+pub fn entries_at_path<'a>(
+    value: &'a Value,
+    path: &str,
+) -> Result<impl Iterator<Item = (&'a str, &'a Value)>> {
+    let map = get_typed_value_by_path::<Mapping>(value, path)?;
+    Ok(map.iter().filter_map(|(k, v)| k.as_str().map(|k| (k, v))))
+}
+
+/// Transform every entry of a mapping found by a path with a fallible function.
+///
+/// The function is called with the key and the value of each entry in document order, see
+/// [`entries_at_path`]. Results are collected in the same order.
+///
+/// # Errors
+/// The function returns an error in case specified path was not found inside an input object
+/// or obtained value is not a mapping. The first error returned by `f` aborts the
+/// transformation and is returned with the failing key prepended to its message.
+pub fn try_map_entries<'a, T, F>(value: &'a Value, path: &str, f: F) -> Result<Vec<T>>
+where
+    F: Fn(&str, &'a Value) -> Result<T>,
+{
+    entries_at_path(value, path)?
+        .map(|(key, entry)| {
+            f(key, entry).map_err(|e| {
+                let message = format!("Invalid entry `{}` of `{}`: {}", key, path, e.message());
+                e.debug_detail().map_or_else(
+                    || Pipeline::new(&message),
+                    |detail| Pipeline::new_debug(&message, detail),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Check that the top-level mapping of a document contains only allowed keys.
+///
+/// Keys which are allowed but missing from the document are not reported. Non-string keys are
+/// never allowed and are reported using their scalar representation.
+///
+/// # Errors
+/// The function returns an error listing all unexpected keys in document order, or an error if
+/// the document is not a mapping.
+pub fn assert_only_keys(value: &Value, allowed: &[&str]) -> Result<()> {
+    let map = <Mapping as FromYaml>::try_from(value)?;
+    let unexpected: Vec<_> = map
+        .iter()
+        .map(|(k, _)| k)
+        .filter(|k| !k.as_str().is_some_and(|k| allowed.contains(&k)))
+        .map(|k| format!("`{}`", key_segment(k)))
+        .collect();
+    if unexpected.is_empty() {
+        return Ok(());
+    }
+    Err(Pipeline::new_debug(
+        &format!("Unexpected top-level keys: {}", unexpected.join(", ")),
+        &format!("Allowed keys: {}", allowed.join(", ")),
+    ))
+}
+
+/// Expand YAML merge keys (`<<`) in place.
+///
+/// `serde_yaml` resolves aliases but keeps merge keys as regular entries. The function replaces
+/// every `<<` entry with the entries of the referenced mapping (`<<: *base`) or mappings
+/// (`<<: [*base1, *base2]`). Precedence follows the YAML merge key specification:
+///  - keys defined locally in the mapping always win over merged keys,
+///  - when a list of mappings is merged, earlier mappings win over later ones.
+///
+/// Merged entries are appended after the local entries, which keep their order. Nested mappings
+/// (including the merged ones) are expanded as well.
+///
+/// # Errors
+/// The function returns an error if a merge key refers to something other than a mapping or a
+/// sequence of mappings, or the document is nested deeper than [`DEFAULT_MAX_DEPTH`].
+pub fn expand_merge_keys(value: &mut Value) -> Result<()> {
+    expand_merge_keys_with_max_depth(value, DEFAULT_MAX_DEPTH)
+}
+
+/// Expand YAML merge keys of a document nested at most `max_depth` levels deep.
+///
+/// The function works as [`expand_merge_keys`] with a custom depth limit, the root node having
+/// depth 0.
+///
+/// # Errors
+/// The function returns an error if a merge key refers to something other than a mapping or a
+/// sequence of mappings, or the document is nested deeper than `max_depth`.
+pub fn expand_merge_keys_with_max_depth(value: &mut Value, max_depth: usize) -> Result<()> {
+    expand_merge_keys_inner(value, 0, max_depth)
+}
+
+// Recursive part of `expand_merge_keys` tracking the depth of the current node
+fn expand_merge_keys_inner(value: &mut Value, depth: usize, max_depth: usize) -> Result<()> {
+    check_depth(depth, max_depth)?;
+    match value {
+        Value::Mapping(map) => {
+            for (_, child) in map.iter_mut() {
+                expand_merge_keys_inner(child, depth + 1, max_depth)?;
+            }
+
+            let merge_key = Value::String("<<".to_string());
+            if !map.contains_key(&merge_key) {
+                return Ok(());
+            }
+            // The mapping is rebuilt, as removing a key moves the last key into its place
+            let mut sources = Value::Null;
+            for (k, v) in std::mem::take(map) {
+                if k == merge_key {
+                    sources = v;
+                } else {
+                    map.insert(k, v);
+                }
+            }
+            let sources = match sources {
+                Value::Mapping(source) => vec![source],
+                Value::Sequence(seq) if seq.iter().all(Value::is_mapping) => seq
+                    .into_iter()
+                    .filter_map(|v| match v {
+                        Value::Mapping(source) => Some(source),
+                        _ => None,
+                    })
+                    .collect(),
+                other => {
+                    return Err(Pipeline::new_debug(
+                        "Merge key `<<` must refer to a mapping or a sequence of mappings",
+                        &format!("Merge key value: {:?}", other),
+                    ))
+                }
+            };
+
+            for source in sources {
+                for (k, v) in source {
+                    if !map.contains_key(&k) {
+                        map.insert(k, v);
+                    }
+                }
+            }
+            Ok(())
+        }
+        Value::Sequence(seq) => seq
+            .iter_mut()
+            .try_for_each(|child| expand_merge_keys_inner(child, depth + 1, max_depth)),
+        _ => Ok(()),
+    }
+}
+
+/// Deep merge a YAML value into another one.
+///
+/// Mappings are merged recursively: entries of `overlay` missing from `base` are appended,
+/// entries present in both are merged. Any other overlay value, including a sequence, replaces
+/// the base value.
+///
+/// See [`merge_reporting`] for a variant reporting replaced values.
+///
+/// # Errors
+/// The function returns an error if mappings nested deeper than [`DEFAULT_MAX_DEPTH`] are to be
+/// merged, see [`merge_with_max_depth`]. The base value may be partially merged in such a case.
+pub fn merge(base: &mut Value, overlay: &Value) -> Result<()> {
+    merge_with_max_depth(base, overlay, DEFAULT_MAX_DEPTH)
+}
+
+/// Deep merge a YAML value into another one, merging mappings nested at most `max_depth` levels
+/// deep.
+///
+/// The function works as [`merge`] with a custom depth limit, the root mappings having depth 0.
+///
+/// # Errors
+/// The function returns an error if mappings nested deeper than `max_depth` are to be merged.
+/// The base value may be partially merged in such a case.
+pub fn merge_with_max_depth(base: &mut Value, overlay: &Value, max_depth: usize) -> Result<()> {
+    merge_inner(base, overlay, max_depth, &mut Vec::new(), &mut None)
+}
+
+/// Deep merge a YAML value into another one, reporting overridden scalars.
+///
+/// The function works as [`merge`] and returns dotted paths of all scalars of `base` which were
+/// replaced by different scalars of `overlay`, in the order of `overlay`. Scalars replaced by an
+/// equal value are not reported, neither are sequences and mappings replaced as a whole.
+///
+/// # Errors
+/// The function returns an error if mappings nested deeper than [`DEFAULT_MAX_DEPTH`] are to be
+/// merged, see [`merge_reporting_with_max_depth`].
+pub fn merge_reporting(base: &mut Value, overlay: &Value) -> Result<Vec<String>> {
+    merge_reporting_with_max_depth(base, overlay, DEFAULT_MAX_DEPTH)
+}
+
+/// Deep merge a YAML value into another one, reporting overridden scalars and merging mappings
+/// nested at most `max_depth` levels deep.
+///
+/// The function works as [`merge_reporting`] with a custom depth limit, see
+/// [`merge_with_max_depth`].
+///
+/// # Errors
+/// The function returns an error if mappings nested deeper than `max_depth` are to be merged.
+/// The base value may be partially merged in such a case.
+pub fn merge_reporting_with_max_depth(
+    base: &mut Value,
+    overlay: &Value,
+    max_depth: usize,
+) -> Result<Vec<String>> {
+    let mut overridden = Some(Vec::new());
+    merge_inner(base, overlay, max_depth, &mut Vec::new(), &mut overridden)?;
+    Ok(overridden.unwrap_or_default())
+}
+
+// Recursive part of `merge` collecting overridden paths if requested
+fn merge_inner(
+    base: &mut Value,
+    overlay: &Value,
+    max_depth: usize,
+    segments: &mut Vec<String>,
+    overridden: &mut Option<Vec<String>>,
+) -> Result<()> {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            check_depth(segments.len(), max_depth)?;
+            for (key, value) in overlay {
+                match base.get_mut(key) {
+                    Some(existing) => {
+                        segments.push(key_segment(key));
+                        merge_inner(existing, value, max_depth, segments, overridden)?;
+                        segments.pop();
+                    }
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, overlay) => {
+            let is_scalar = |v: &Value| !matches!(v, Value::Mapping(_) | Value::Sequence(_));
+            if let Some(overridden) = overridden {
+                if is_scalar(base) && is_scalar(overlay) && base != overlay {
+                    overridden.push(segments.join("."));
+                }
+            }
+            *base = overlay.clone();
+        }
+    }
+    Ok(())
+}
+
+/// Serialize a YAML value with deterministic key ordering.
+///
+/// Mapping keys are sorted lexicographically, recursively at every level. String keys are
+/// compared directly, other keys are compared by their scalar representation (as used by
+/// [`walk`]). Sequences keep their order. Logically equal documents which differ only in key
+/// order are therefore serialized identically.
+///
+/// # Errors
+/// The function returns an error if the value cannot be serialized or is nested deeper than
+/// [`DEFAULT_MAX_DEPTH`], see [`to_yaml_canonical_with_max_depth`].
+pub fn to_yaml_canonical(value: &Value) -> Result<String> {
+    to_yaml_canonical_with_max_depth(value, DEFAULT_MAX_DEPTH)
+}
+
+/// Serialize a YAML value nested at most `max_depth` levels deep with deterministic key ordering.
+///
+/// The function works as [`to_yaml_canonical`] with a custom depth limit, the root node having
+/// depth 0.
+///
+/// # Errors
+/// The function returns an error if the value cannot be serialized or is nested deeper than
+/// `max_depth`.
+pub fn to_yaml_canonical_with_max_depth(value: &Value, max_depth: usize) -> Result<String> {
+    fn canonicalize(value: &Value, depth: usize, max_depth: usize) -> Result<Value> {
+        check_depth(depth, max_depth)?;
+        Ok(match value {
+            Value::Mapping(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by_cached_key(|(k, _)| (key_segment(k), value_type_name(k)));
+                Value::Mapping(
+                    entries
+                        .into_iter()
+                        .map(|(k, v)| {
+                            Ok((
+                                canonicalize(k, depth + 1, max_depth)?,
+                                canonicalize(v, depth + 1, max_depth)?,
+                            ))
+                        })
+                        .collect::<Result<_>>()?,
+                )
+            }
+            Value::Sequence(seq) => Value::Sequence(
+                seq.iter()
+                    .map(|v| canonicalize(v, depth + 1, max_depth))
+                    .collect::<Result<_>>()?,
+            ),
+            other => other.clone(),
+        })
+    }
+
+    serde_yaml::to_string(&canonicalize(value, 0, max_depth)?)
+        .map_err(|e| Pipeline::new_debug("Could not serialize YAML value", &e.to_string()))
+}
+
+/// Compute a content hash of a YAML value.
+///
+/// The hash is a 64-bit FNV-1a hash of the serialization produced by [`to_yaml_canonical`], so
+/// values which differ only in mapping key order hash identically. The algorithm does not
+/// depend on the platform or the process, so hashes can be persisted (e.g. for cache
+/// invalidation). Hashes may change when the serialization format of `serde_yaml` changes, i.e.
+/// after a dependency upgrade. FNV-1a is not a cryptographic hash.
+///
+/// # Errors
+/// The function returns an error if the value cannot be serialized.
+pub fn content_hash(value: &Value) -> Result<u64> {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let canonical = to_yaml_canonical(value)?;
+    Ok(canonical.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    }))
+}
+
+/// Check whether a YAML document survives a parse-serialize-parse round trip unchanged.
+///
+/// The text is parsed, serialized again and the result is parsed once more. The function returns
+/// `true` if both parsed documents are structurally equal. Otherwise a warning naming the first
+/// path at which the documents differ is logged and `false` is returned, e.g. when a string is
+/// serialized in a form which is read back as a number. Use [`round_trip_difference`] to obtain
+/// the difference.
+///
+/// Only the document structure is compared. Comments, formatting, anchors and tags are never
+/// preserved by a round trip and are not taken into account.
+///
+/// # Errors
+/// The function returns an error if the text is not a valid YAML document or if the document
+/// cannot be serialized.
+pub fn round_trips(text: &str) -> Result<bool> {
+    match round_trip_difference(text)? {
+        None => Ok(true),
+        Some(difference) => {
+            warn!("Document does not survive a round trip, {}", difference);
+            Ok(false)
+        }
+    }
+}
+
+/// First difference between a parsed YAML document and its round trip, see
+/// [`round_trip_difference`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundTripDifference {
+    /// Dotted path of the differing node, empty for the document root.
+    pub path: String,
+    /// The node as parsed from the original text.
+    pub original: Value,
+    /// The node as parsed back from the serialized document.
+    pub reparsed: Value,
+}
+
+impl std::fmt::Display for RoundTripDifference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "it differs at the document root")?;
+        } else {
+            write!(f, "it differs at `{}`", self.path)?;
+        }
+        // Serialized YAML would hide the difference, which lies in the serialization itself
+        write!(
+            f,
+            ": {:?} is read back as {:?}",
+            self.original, self.reparsed
+        )
+    }
+}
+
+/// Find the first node of a YAML document which changes in a parse-serialize-parse round trip.
+///
+/// The function works as [`round_trips`] and returns `None` if the document is stable, or the
+/// first differing node in document order otherwise.
+///
+/// # Errors
+/// The function returns an error if the text is not a valid YAML document or if the document
+/// cannot be serialized.
+pub fn round_trip_difference(text: &str) -> Result<Option<RoundTripDifference>> {
+    let parse = |text: &str| {
+        serde_yaml::from_str::<Value>(text)
+            .map_err(|e| Pipeline::new_debug("Could not parse YAML document", &e.to_string()))
+    };
+    let original = parse(text)?;
+    let serialized = serde_yaml::to_string(&original)
+        .map_err(|e| Pipeline::new_debug("Could not serialize YAML value", &e.to_string()))?;
+    let reparsed = parse(&serialized)?;
+
+    Ok(
+        first_difference(&original, &reparsed, &mut Vec::new()).map(
+            |(path, original, reparsed)| RoundTripDifference {
+                path,
+                original: original.clone(),
+                reparsed: reparsed.clone(),
+            },
+        ),
+    )
+}
+
+// Find the path of the first node at which two values differ
+fn first_difference<'a>(
+    first: &'a Value,
+    second: &'a Value,
+    segments: &mut Vec<String>,
+) -> Option<(String, &'a Value, &'a Value)> {
+    if first == second {
+        return None;
+    }
+    let differing_child = match (first, second) {
+        (Value::Mapping(a), Value::Mapping(b)) if a.len() == b.len() => a
+            .iter()
+            .zip(b.iter())
+            .find(|((ka, va), (kb, vb))| ka != kb || va != vb)
+            .filter(|((ka, _), (kb, _))| ka == kb)
+            .map(|((key, va), (_, vb))| (key_segment(key), va, vb)),
+        (Value::Sequence(a), Value::Sequence(b)) if a.len() == b.len() => a
+            .iter()
+            .zip(b.iter())
+            .enumerate()
+            .find(|(_, (va, vb))| va != vb)
+            .map(|(idx, (va, vb))| (idx.to_string(), va, vb)),
+        _ => None,
+    };
+    match differing_child {
+        Some((segment, a, b)) => {
+            segments.push(segment);
+            first_difference(a, b, segments)
+        }
+        None => Some((segments.join("."), first, second)),
+    }
+}
+
+/// Visit every node of a YAML document.
+///
+/// The visitor is invoked with the path segments leading to a node and the node itself. The root
+/// node is visited first with an empty path. Nodes are visited in pre-order, i.e. mappings and
+/// sequences are visited before their children. Children are visited in document order.
+///
+/// Sequence elements are identified by their index. Mapping keys which are not strings are
+/// rendered using their scalar representation, complex keys use debug formatting.
+///
+/// # Errors
+/// The function returns an error if the document is nested deeper than [`DEFAULT_MAX_DEPTH`],
+/// see [`walk_with_max_depth`]. Nodes up to the limit are visited before the error is returned.
+pub fn walk(value: &Value, visitor: &mut dyn FnMut(&[&str], &Value)) -> Result<()> {
+    walk_with_max_depth(value, DEFAULT_MAX_DEPTH, visitor)
+}
+
+/// Visit every node of a YAML document nested at most `max_depth` levels deep.
+///
+/// The function works as [`walk`] with a custom depth limit. The root node has depth 0, its
+/// children have depth 1 and so on.
+///
+/// # Errors
+/// The function returns an error if the document is nested deeper than `max_depth`.
+pub fn walk_with_max_depth(
+    value: &Value,
+    max_depth: usize,
+    visitor: &mut dyn FnMut(&[&str], &Value),
+) -> Result<()> {
+    fn walk_inner(
+        value: &Value,
+        segments: &mut Vec<String>,
+        max_depth: usize,
+        visitor: &mut dyn FnMut(&[&str], &Value),
+    ) -> Result<()> {
+        check_depth(segments.len(), max_depth)?;
+        let path: Vec<&str> = segments.iter().map(String::as_str).collect();
+        visitor(&path, value);
+
+        match value {
+            Value::Mapping(map) => {
+                for (key, child) in map {
+                    segments.push(key_segment(key));
+                    walk_inner(child, segments, max_depth, visitor)?;
+                    segments.pop();
+                }
+            }
+            Value::Sequence(seq) => {
+                for (idx, child) in seq.iter().enumerate() {
+                    segments.push(idx.to_string());
+                    walk_inner(child, segments, max_depth, visitor)?;
+                    segments.pop();
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    walk_inner(value, &mut Vec::new(), max_depth, visitor)
+}
+
+/// Count mapping entries with a given key anywhere in a YAML document.
+///
+/// The document is traversed with [`walk`], so keys of nested mappings, including mappings
+/// inside sequences, are counted. Only string keys are compared. Nodes nested deeper than
+/// [`DEFAULT_MAX_DEPTH`] are not counted.
+#[must_use]
+pub fn count_key(value: &Value, key: &str) -> usize {
+    let mut count = 0;
+    // Nodes up to the depth limit are visited before an error is returned
+    let _ = walk(value, &mut |_, node| {
+        if let Value::Mapping(map) = node {
+            count += map.iter().filter(|(k, _)| k.as_str() == Some(key)).count();
+        }
+    });
+    count
+}
+
+/// Default limit of nesting depth of recursive operations on documents.
+///
+/// The limit protects against stack overflows on deeply nested documents, e.g. built
+/// programmatically from untrusted input. It equals the nesting limit of the `serde_yaml` parser,
+/// so every parsed document is within the limit.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+// Check that the depth of a node does not exceed the limit
+pub(crate) fn check_depth(depth: usize, max_depth: usize) -> Result<()> {
+    if depth > max_depth {
+        return Err(Pipeline::new(&format!(
+            "Maximum nesting depth of {} exceeded",
+            max_depth
+        )));
+    }
+    Ok(())
+}
+
+/// Display of a path given as segments, e.g. the path passed to a [`walk`] visitor.
+///
+/// Segments are joined with dots. Segments which are empty or contain dots, quotes or
+/// backslashes are rendered in double quotes, escaping quotes and backslashes with a backslash,
+/// so every path is rendered unambiguously. Such segments cannot be used with
+/// [`get_value_by_path`], which has no notation for them. Nothing is allocated until the path is
+/// written.
+///
+/// ```
+/// use pipeline::yutil::PathDisplay;
+///
+/// assert_eq!("servers.0.name", PathDisplay(&["servers", "0", "name"]).to_string());
+/// assert_eq!(r#"hosts."example.com".port"#, PathDisplay(&["hosts", "example.com", "port"]).to_string());
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct PathDisplay<'a>(pub &'a [&'a str]);
+
+impl std::fmt::Display for PathDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, segment) in self.0.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(".")?;
+            }
+            if !segment.is_empty() && !segment.contains(['.', '"', '\\']) {
+                f.write_str(segment)?;
+                continue;
+            }
+            f.write_str("\"")?;
+            for c in segment.chars() {
+                if matches!(c, '"' | '\\') {
+                    f.write_str("\\")?;
+                }
+                write!(f, "{}", c)?;
+            }
+            f.write_str("\"")?;
+        }
+        Ok(())
+    }
+}
+
+// Convert a mapping key into a path segment
+fn key_segment(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Null => "~".to_string(),
+        _ => format!("{:?}", key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    /* ------------------ */
+    /* ---- Fixtures ---- */
+    /* ------------------ */
+
+    #[fixture]
+    fn test_yaml() -> Value {
+        serde_yaml::from_str(
+            r#"
+            name: "John Doe"
+            adult: true
+            age: 22
+            score: 214.67
+            rank_delta: -10
+            cars_owned:
+                - name: "Ford Mustang"
+                  age: 5
+                  last_inspection:
+                    date: "2020-01-05"
+        "#,
+        )
+        .unwrap()
+    }
+
+    // Sequences nested `depth` levels below the root
+    fn nested(depth: usize) -> Value {
+        (0..depth).fold(Value::Null, |acc, _| Value::Sequence(vec![acc]))
+    }
+
+    /* -------------------------- */
+    /* ---- Test definitions ---- */
+    /* -------------------------- */
+
+    #[rstest]
+    #[case("name", "string")]
+    #[case("adult", "bool")]
+    #[case("age", "number")]
+    #[case("score", "number")]
+    #[case("cars_owned", "sequence")]
+    fn value_type_name_returns_name_of_node_type(
+        #[case] path: &str,
+        #[case] expected: &str,
+        test_yaml: Value,
+    ) {
+        assert_eq!(expected, value_type_name(&test_yaml[path]));
+        assert_eq!("mapping", value_type_name(&test_yaml));
+        assert_eq!("null", value_type_name(&Value::Null));
+    }
+
+    #[rstest]
+    #[case("name", ValueKind::String)]
+    #[case("age", ValueKind::Number)]
+    #[case("cars_owned", ValueKind::Sequence)]
+    #[case("adult", ValueKind::Bool)]
+    fn get_node_of_kind_returns_node_of_requested_kind(
+        #[case] path: &str,
+        #[case] kind: ValueKind,
+        test_yaml: Value,
+    ) {
+        let node = get_node_of_kind(&test_yaml, path, kind).unwrap();
+        assert_eq!(&test_yaml[path], node);
+        assert_eq!(kind.name(), value_type_name(node));
+    }
+
+    #[rstest]
+    fn get_node_of_kind_allows_further_traversal(test_yaml: Value) {
+        let car = get_node_of_kind(
+            &test_yaml["cars_owned"][0],
+            "last_inspection",
+            ValueKind::Mapping,
+        )
+        .unwrap();
+        assert_eq!(
+            "2020-01-05",
+            get_typed_value_by_path::<str>(car, "date").unwrap()
+        );
+    }
+
+    #[rstest]
+    fn get_node_of_kind_returns_error_when_kind_differs(test_yaml: Value) {
+        let err = get_node_of_kind(&test_yaml, "cars_owned", ValueKind::Mapping).unwrap_err();
+        assert_eq!(
+            "Expected mapping at path `cars_owned`, found sequence",
+            err.to_string()
+        );
+        assert!(get_node_of_kind(&test_yaml, "missing", ValueKind::Null).is_err());
+    }
+
+    #[rstest]
+    #[case("~", false)]
+    #[case("false", false)]
+    #[case("0", false)]
+    #[case("0.0", false)]
+    #[case("-0.0", false)]
+    #[case("''", false)]
+    #[case("[]", false)]
+    #[case("{}", false)]
+    #[case("true", true)]
+    #[case("1", true)]
+    #[case("-1", true)]
+    #[case("0.5", true)]
+    #[case(".nan", true)]
+    #[case("'false'", true)]
+    #[case("'0'", true)]
+    #[case("' '", true)]
+    #[case("[~]", true)]
+    #[case("{ a: ~ }", true)]
+    fn as_truthy_follows_truthiness_rules(#[case] yaml: &str, #[case] expected: bool) {
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(expected, as_truthy(&value));
+    }
+
+    #[derive(Debug)]
+    struct Car<'a> {
+        name: &'a str,
+        age: u64,
+        last_inspection: &'a Mapping,
+    }
+
+    #[rstest]
+    fn extract_builds_struct_from_paths(test_yaml: Value) {
+        let car = crate::extract!(&test_yaml["cars_owned"][0] => Car {
+            name: str = "name",
+            age: u64 = "age",
+            last_inspection: Mapping = "last_inspection",
+        })
+        .unwrap();
+
+        assert_eq!("Ford Mustang", car.name);
+        assert_eq!(5, car.age);
+        assert_eq!(1, car.last_inspection.len());
+    }
+
+    #[rstest]
+    fn extract_reports_every_failed_field(test_yaml: Value) {
+        let err = crate::extract!(&test_yaml => Car {
+            name: str = "name",
+            age: u64 = "score",
+            last_inspection: Mapping = "last_inspection",
+        })
+        .unwrap_err()
+        .to_string();
+
+        assert!(err.starts_with("Could not extract 2 field(s): "));
+        assert!(err.contains("`age`"));
+        assert!(err.contains("`last_inspection`"));
+        assert!(!err.contains("`name`"));
+    }
+
+    #[rstest]
+    #[case("tags: web", vec!["web"])]
+    #[case("tags: [web, api]", vec!["web", "api"])]
+    #[case("tags: []", vec![])]
+    fn get_string_list_returns_list_for_string_or_sequence(
+        #[case] yaml: &str,
+        #[case] expected: Vec<&str>,
+    ) {
+        let yaml: Value = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(expected, get_string_list(&yaml, "tags").unwrap());
+    }
+
+    #[rstest]
+    #[case("tags: 1")]
+    #[case("tags: { web: true }")]
+    #[case("tags: [web, 1]")]
+    #[case("other: web")]
+    fn get_string_list_returns_error_when_invalid_value_is_found(#[case] yaml: &str) {
+        let yaml: Value = serde_yaml::from_str(yaml).unwrap();
+        assert!(get_string_list(&yaml, "tags").is_err());
+    }
+
+    #[rstest]
+    #[case("tags: web", vec!["web"])]
+    #[case("tags: ' web, api ,,db, '", vec!["web", "api", "db"])]
+    #[case("tags: ' , '", vec![])]
+    #[case("tags: [web, 'api,db']", vec!["web", "api,db"])]
+    fn get_csv_list_splits_strings_on_commas(#[case] yaml: &str, #[case] expected: Vec<&str>) {
+        let yaml: Value = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(expected, get_csv_list(&yaml, "tags").unwrap());
+    }
+
+    #[rstest]
+    #[case("tags: 1")]
+    #[case("tags: [web, 1]")]
+    #[case("other: web")]
+    fn get_csv_list_returns_error_when_invalid_value_is_found(#[case] yaml: &str) {
+        let yaml: Value = serde_yaml::from_str(yaml).unwrap();
+        assert!(get_csv_list(&yaml, "tags").is_err());
+    }
+
+    #[rstest]
+    #[case(&test_yaml(), "cars_owned", 1)]
+    #[case(&test_yaml()["cars_owned"][0], "last_inspection", 1)]
+    fn len_at_path_returns_number_of_elements(
+        #[case] input_yml: &Value,
+        #[case] path: &str,
+        #[case] expected: usize,
+    ) {
+        assert_eq!(expected, len_at_path(input_yml, path).unwrap());
+    }
+
+    #[rstest]
+    #[case("name")]
+    #[case("age")]
+    #[case("invalid")]
+    fn len_at_path_returns_error_when_collection_not_found(#[case] path: &str, test_yaml: Value) {
+        assert!(len_at_path(&test_yaml, path).is_err());
+    }
+
+    #[rstest]
+    fn entries_at_path_yields_string_keyed_entries_in_order(test_yaml: Value) {
+        let entries: Vec<_> = entries_at_path(&test_yaml["cars_owned"][0], "last_inspection")
+            .unwrap()
+            .collect();
+        assert_eq!(
+            vec![(
+                "date",
+                &test_yaml["cars_owned"][0]["last_inspection"]["date"]
+            )],
+            entries
+        );
+    }
+
+    #[rstest]
+    fn entries_at_path_skips_non_string_keys() {
+        let yaml: Value = serde_yaml::from_str("map: { a: 1, 2: 2, b: 3 }").unwrap();
+        let keys: Vec<_> = entries_at_path(&yaml, "map")
+            .unwrap()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(vec!["a", "b"], keys);
+    }
+
+    #[rstest]
+    #[case("name")]
+    #[case("invalid")]
+    fn entries_at_path_returns_error_when_mapping_not_found(#[case] path: &str, test_yaml: Value) {
+        assert!(entries_at_path(&test_yaml, path).is_err());
+    }
+
+    #[rstest]
+    fn try_map_entries_transforms_entries_in_order(test_yaml: Value) {
+        assert!(try_map_entries(&test_yaml, "cars_owned", |_, _| Ok(())).is_err());
+
+        let car = &test_yaml["cars_owned"][0];
+        let entries = try_map_entries(car, "last_inspection", |key, value| {
+            Ok(format!("{}={}", key, <str as FromYaml>::try_from(value)?))
+        })
+        .unwrap();
+        assert_eq!(vec!["date=2020-01-05"], entries);
+    }
+
+    #[rstest]
+    fn try_map_entries_reports_failing_key() {
+        let yaml: Value = serde_yaml::from_str("limits: { cpu: 2, memory: big, disk: x }").unwrap();
+        let calls = std::cell::Cell::new(0);
+        let err = try_map_entries(&yaml, "limits", |_, value| {
+            calls.set(calls.get() + 1);
+            <u64 as FromYaml>::try_from(value)
+        })
+        .unwrap_err();
+
+        assert_eq!(2, calls.get());
+        assert_eq!(
+            "Invalid entry `memory` of `limits`: Could not parse requested yaml value: expected u64, found string",
+            err.to_string()
+        );
+        assert_eq!(Some("Input object: String(\"big\")"), err.debug_detail());
+    }
+
+    #[rstest]
+    #[case(&["name", "adult", "age", "score", "rank_delta", "cars_owned"])]
+    #[case(&["name", "adult", "age", "score", "rank_delta", "cars_owned", "missing"])]
+    fn assert_only_keys_accepts_allowed_keys(#[case] allowed: &[&str], test_yaml: Value) {
+        assert!(assert_only_keys(&test_yaml, allowed).is_ok());
+    }
+
+    #[rstest]
+    fn assert_only_keys_lists_unexpected_keys(test_yaml: Value) {
+        let err = assert_only_keys(&test_yaml, &["name", "age", "cars_owned"]).unwrap_err();
+        assert_eq!(
+            "Unexpected top-level keys: `adult`, `score`, `rank_delta`",
+            err.to_string()
+        );
+        assert_eq!(
+            Some("Allowed keys: name, age, cars_owned"),
+            err.debug_detail()
+        );
+    }
+
+    #[rstest]
+    fn assert_only_keys_reports_non_string_keys() {
+        let yaml: Value = serde_yaml::from_str("{ name: x, 1: y, true: z }").unwrap();
+        let err = assert_only_keys(&yaml, &["name", "1"]).unwrap_err();
+        assert_eq!("Unexpected top-level keys: `1`, `true`", err.to_string());
+    }
+
+    #[rstest]
+    #[case(Value::Null)]
+    #[case(Value::from("name"))]
+    #[case(Value::Sequence(vec![Value::from("name")]))]
+    fn assert_only_keys_returns_error_when_document_is_not_mapping(#[case] value: Value) {
+        assert!(assert_only_keys(&value, &["name"]).is_err());
+    }
+
+    #[rstest]
+    fn expand_merge_keys_merges_single_mapping_with_local_keys_winning() {
+        let mut yaml: Value = serde_yaml::from_str(
+            r#"
+            base: &base { image: alpine, shell: sh }
+            job:
+                <<: *base
+                image: debian
+        "#,
+        )
+        .unwrap();
+        expand_merge_keys(&mut yaml).unwrap();
+
+        let expected: Value = serde_yaml::from_str("{ image: debian, shell: sh }").unwrap();
+        assert_eq!(expected, yaml["job"]);
+    }
+
+    #[rstest]
+    fn expand_merge_keys_merges_list_of_mappings_with_earlier_winning() {
+        let mut yaml: Value = serde_yaml::from_str(
+            r#"
+            first: &first { a: 1, b: 1 }
+            second: &second { b: 2, c: 2 }
+            job:
+                <<: [*first, *second]
+                c: 3
+        "#,
+        )
+        .unwrap();
+        expand_merge_keys(&mut yaml).unwrap();
+
+        let expected: Value = serde_yaml::from_str("{ c: 3, a: 1, b: 1 }").unwrap();
+        assert_eq!(expected, yaml["job"]);
+    }
+
+    #[rstest]
+    fn expand_merge_keys_keeps_order_of_local_keys() {
+        let mut yaml: Value = serde_yaml::from_str(
+            r#"
+            base: &base { b: 1, x: 0 }
+            job: { a: 1, <<: *base, x: 2, y: 3, z: 4 }
+        "#,
+        )
+        .unwrap();
+        expand_merge_keys(&mut yaml).unwrap();
+
+        let keys: Vec<_> = yaml["job"]
+            .as_mapping()
+            .unwrap()
+            .iter()
+            .map(|(k, _)| k.as_str().unwrap())
+            .collect();
+        assert_eq!(vec!["a", "x", "y", "z", "b"], keys);
+        assert_eq!(Value::from(2), yaml["job"]["x"]);
+    }
+
+    #[rstest]
+    fn expand_merge_keys_expands_nested_mappings() {
+        let mut yaml: Value = serde_yaml::from_str(
+            r#"
+            inner: &inner { x: 1 }
+            outer: &outer { nested: { <<: *inner, y: 2 } }
+            jobs:
+                - <<: *outer
+        "#,
+        )
+        .unwrap();
+        expand_merge_keys(&mut yaml).unwrap();
+
+        let expected: Value = serde_yaml::from_str("{ nested: { y: 2, x: 1 } }").unwrap();
+        assert_eq!(expected, yaml["jobs"][0]);
+    }
+
+    #[rstest]
+    #[case("job: { <<: 5 }")]
+    #[case("job: { <<: [{ a: 1 }, 5] }")]
+    fn expand_merge_keys_returns_error_when_invalid_merge_value_is_found(#[case] yaml: &str) {
+        let mut yaml: Value = serde_yaml::from_str(yaml).unwrap();
+        assert!(expand_merge_keys(&mut yaml).is_err());
+    }
+
+    #[rstest]
+    fn merge_merges_mappings_recursively() {
+        let mut base: Value = serde_yaml::from_str(
+            "{ image: alpine, env: { A: 1, B: 2 }, args: [x, y], keep: true }",
+        )
+        .unwrap();
+        let overlay: Value =
+            serde_yaml::from_str("{ env: { B: 3, C: 4 }, args: [z], image: debian, new: ~ }")
+                .unwrap();
+        merge(&mut base, &overlay).unwrap();
+
+        let expected: Value = serde_yaml::from_str(
+            "{ image: debian, env: { A: 1, B: 3, C: 4 }, args: [z], keep: true, new: ~ }",
+        )
+        .unwrap();
+        assert_eq!(expected, base);
+    }
+
+    #[rstest]
+    #[case("{ a: { b: 1 } }", "{ a: 2 }", "{ a: 2 }")]
+    #[case("{ a: 1 }", "{ a: { b: 2 } }", "{ a: { b: 2 } }")]
+    #[case("{ a: 1 }", "[1, 2]", "[1, 2]")]
+    #[case("[1, 2]", "{ a: 1 }", "{ a: 1 }")]
+    fn merge_replaces_values_of_different_kinds(
+        #[case] base: &str,
+        #[case] overlay: &str,
+        #[case] expected: &str,
+    ) {
+        let mut base: Value = serde_yaml::from_str(base).unwrap();
+        merge(&mut base, &serde_yaml::from_str(overlay).unwrap()).unwrap();
+        assert_eq!(serde_yaml::from_str::<Value>(expected).unwrap(), base);
+    }
+
+    #[rstest]
+    fn merge_reporting_returns_paths_of_overridden_scalars(test_yaml: Value) {
+        let mut base = test_yaml.clone();
+        let overlay: Value = serde_yaml::from_str(
+            r#"
+            age: 23
+            adult: true
+            name: { first: John }
+            cars_owned: []
+            score: ~
+            extra: { nested: 1 }
+            "#,
+        )
+        .unwrap();
+        let overridden = merge_reporting(&mut base, &overlay).unwrap();
+
+        assert_eq!(vec!["age", "score"], overridden);
+        let mut merged = test_yaml;
+        merge(&mut merged, &overlay).unwrap();
+        assert_eq!(merged, base);
+    }
+
+    #[rstest]
+    fn merge_reporting_returns_nested_paths() {
+        let mut base: Value = serde_yaml::from_str("{ a: { b: { c: 1, d: 2 } }, 5: x }").unwrap();
+        let overlay: Value = serde_yaml::from_str("{ a: { b: { d: 3 } }, 5: y }").unwrap();
+        assert_eq!(
+            vec!["a.b.d", "5"],
+            merge_reporting(&mut base, &overlay).unwrap()
+        );
+    }
+
+    #[rstest]
+    fn merge_returns_error_when_mappings_are_nested_beyond_depth_limit() {
+        let wrap = |depth: usize, leaf: Value| {
+            (0..depth).fold(leaf, |acc, _| {
+                let mut map = Mapping::new();
+                map.insert(Value::from("k"), acc);
+                Value::Mapping(map)
+            })
+        };
+        let leaf = |key: &str| serde_yaml::from_str::<Value>(&format!("{{ {}: 1 }}", key)).unwrap();
+        let mut base = wrap(DEFAULT_MAX_DEPTH + 1, leaf("a"));
+        let overlay = wrap(DEFAULT_MAX_DEPTH + 1, leaf("b"));
+        assert_eq!(
+            format!("Maximum nesting depth of {} exceeded", DEFAULT_MAX_DEPTH),
+            merge(&mut base, &overlay).unwrap_err().to_string()
+        );
+        assert!(merge_reporting(&mut base, &overlay).is_err());
+
+        let mut base = wrap(DEFAULT_MAX_DEPTH, leaf("a"));
+        merge(&mut base, &wrap(DEFAULT_MAX_DEPTH, leaf("b"))).unwrap();
+        assert_eq!(1, count_key(&base, "a"));
+        assert_eq!(1, count_key(&base, "b"));
+    }
+
+    #[rstest]
+    fn to_yaml_canonical_sorts_keys_recursively() {
+        let yaml: Value =
+            serde_yaml::from_str("b: { z: 1, a: [{ y: 1, x: 2 }] }\na: true").unwrap();
+        let expected: Value =
+            serde_yaml::from_str("a: true\nb: { a: [{ x: 2, y: 1 }], z: 1 }").unwrap();
+        assert_eq!(
+            serde_yaml::to_string(&expected).unwrap(),
+            to_yaml_canonical(&yaml).unwrap()
+        );
+    }
+
+    #[rstest]
+    fn to_yaml_canonical_is_independent_of_key_order() {
+        let first: Value = serde_yaml::from_str("{ a: 1, b: { c: 2, d: 3 } }").unwrap();
+        let second: Value = serde_yaml::from_str("{ b: { d: 3, c: 2 }, a: 1 }").unwrap();
+        assert_eq!(
+            to_yaml_canonical(&first).unwrap(),
+            to_yaml_canonical(&second).unwrap()
+        );
+    }
+
+    #[rstest]
+    fn content_hash_is_independent_of_key_order() {
+        let first: Value = serde_yaml::from_str("{ a: 1, b: { c: 2, d: 3 } }").unwrap();
+        let second: Value = serde_yaml::from_str("{ b: { d: 3, c: 2 }, a: 1 }").unwrap();
+        assert_eq!(
+            content_hash(&first).unwrap(),
+            content_hash(&second).unwrap()
+        );
+    }
+
+    #[rstest]
+    fn content_hash_differs_for_different_values(test_yaml: Value) {
+        let mut modified = test_yaml.clone();
+        modified["age"] = Value::from(23);
+        assert_ne!(
+            content_hash(&test_yaml).unwrap(),
+            content_hash(&modified).unwrap()
+        );
+    }
+
+    #[rstest]
+    fn content_hash_is_stable() {
+        let yaml: Value = serde_yaml::from_str("{ b: 2, a: 1 }").unwrap();
+        let canonical = to_yaml_canonical(&yaml).unwrap();
+        let expected = canonical
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+            });
+        assert_eq!(expected, content_hash(&yaml).unwrap());
+        assert_eq!(0x5ef6_7fa3_eb41_da6c, content_hash(&Value::Null).unwrap());
+    }
+
+    #[rstest]
+    #[case("name: Mark\nlist: [1, 'two', ~]\nnested: { flag: true, rate: 1.5 }")]
+    #[case("# comment\nkey: value # trailing")]
+    #[case("base: &base { a: 1 }\nderived: *base")]
+    #[case("quoted: '0x10'")]
+    fn round_trips_returns_true_when_document_is_stable(#[case] text: &str) {
+        assert!(round_trips(text).unwrap());
+    }
+
+    #[rstest]
+    #[case("octal: '0o7'")]
+    #[case("nested: { list: [a, '0o17'] }")]
+    fn round_trips_returns_false_when_document_changes(#[case] text: &str) {
+        assert!(!round_trips(text).unwrap());
+    }
+
+    #[rstest]
+    #[case("name: Mark", None)]
+    #[case("octal: '0o7'", Some(("octal", Value::from("0o7"), Value::from(7))))]
+    #[case("nested: { list: [a, '0o17'] }", Some(("nested.list.1", Value::from("0o17"), Value::from(15))))]
+    #[case("'0o7'", Some(("", Value::from("0o7"), Value::from(7))))]
+    fn round_trip_difference_reports_first_differing_node(
+        #[case] text: &str,
+        #[case] expected: Option<(&str, Value, Value)>,
+    ) {
+        let expected = expected.map(|(path, original, reparsed)| RoundTripDifference {
+            path: path.to_string(),
+            original,
+            reparsed,
+        });
+        assert_eq!(expected, round_trip_difference(text).unwrap());
+    }
+
+    #[rstest]
+    fn round_trip_difference_is_displayed_with_both_values() {
+        let difference = round_trip_difference("octal: '0o7'").unwrap().unwrap();
+        assert_eq!(
+            "it differs at `octal`: String(\"0o7\") is read back as Number(PosInt(7))",
+            difference.to_string()
+        );
+    }
+
+    #[rstest]
+    #[case(Value::from("0o7"), Value::from(7), Some(""))]
+    #[case(Value::from(1), Value::from(1), None)]
+    fn first_difference_finds_differing_node(
+        #[case] first: Value,
+        #[case] second: Value,
+        #[case] expected: Option<&str>,
+    ) {
+        assert_eq!(
+            expected.map(str::to_string),
+            first_difference(&first, &second, &mut Vec::new()).map(|(path, ..)| path)
+        );
+    }
+
+    #[rstest]
+    fn first_difference_reports_path_of_nested_node() {
+        let first: Value = serde_yaml::from_str("a: { b: [1, '0o7'] }").unwrap();
+        let second: Value = serde_yaml::from_str("a: { b: [1, 7] }").unwrap();
+        assert_eq!(
+            Some((
+                "a.b.1".to_string(),
+                &first["a"]["b"][1],
+                &second["a"]["b"][1]
+            )),
+            first_difference(&first, &second, &mut Vec::new())
+        );
+
+        let second: Value = serde_yaml::from_str("a: { c: [1, '0o7'] }").unwrap();
+        assert_eq!(
+            Some("a".to_string()),
+            first_difference(&first, &second, &mut Vec::new()).map(|(path, ..)| path)
+        );
+    }
+
+    #[rstest]
+    fn round_trips_returns_error_when_document_is_malformed() {
+        assert!(round_trips("key: [unterminated").is_err());
+    }
+
+    #[rstest]
+    fn walk_visits_every_node_in_pre_order(test_yaml: Value) {
+        let mut paths = Vec::new();
+        walk(&test_yaml, &mut |path, _| paths.push(path.join("."))).unwrap();
+        assert_eq!(
+            vec![
+                "",
+                "name",
+                "adult",
+                "age",
+                "score",
+                "rank_delta",
+                "cars_owned",
+                "cars_owned.0",
+                "cars_owned.0.name",
+                "cars_owned.0.age",
+                "cars_owned.0.last_inspection",
+                "cars_owned.0.last_inspection.date",
+            ],
+            paths
+        );
+    }
+
+    #[rstest]
+    fn walk_passes_node_matching_path(test_yaml: Value) {
+        walk(&test_yaml, &mut |path, node| {
+            if path == ["cars_owned", "0", "last_inspection", "date"] {
+                assert_eq!("2020-01-05", node.as_str().unwrap());
+            }
+        })
+        .unwrap();
+    }
+
+    #[rstest]
+    #[case("name", 2)]
+    #[case("age", 2)]
+    #[case("date", 1)]
+    #[case("cars_owned", 1)]
+    #[case("John Doe", 0)]
+    #[case("missing", 0)]
+    fn count_key_counts_entries_at_every_level(
+        #[case] key: &str,
+        #[case] expected: usize,
+        test_yaml: Value,
+    ) {
+        assert_eq!(expected, count_key(&test_yaml, key));
+    }
+
+    #[rstest]
+    fn count_key_ignores_non_string_keys_and_values() {
+        let yaml: Value =
+            serde_yaml::from_str("{ 1: a, image: { image: x }, list: [image, { image: y }] }")
+                .unwrap();
+        assert_eq!(3, count_key(&yaml, "image"));
+        assert_eq!(0, count_key(&yaml, "1"));
+        assert_eq!(0, count_key(&Value::from("image"), "image"));
+    }
+
+    #[rstest]
+    #[case(3, 3, true)]
+    #[case(3, 2, false)]
+    #[case(0, 0, true)]
+    fn walk_with_max_depth_limits_nesting(
+        #[case] depth: usize,
+        #[case] max_depth: usize,
+        #[case] ok: bool,
+    ) {
+        let value = nested(depth);
+        let mut visited = 0;
+        let result = walk_with_max_depth(&value, max_depth, &mut |_, _| visited += 1);
+        assert_eq!(ok, result.is_ok());
+        assert_eq!(depth.min(max_depth) + 1, visited);
+    }
+
+    #[rstest]
+    #[case(3, 3, true)]
+    #[case(3, 2, false)]
+    #[case(0, 0, true)]
+    fn recursive_operations_respect_custom_depth_limit(
+        #[case] depth: usize,
+        #[case] max_depth: usize,
+        #[case] ok: bool,
+    ) {
+        let mapping = |depth: usize| {
+            (0..depth).fold(Value::from(1), |acc, _| {
+                let mut map = Mapping::new();
+                map.insert(Value::from("k"), acc);
+                Value::Mapping(map)
+            })
+        };
+        let expected = format!("Maximum nesting depth of {} exceeded", max_depth);
+        let check = |result: Result<()>| match result {
+            Ok(()) => assert!(ok),
+            Err(e) => assert_eq!((false, expected.as_str()), (ok, e.to_string().as_str())),
+        };
+
+        check(to_yaml_canonical_with_max_depth(&nested(depth), max_depth).map(drop));
+        check(expand_merge_keys_with_max_depth(
+            &mut nested(depth),
+            max_depth,
+        ));
+        // Merged mappings are one level shallower than the deepest node
+        check(merge_with_max_depth(
+            &mut mapping(depth + 1),
+            &mapping(depth + 1),
+            max_depth,
+        ));
+        check(
+            merge_reporting_with_max_depth(&mut mapping(depth + 1), &mapping(depth + 1), max_depth)
+                .map(drop),
+        );
+    }
+
+    #[rstest]
+    fn recursive_operations_return_error_when_default_depth_is_exceeded() {
+        let mut value = nested(DEFAULT_MAX_DEPTH + 1);
+        let expected = format!("Maximum nesting depth of {} exceeded", DEFAULT_MAX_DEPTH);
+
+        assert_eq!(
+            expected,
+            walk(&value, &mut |_, _| {}).unwrap_err().to_string()
+        );
+        assert_eq!(expected, to_yaml_canonical(&value).unwrap_err().to_string());
+        assert_eq!(expected, content_hash(&value).unwrap_err().to_string());
+        assert_eq!(
+            expected,
+            expand_merge_keys(&mut value).unwrap_err().to_string()
+        );
+        assert!(walk(&nested(DEFAULT_MAX_DEPTH), &mut |_, _| {}).is_ok());
+    }
+
+    #[rstest]
+    fn get_value_by_path_returns_error_when_empty_path_is_passed(test_yaml: Value) {
+        assert!(get_value_by_path(&test_yaml, "").is_err());
+        assert_eq!(
+            "Path must not be empty",
+            get_value_by_path(&test_yaml, "").unwrap_err().to_string()
+        );
+    }
+
+    #[rstest]
+    #[case(".", 1)]
+    #[case("..", 1)]
+    #[case(".key", 1)]
+    #[case("key1.key2.", 3)]
+    #[case("name..first", 2)]
     fn get_value_by_path_returns_error_when_invalid_path_is_passed(
         #[case] path: &str,
+        #[case] position: usize,
+        test_yaml: Value,
+    ) {
+        assert!(get_value_by_path(&test_yaml, path).is_err());
+        assert_eq!(
+            format!(
+                "Path `{}` contains an empty segment at position {}",
+                path, position
+            ),
+            get_value_by_path(&test_yaml, path).unwrap_err().to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("age.")]
+    fn path_operations_return_error_when_malformed_path_is_passed(
+        #[case] path: &str,
+        mut test_yaml: Value,
+    ) {
+        let expected = get_value_by_path(&test_yaml, path).unwrap_err().to_string();
+        let cursor = Cursor::at(&test_yaml["cars_owned"][0], "last_inspection").unwrap();
+
+        assert_eq!(expected, cursor.get(path).unwrap_err().to_string());
+        assert_eq!(
+            expected,
+            get_value_by_path_or(&test_yaml, path, Value::Null)
+                .unwrap_err()
+                .to_string()
+        );
+        let original = test_yaml.clone();
+        assert_eq!(
+            expected,
+            replace_value_by_path(&mut test_yaml, path, Value::Null)
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            expected,
+            get_or_insert_mut(&mut test_yaml, path, || Value::Null)
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(original, test_yaml);
+    }
+
+    #[rstest]
+    #[case("invalid.invalid")]
+    #[case("name.invalid")]
+    #[case("cars_owned.invalid")]
+    // Sequence indices not supported
+    #[case("cars_owned.0.name")]
+    #[case("cars_owned.0.last_inspection")]
+    fn get_value_by_path_returns_error_when_non_existing_path_is_passed(
+        #[case] path: &str,
+        test_yaml: Value,
+    ) {
+        assert!(get_value_by_path(&test_yaml, path).is_err());
+    }
+
+    #[rstest]
+    #[case(&test_yaml(), "name")]
+    #[case(&test_yaml(), "cars_owned")]
+    #[case(&test_yaml()["cars_owned"][0], "name")]
+    #[case(&test_yaml()["cars_owned"][0], "age")]
+    #[case(&test_yaml()["cars_owned"][0], "last_inspection")]
+    #[case(&test_yaml()["cars_owned"][0], "last_inspection.date")]
+    fn get_value_by_path_returns_reference_when_existing_path_is_passed(
+        #[case] input_yml: &Value,
+        #[case] path: &str,
+    ) {
+        get_value_by_path(input_yml, path).unwrap();
+    }
+
+    #[rstest]
+    fn get_owned_value_by_path_returns_copy_of_node(test_yaml: Value) {
+        let owned = get_owned_value_by_path(&test_yaml, "cars_owned").unwrap();
+        drop(test_yaml);
+        assert_eq!(1, owned.as_sequence().unwrap().len());
+    }
+
+    #[rstest]
+    fn get_owned_value_by_path_returns_error_when_non_existing_path_is_passed(test_yaml: Value) {
+        assert!(get_owned_value_by_path(&test_yaml, "invalid").is_err());
+    }
+
+    #[rstest]
+    fn get_value_by_path_or_else_borrows_existing_value(test_yaml: Value) {
+        let value = get_value_by_path_or_else(&test_yaml, "name", || unreachable!()).unwrap();
+        assert!(matches!(value, Cow::Borrowed(Value::String(s)) if s == "John Doe"));
+    }
+
+    #[rstest]
+    #[case("missing")]
+    #[case("missing.key")]
+    #[case("cars_owned_count")]
+    fn get_value_by_path_or_else_returns_default_when_key_is_missing(
+        #[case] path: &str,
+        test_yaml: Value,
+    ) {
+        let mut calls = 0;
+        let value = get_value_by_path_or_else(&test_yaml, path, || {
+            calls += 1;
+            Value::from(7)
+        })
+        .unwrap();
+        assert_eq!(Value::from(7), *value);
+        assert_eq!(1, calls);
+    }
+
+    #[rstest]
+    #[case("name.first", "Path `name.first` traverses a string at `name`")]
+    #[case(
+        "cars_owned.name",
+        "Path `cars_owned.name` traverses a sequence at `cars_owned`"
+    )]
+    fn get_value_by_path_or_else_returns_error_when_non_mapping_is_traversed(
+        #[case] path: &str,
+        #[case] expected: &str,
+        test_yaml: Value,
+    ) {
+        let err = get_value_by_path_or_else(&test_yaml, path, || unreachable!()).unwrap_err();
+        assert_eq!(expected, err.to_string());
+    }
+
+    #[rstest]
+    #[case(&["nickname", "name"], "John Doe")]
+    #[case(&["age", "name"], 22)]
+    #[case(&["name.first", "cars_owned.name", "adult"], true)]
+    fn get_first_present_returns_first_found_value<T: Into<Value>>(
+        #[case] paths: &[&str],
+        #[case] expected: T,
+        test_yaml: Value,
+    ) {
+        assert_eq!(
+            &expected.into(),
+            get_first_present(&test_yaml, paths).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case(
+        &["nickname", "alias.name"],
+        "None of paths `nickname`, `alias.name` was found within the input object"
+    )]
+    #[case(&[], "No candidate paths were given")]
+    #[case(&["missing", "name."], "Path `name.` contains an empty segment at position 2")]
+    fn get_first_present_returns_error_when_no_path_is_found(
+        #[case] paths: &[&str],
+        #[case] expected: &str,
+        test_yaml: Value,
+    ) {
+        let err = get_first_present(&test_yaml, paths).unwrap_err();
+        assert_eq!(expected, err.to_string());
+    }
+
+    #[rstest]
+    fn get_value_with_fallback_prefers_primary_object(test_yaml: Value) {
+        let fallback: Value = serde_yaml::from_str("{ name: Jane, nickname: JD }").unwrap();
+
+        let name = get_value_with_fallback(&test_yaml, &fallback, "name").unwrap();
+        assert_eq!(&test_yaml["name"], name);
+        let nickname = get_value_with_fallback(&test_yaml, &fallback, "nickname").unwrap();
+        assert_eq!(&fallback["nickname"], nickname);
+    }
+
+    #[rstest]
+    #[case(
+        "alias",
+        "Path `alias` was found neither within the primary nor within the fallback object"
+    )]
+    #[case("", "Path must not be empty")]
+    fn get_value_with_fallback_returns_error_when_both_miss(
+        #[case] path: &str,
+        #[case] expected: &str,
+        test_yaml: Value,
+    ) {
+        let fallback: Value = serde_yaml::from_str("{ nickname: JD }").unwrap();
+        let err = get_value_with_fallback(&test_yaml, &fallback, path).unwrap_err();
+        assert_eq!(expected, err.to_string());
+    }
+
+    #[rstest]
+    fn get_value_by_path_or_returns_default_when_key_is_missing(test_yaml: Value) {
+        let value = get_value_by_path_or(&test_yaml, "nickname", Value::Null).unwrap();
+        assert!(matches!(value, Cow::Owned(Value::Null)));
+        assert!(get_value_by_path_or(&test_yaml, "age.years", Value::Null).is_err());
+    }
+
+    #[rstest]
+    #[case("name", "str")]
+    #[case("adult", "bool")]
+    #[case("age", "u64")]
+    #[case("rank_delta", "i64")]
+    #[case("score", "f64")]
+    #[case("cars_owned", "sequence")]
+    fn converter_registry_converts_builtin_types(
+        #[case] path: &str,
+        #[case] name: &str,
+        test_yaml: Value,
+    ) {
+        let registry = ConverterRegistry::with_builtin();
+        let converted = registry.get_value_as(&test_yaml, path, name).unwrap();
+        let node = &test_yaml[path];
+        match name {
+            "str" => assert_eq!(
+                node.as_str(),
+                converted.downcast_ref::<String>().map(String::as_str)
+            ),
+            "bool" => assert_eq!(node.as_bool(), converted.downcast_ref().copied()),
+            "u64" => assert_eq!(node.as_u64(), converted.downcast_ref().copied()),
+            "i64" => assert_eq!(node.as_i64(), converted.downcast_ref().copied()),
+            "f64" => assert_eq!(node.as_f64(), converted.downcast_ref().copied()),
+            _ => assert_eq!(node.as_sequence(), converted.downcast_ref()),
+        }
+    }
+
+    #[rstest]
+    fn converter_registry_uses_registered_converter(test_yaml: Value) {
+        let mut registry = ConverterRegistry::new();
+        assert!(!registry.contains("initials"));
+        registry.register("initials", |v| {
+            let name = <str as FromYaml>::try_from(v)?;
+            Ok(Box::new(
+                name.split(' ')
+                    .filter_map(|w| w.chars().next())
+                    .collect::<String>(),
+            ))
+        });
+        assert!(registry.contains("initials"));
+
+        let converted = registry
+            .get_value_as(&test_yaml, "name", "initials")
+            .unwrap();
+        assert_eq!(
+            Some("JD"),
+            converted.downcast_ref::<String>().map(String::as_str)
+        );
+        assert!(registry
+            .get_value_as(&test_yaml, "age", "initials")
+            .is_err());
+    }
+
+    #[rstest]
+    fn converter_registry_register_replaces_previous_converter() {
+        let mut registry = ConverterRegistry::with_builtin();
+        assert!(registry.register("u64", |_| Ok(Box::new(0_u64))).is_some());
+        let converted = registry.convert(&Value::from(5), "u64").unwrap();
+        assert_eq!(Some(&0), converted.downcast_ref::<u64>());
+    }
+
+    #[rstest]
+    fn converter_registry_lists_sorted_names() {
+        let mut registry = ConverterRegistry::new();
+        assert!(registry.names().is_empty());
+        registry.register("u64", |_| Ok(Box::new(0_u64)));
+        registry.register("bool", |_| Ok(Box::new(false)));
+        assert_eq!(vec!["bool", "u64"], registry.names());
+        assert!(ConverterRegistry::with_builtin()
+            .names()
+            .contains(&"mapping"));
+    }
+
+    #[rstest]
+    #[case("age", "ipaddr", "Unknown type `ipaddr`")]
+    #[case(
+        "name",
+        "u64",
+        "Could not parse requested yaml value: expected u64, found string"
+    )]
+    #[case(
+        "missing",
+        "u64",
+        "Path `missing` was not found within the input object"
+    )]
+    fn converter_registry_returns_error_when_conversion_fails(
+        #[case] path: &str,
+        #[case] name: &str,
+        #[case] expected: &str,
         test_yaml: Value,
     ) {
-        assert!(get_value_by_path(&test_yaml, path).is_err());
+        let registry = ConverterRegistry::with_builtin();
+        let err = registry.get_value_as(&test_yaml, path, name).unwrap_err();
+        assert_eq!(expected, err.to_string());
     }
 
     #[rstest]
-    #[case("invalid.invalid")]
-    #[case("name.invalid")]
-    #[case("cars_owned.invalid")]
-    // Sequence indices not supported
-    #[case("cars_owned.0.name")]
-    #[case("cars_owned.0.last_inspection")]
-    fn get_value_by_path_returns_error_when_non_existing_path_is_passed(
+    fn get_or_insert_mut_returns_existing_value(mut test_yaml: Value) {
+        let age = get_or_insert_mut(&mut test_yaml, "age", || unreachable!()).unwrap();
+        *age = Value::from(23);
+        assert_eq!(Value::from(23), test_yaml["age"]);
+    }
+
+    #[rstest]
+    fn get_or_insert_mut_creates_missing_nodes(mut test_yaml: Value) {
+        let mut calls = 0;
+        let value = get_or_insert_mut(&mut test_yaml, "address.home.city", || {
+            calls += 1;
+            Value::from("Paris")
+        })
+        .unwrap();
+        assert_eq!(Value::from("Paris"), *value);
+        assert_eq!(1, calls);
+        assert_eq!(Value::from("Paris"), test_yaml["address"]["home"]["city"]);
+        assert_eq!(Value::from("John Doe"), test_yaml["name"]);
+    }
+
+    #[rstest]
+    fn get_or_insert_mut_replaces_null_nodes() {
+        let mut value: Value = serde_yaml::from_str("env: ~").unwrap();
+        get_or_insert_mut(&mut value, "env.MODE", || Value::from("fast")).unwrap();
+        assert_eq!(Value::from("fast"), value["env"]["MODE"]);
+    }
+
+    #[rstest]
+    #[case("name.first", "Path `name.first` traverses a string at `name`")]
+    #[case(
+        "cars_owned.0.name",
+        "Path `cars_owned.0.name` traverses a sequence at `cars_owned`"
+    )]
+    fn get_or_insert_mut_returns_error_when_non_mapping_is_traversed(
         #[case] path: &str,
-        test_yaml: Value,
+        #[case] expected: &str,
+        mut test_yaml: Value,
     ) {
-        assert!(get_value_by_path(&test_yaml, path).is_err());
+        let original = test_yaml.clone();
+        let err = get_or_insert_mut(&mut test_yaml, path, || unreachable!()).unwrap_err();
+        assert_eq!(expected, err.to_string());
+        assert_eq!(original, test_yaml);
     }
 
     #[rstest]
-    #[case(&test_yaml(), "name")]
-    #[case(&test_yaml(), "cars_owned")]
-    #[case(&test_yaml()["cars_owned"][0], "name")]
-    #[case(&test_yaml()["cars_owned"][0], "age")]
-    #[case(&test_yaml()["cars_owned"][0], "last_inspection")]
-    #[case(&test_yaml()["cars_owned"][0], "last_inspection.date")]
-    fn get_value_by_path_returns_reference_when_existing_path_is_passed(
-        #[case] input_yml: &Value,
+    fn replace_value_by_path_returns_previous_value(mut test_yaml: Value) {
+        let expected = test_yaml["cars_owned"][0]["last_inspection"].clone();
+        let mut car = test_yaml["cars_owned"][0].clone();
+
+        let old = replace_value_by_path(&mut car, "last_inspection", Value::Null).unwrap();
+        assert_eq!(expected, old);
+        assert!(car["last_inspection"].is_null());
+
+        let old = replace_value_by_path(&mut test_yaml, "age", Value::from(23)).unwrap();
+        assert_eq!(Value::from(22), old);
+        assert_eq!(Value::from(23), test_yaml["age"]);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("invalid")]
+    #[case("name.invalid")]
+    #[case("cars_owned.0")]
+    fn replace_value_by_path_returns_error_when_path_does_not_exist(
         #[case] path: &str,
+        mut test_yaml: Value,
     ) {
-        get_value_by_path(input_yml, path).unwrap();
+        let expected = test_yaml.clone();
+        assert!(replace_value_by_path(&mut test_yaml, path, Value::Null).is_err());
+        assert_eq!(expected, test_yaml);
+    }
+
+    #[rstest]
+    fn cursor_reads_values_relative_to_base(test_yaml: Value) {
+        let car = Cursor::at(&test_yaml["cars_owned"][0], "last_inspection").unwrap();
+        assert_eq!("2020-01-05", car.typed::<str>("date").unwrap());
+        assert_eq!(&test_yaml["cars_owned"][0]["last_inspection"], car.node());
+        assert_eq!(&Value::from("2020-01-05"), car.get("date").unwrap());
+    }
+
+    #[rstest]
+    fn cursor_returns_error_when_base_path_is_not_found(test_yaml: Value) {
+        assert!(Cursor::at(&test_yaml, "cars_owned.0").is_err());
+    }
+
+    #[rstest]
+    fn cursor_reports_full_path_when_relative_path_is_not_found(test_yaml: Value) {
+        let car = &test_yaml["cars_owned"][0];
+        let err = Cursor::at(car, "last_inspection")
+            .unwrap()
+            .get("place.city")
+            .unwrap_err();
+        assert_eq!(
+            "Path `last_inspection.place.city` was not found within the input object",
+            err.to_string()
+        );
+    }
+
+    #[rstest]
+    fn cursor_returns_error_when_invalid_type_requested(test_yaml: Value) {
+        let car = Cursor::at(&test_yaml["cars_owned"][0], "last_inspection").unwrap();
+        assert!(car.typed::<u64>("date").is_err());
+    }
+
+    #[rstest]
+    fn yaml_ref_returns_value_when_all_steps_are_valid(test_yaml: Value) {
+        assert_eq!(&test_yaml, YamlRef::new(&test_yaml).get().unwrap());
+        assert_eq!(
+            "2020-01-05",
+            YamlRef::new(&test_yaml)
+                .key("cars_owned")
+                .index(0)
+                .key("last_inspection")
+                .key("date")
+                .get()
+                .unwrap()
+        );
+    }
+
+    #[rstest]
+    fn yaml_ref_reports_first_invalid_step(test_yaml: Value) {
+        let err = YamlRef::new(&test_yaml)
+            .key("cars_owned")
+            .index(3)
+            .key("name")
+            .get()
+            .unwrap_err();
+        assert_eq!(
+            "Path `cars_owned[3]` was not found within the input object",
+            err.to_string()
+        );
+    }
+
+    #[rstest]
+    fn yaml_ref_returns_error_when_wrong_node_type_is_accessed(test_yaml: Value) {
+        assert!(YamlRef::new(&test_yaml)
+            .key("name")
+            .key("first")
+            .get()
+            .is_err());
+        assert!(YamlRef::new(&test_yaml).index(0).get().is_err());
+        assert!(YamlRef::new(&test_yaml)
+            .key("cars_owned")
+            .key("0")
+            .get()
+            .is_err());
+    }
+
+    #[rstest]
+    #[case("0xFF", 255)]
+    #[case("0XfF", 255)]
+    #[case("0o17", 15)]
+    #[case("0b1010_1010", 170)]
+    #[case("+0x10", 16)]
+    fn get_typed_value_by_path_parses_radix_strings_when_integer_requested(
+        #[case] text: &str,
+        #[case] expected: u64,
+    ) {
+        let value = Value::from(text);
+        assert_eq!(expected, <u64 as FromYaml>::try_from(&value).unwrap());
+        assert_eq!(
+            expected as i64,
+            <i64 as FromYaml>::try_from(&value).unwrap()
+        );
+    }
+
+    #[rstest]
+    fn get_typed_value_by_path_parses_negative_radix_strings_when_i64_requested() {
+        let value = Value::from("-0x8000_0000_0000_0000");
+        assert_eq!(i64::MIN, <i64 as FromYaml>::try_from(&value).unwrap());
+    }
+
+    #[rstest]
+    #[case("0xZZ", "Could not parse `0xZZ` as a base 16 integer")]
+    #[case("0x", "Could not parse `0x` as a base 16 integer")]
+    #[case("0b102", "Could not parse `0b102` as a base 2 integer")]
+    #[case("0x-1", "Could not parse `0x-1` as a base 16 integer")]
+    #[case("0x+FF", "Could not parse `0x+FF` as a base 16 integer")]
+    #[case("-0x-1", "Could not parse `-0x-1` as a base 16 integer")]
+    #[case("-0x1", "Integer `-0x1` is out of range for u64")]
+    #[case(
+        "0x1_0000_0000_0000_0000",
+        "Integer `0x1_0000_0000_0000_0000` is out of range for u64"
+    )]
+    #[case(
+        "255",
+        "Could not parse requested yaml value: expected u64, found string"
+    )]
+    fn get_typed_value_by_path_returns_error_when_malformed_radix_string_is_found(
+        #[case] text: &str,
+        #[case] expected: &str,
+    ) {
+        let yaml = Value::Mapping(
+            [(Value::from("flags"), Value::from(text))]
+                .into_iter()
+                .collect(),
+        );
+        let err = get_typed_value_by_path::<u64>(&yaml, "flags").unwrap_err();
+        assert_eq!(expected, err.to_string());
+    }
+
+    #[rstest]
+    #[case("", b"")]
+    #[case("Zg==", b"f")]
+    #[case("Zm8=", b"fo")]
+    #[case("Zm9v", b"foo")]
+    #[case("Zm9vYg", b"foob")]
+    #[case("Zm9vYmE=", b"fooba")]
+    #[case("Zm9v\nYmFy\n", b"foobar")]
+    #[case("/+8=", b"\xff\xef")]
+    fn get_typed_value_by_path_decodes_base64_when_base64_requested(
+        #[case] text: &str,
+        #[case] expected: &[u8],
+    ) {
+        let doc = serde_yaml::to_value(HashMap::from([("cert", text)])).unwrap();
+        assert_eq!(
+            expected.to_vec(),
+            get_typed_value_by_path::<Base64>(&doc, "cert").unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case(
+        Value::from("Zm9v!"),
+        "Could not decode base64 value: invalid character `!` at offset 4"
+    )]
+    #[case(
+        Value::from("Zm9vé"),
+        "Could not decode base64 value: invalid character `é` at offset 4"
+    )]
+    #[case(
+        Value::from("Zm9vY"),
+        "Could not decode base64 value: invalid length or padding"
+    )]
+    #[case(
+        Value::from("Zg="),
+        "Could not decode base64 value: invalid length or padding"
+    )]
+    #[case(
+        Value::from("Z==="),
+        "Could not decode base64 value: invalid length or padding"
+    )]
+    #[case(
+        Value::from("Zg==Zg=="),
+        "Could not decode base64 value: invalid character `=` at offset 2"
+    )]
+    #[case(
+        Value::from(5),
+        "Could not parse requested yaml value: expected Base64, found integer"
+    )]
+    fn get_typed_value_by_path_returns_error_when_malformed_base64_is_found(
+        #[case] value: Value,
+        #[case] expected: &str,
+    ) {
+        let err = <Base64 as FromYaml>::try_from(&value).unwrap_err();
+        assert_eq!(expected, err.to_string());
+    }
+
+    #[rstest]
+    #[case(&[], "")]
+    #[case(&["name"], "name")]
+    #[case(&["cars_owned", "0", "age"], "cars_owned.0.age")]
+    #[case(&["hosts", "example.com"], r#"hosts."example.com""#)]
+    #[case(&["a", "", "b"], r#"a."".b"#)]
+    #[case(&[r#"say "hi""#, r"C:\dir"], r#""say \"hi\""."C:\\dir""#)]
+    fn path_display_escapes_ambiguous_segments(#[case] segments: &[&str], #[case] expected: &str) {
+        assert_eq!(expected, PathDisplay(segments).to_string());
+    }
+
+    #[rstest]
+    #[case(Value::from(512), 512)]
+    #[case(Value::from("512"), 512)]
+    #[case(Value::from("0B"), 0)]
+    #[case(Value::from("10MB"), 10_000_000)]
+    #[case(Value::from("10 mb"), 10_000_000)]
+    #[case(Value::from("4KiB"), 4096)]
+    #[case(Value::from("1.5kib"), 1536)]
+    #[case(Value::from("2GB"), 2_000_000_000)]
+    #[case(Value::from("1GiB"), 1 << 30)]
+    #[case(Value::from("0.0015KB"), 1)]
+    #[case(Value::from(" 3 TiB "), 3 << 40)]
+    fn get_typed_value_by_path_parses_size_when_byte_size_requested(
+        #[case] value: Value,
+        #[case] expected: u64,
+    ) {
+        assert_eq!(expected, <ByteSize as FromYaml>::try_from(&value).unwrap());
+    }
+
+    #[rstest]
+    #[case(
+        Value::from("10XB"),
+        "Could not parse `10XB` as a byte size: unknown unit `XB`"
+    )]
+    #[case(
+        Value::from("MB"),
+        "Could not parse `MB` as a byte size: expected a number followed by an optional unit, e.g. `10MB`"
+    )]
+    #[case(
+        Value::from("1.2.3KB"),
+        "Could not parse `1.2.3KB` as a byte size: expected a number followed by an optional unit, e.g. `10MB`"
+    )]
+    #[case(
+        Value::from("-1KB"),
+        "Could not parse `-1KB` as a byte size: expected a number followed by an optional unit, e.g. `10MB`"
+    )]
+    #[case(
+        Value::from("20000000TB"),
+        "Could not parse `20000000TB` as a byte size: the size is out of range"
+    )]
+    #[case(
+        Value::from(-1),
+        "Could not parse requested yaml value: expected ByteSize, found integer"
+    )]
+    fn get_typed_value_by_path_returns_error_when_malformed_byte_size_is_found(
+        #[case] value: Value,
+        #[case] expected: &str,
+    ) {
+        let err = <ByteSize as FromYaml>::try_from(&value).unwrap_err();
+        assert_eq!(expected, err.to_string());
+    }
+
+    #[rstest]
+    #[case(Value::from("85%"), 0.85)]
+    #[case(Value::from(" 12.5 % "), 0.125)]
+    #[case(Value::from("-5%"), -0.05)]
+    #[case(Value::from("150%"), 1.5)]
+    #[case(Value::from(0.85), 0.85)]
+    #[case(Value::from(1), 1.0)]
+    fn get_typed_value_by_path_parses_percentage_when_percent_requested(
+        #[case] value: Value,
+        #[case] expected: f64,
+    ) {
+        let fraction = <Percent as FromYaml>::try_from(&value).unwrap();
+        assert!((expected - fraction).abs() < 1e-12, "{}", fraction);
+    }
+
+    #[rstest]
+    #[case(Value::from("85"))]
+    #[case(Value::from("%"))]
+    #[case(Value::from("a lot%"))]
+    #[case(Value::from("inf%"))]
+    #[case(Value::from("85%%"))]
+    fn get_typed_value_by_path_returns_error_when_malformed_percentage_is_found(
+        #[case] value: Value,
+    ) {
+        let err = <Percent as FromYaml>::try_from(&value).unwrap_err();
+        let expected = format!(
+            "Could not parse `{}` as a percentage: expected a number followed by `%`, e.g. `85%`",
+            value.as_str().unwrap()
+        );
+        assert_eq!(expected, err.to_string());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[rstest]
+    fn get_typed_value_by_path_parses_date_when_date_spec_requested(test_yaml: Value) {
+        let date = get_typed_value_by_path::<DateSpec>(
+            &test_yaml["cars_owned"][0],
+            "last_inspection.date",
+        )
+        .unwrap();
+        assert_eq!(
+            DateSpec::Date(chrono::NaiveDate::from_ymd_opt(2020, 1, 5).unwrap()),
+            date
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[rstest]
+    #[case("2020-01-05T10:30:00Z", "2020-01-05", 0)]
+    #[case("2020-01-05T23:30:00-02:00", "2020-01-05", -7200)]
+    #[case("2020-01-05t10:30:00.5+01:00", "2020-01-05", 3600)]
+    fn get_typed_value_by_path_parses_date_time_when_date_spec_requested(
+        #[case] text: &str,
+        #[case] date: &str,
+        #[case] offset: i32,
+    ) {
+        let spec = <DateSpec as FromYaml>::try_from(&Value::from(text)).unwrap();
+        let DateSpec::DateTime(date_time) = spec else {
+            panic!("expected a date-time, found {:?}", spec);
+        };
+        assert_eq!(offset, date_time.offset().local_minus_utc());
+        assert_eq!(date, spec.date().to_string());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[rstest]
+    #[case(Value::from("2020-13-05"), "Could not parse `2020-13-05` as a date")]
+    #[case(Value::from("05/01/2020"), "Could not parse `05/01/2020` as a date")]
+    #[case(
+        Value::from("2020-01-05 10:30"),
+        "Could not parse `2020-01-05 10:30` as a date"
+    )]
+    #[case(
+        Value::from(20200105),
+        "Could not parse requested yaml value: expected DateSpec, found integer"
+    )]
+    fn get_typed_value_by_path_returns_error_when_malformed_date_is_found(
+        #[case] value: Value,
+        #[case] expected: &str,
+    ) {
+        let err = <DateSpec as FromYaml>::try_from(&value).unwrap_err();
+        assert!(err.to_string().starts_with(expected), "{}", err);
+    }
+
+    #[cfg(feature = "regex")]
+    #[rstest]
+    fn get_typed_value_by_path_compiles_regex_when_regex_spec_requested(test_yaml: Value) {
+        let regex = get_typed_value_by_path::<RegexSpec>(&test_yaml, "name").unwrap();
+        assert!(regex.is_match("John Doe"));
+        assert!(!regex.is_match("Jane Doe"));
+    }
+
+    #[cfg(feature = "regex")]
+    #[rstest]
+    #[case(
+        Value::from("(unclosed"),
+        "Could not compile `(unclosed` as a regex: regex parse error:"
+    )]
+    #[case(
+        Value::from(5),
+        "Could not parse requested yaml value: expected RegexSpec, found integer"
+    )]
+    fn get_typed_value_by_path_returns_error_when_malformed_regex_is_found(
+        #[case] value: Value,
+        #[case] expected: &str,
+    ) {
+        let err = <RegexSpec as FromYaml>::try_from(&value).unwrap_err();
+        assert!(err.to_string().starts_with(expected), "{}", err);
     }
 
     #[rstest]
@@ -221,6 +3741,53 @@ mod tests {
         assert!(get_typed_value_by_path::<Sequence>(&test_yaml, "name").is_err());
     }
 
+    #[rstest]
+    #[case("name", "found string")]
+    #[case("age", "found integer")]
+    #[case("score", "found float")]
+    #[case("adult", "found bool")]
+    #[case("cars_owned", "found sequence")]
+    fn get_typed_value_by_path_error_contains_actual_kind(
+        #[case] path: &str,
+        #[case] expected: &str,
+        test_yaml: Value,
+    ) {
+        let err = get_typed_value_by_path::<Mapping>(&test_yaml, path).unwrap_err();
+        assert!(err.to_string().ends_with(expected));
+    }
+
+    #[rstest]
+    fn get_typed_value_by_path_error_names_scalar_types_plainly(test_yaml: Value) {
+        let message = |err: Pipeline| {
+            err.to_string()
+                .trim_start_matches("Could not parse requested yaml value: ")
+                .to_string()
+        };
+        assert_eq!(
+            "expected bool, found string",
+            message(get_typed_value_by_path::<bool>(&test_yaml, "name").unwrap_err())
+        );
+        assert_eq!(
+            "expected i64, found float",
+            message(get_typed_value_by_path::<i64>(&test_yaml, "score").unwrap_err())
+        );
+        assert_eq!(
+            "expected u64, found bool",
+            message(get_typed_value_by_path::<u64>(&test_yaml, "adult").unwrap_err())
+        );
+        assert_eq!(
+            "expected f64, found sequence",
+            message(get_typed_value_by_path::<f64>(&test_yaml, "cars_owned").unwrap_err())
+        );
+    }
+
+    #[rstest]
+    fn get_typed_value_by_path_error_contains_null_kind() {
+        let yaml: Value = serde_yaml::from_str("key: ~").unwrap();
+        let err = get_typed_value_by_path::<str>(&yaml, "key").unwrap_err();
+        assert!(err.to_string().ends_with("expected str, found null"));
+    }
+
     #[rstest]
     fn get_typed_value_by_path_valid_value_returned_when_bool_requested(test_yaml: Value) {
         assert_eq!(
@@ -253,6 +3820,18 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case("age", 22.0)]
+    #[case("rank_delta", -10.0)]
+    fn get_typed_value_by_path_integer_converted_when_f64_requested(
+        #[case] path: &str,
+        #[case] expected: f64,
+        test_yaml: Value,
+    ) {
+        let value = get_typed_value_by_path::<f64>(&test_yaml, path).unwrap();
+        assert!((value - expected).abs() < f64::EPSILON);
+    }
+
     #[rstest]
     fn get_typed_value_by_path_valid_value_returned_when_str_requested(test_yaml: Value) {
         assert_eq!(
@@ -261,6 +3840,242 @@ mod tests {
         );
     }
 
+    #[rstest]
+    fn try_typed_converts_resolved_node(test_yaml: Value) {
+        let car = &test_yaml["cars_owned"][0];
+        assert_eq!(5, try_typed::<u64>(&car["age"]).unwrap());
+        assert_eq!("Ford Mustang", try_typed::<str>(&car["name"]).unwrap());
+        assert!(try_typed::<bool>(&car["name"]).is_err());
+    }
+
+    #[rstest]
+    fn get_typed_value_by_path_owned_string_returned_when_string_requested(test_yaml: Value) {
+        assert_eq!(
+            "John Doe".to_string(),
+            get_typed_value_by_path::<String>(&test_yaml, "name").unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case("cars_owned.*.age", vec![Value::from(5)])]
+    #[case("cars_owned.*.*.date", vec![Value::from("2020-01-05")])]
+    #[case("*.age", vec![])]
+    #[case("cars_owned.*.missing", vec![])]
+    #[case("name.*", vec![])]
+    #[case("cars_owned.0", vec![])]
+    fn get_values_by_glob_returns_matches_in_document_order(
+        #[case] pattern: &str,
+        #[case] expected: Vec<Value>,
+        test_yaml: Value,
+    ) {
+        let values = get_values_by_glob(&test_yaml, pattern).unwrap();
+        assert_eq!(expected.iter().collect::<Vec<_>>(), values);
+    }
+
+    #[rstest]
+    fn get_values_by_glob_matches_every_value_of_mapping(test_yaml: Value) {
+        let values = get_values_by_glob(&test_yaml, "*").unwrap();
+        let expected: Vec<_> = test_yaml
+            .as_mapping()
+            .unwrap()
+            .iter()
+            .map(|(_, v)| v)
+            .collect();
+        assert_eq!(expected, values);
+    }
+
+    #[rstest]
+    fn get_entries_by_glob_returns_concrete_paths(test_yaml: Value) {
+        let entries = get_entries_by_glob(&test_yaml, "cars_owned.*.*").unwrap();
+        let paths: Vec<_> = entries.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(
+            vec![
+                "cars_owned.0.name",
+                "cars_owned.0.age",
+                "cars_owned.0.last_inspection"
+            ],
+            paths
+        );
+        assert_eq!(&test_yaml["cars_owned"][0]["age"], entries[1].1);
+    }
+
+    #[rstest]
+    fn get_entries_by_glob_quotes_ambiguous_keys() {
+        let doc: Value = serde_yaml::from_str("hosts: { example.com: 1, 8080: 2 }").unwrap();
+        let entries = get_entries_by_glob(&doc, "hosts.*").unwrap();
+        let paths: Vec<_> = entries.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(vec![r#"hosts."example.com""#, "hosts.8080"], paths);
+    }
+
+    #[rstest]
+    fn get_typed_values_by_glob_converts_every_match() {
+        let doc: Value =
+            serde_yaml::from_str("cars: [{ age: 5 }, { age: 0x10 }, { age: 7 }]").unwrap();
+        assert_eq!(
+            vec![5, 16, 7],
+            get_typed_values_by_glob::<u64>(&doc, "cars.*.age").unwrap()
+        );
+    }
+
+    #[rstest]
+    fn get_typed_values_by_glob_reports_path_of_invalid_match() {
+        let doc: Value = serde_yaml::from_str("cars: [{ age: 5 }, { age: old }]").unwrap();
+        let err = get_typed_values_by_glob::<u64>(&doc, "cars.*.age").unwrap_err();
+
+        assert_eq!(
+            "Invalid value at path `cars.1.age`: Could not parse requested yaml value: expected u64, found string",
+            err.to_string()
+        );
+        assert_eq!(Some("Input object: String(\"old\")"), err.debug_detail());
+    }
+
+    #[rstest]
+    fn get_with_env_override_falls_back_to_config_when_variable_is_unset(test_yaml: Value) {
+        let age = get_with_env_override::<u64, _>(&test_yaml, "age", "YUTIL_TEST_UNSET_AGE");
+        assert_eq!(22, age.unwrap());
+    }
+
+    // Look variables up in a map in place of the process environment
+    fn lookup_in(
+        vars: &[(&str, &str)],
+    ) -> impl Fn(&str) -> std::result::Result<String, std::env::VarError> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| {
+            vars.get(name)
+                .cloned()
+                .ok_or(std::env::VarError::NotPresent)
+        }
+    }
+
+    #[rstest]
+    #[case("Jane Roe", "Jane Roe")]
+    #[case("123", "123")]
+    fn get_with_env_override_prefers_variable_when_string_requested(
+        #[case] text: &str,
+        #[case] expected: &str,
+        test_yaml: Value,
+    ) {
+        let lookup = lookup_in(&[("NAME", text)]);
+        let name = get_with_override_from::<String, _, _>(&test_yaml, "name", "NAME", lookup);
+        assert_eq!(expected, name.unwrap());
+    }
+
+    #[rstest]
+    fn get_with_env_override_coerces_variable_to_requested_type(test_yaml: Value) {
+        let lookup = lookup_in(&[("AGE", "0x10"), ("ADULT", "false"), ("SCORE", "1.5")]);
+
+        let age = get_with_override_from::<u64, _, _>(&test_yaml, "age", "AGE", &lookup);
+        let adult = get_with_override_from::<bool, _, _>(&test_yaml, "adult", "ADULT", &lookup);
+        let score = get_with_override_from::<f64, _, _>(&test_yaml, "score", "SCORE", &lookup);
+        assert_eq!(16, age.unwrap());
+        assert!(!adult.unwrap());
+        assert!((score.unwrap() - 1.5).abs() < f64::EPSILON);
+    }
+
+    #[rstest]
+    fn get_with_env_override_non_unicode_variable_reported(test_yaml: Value) {
+        let lookup = |_: &str| Err(std::env::VarError::NotUnicode("\u{fffd}".into()));
+        let err = get_with_override_from::<u64, _, _>(&test_yaml, "age", "AGE", lookup);
+        assert_eq!(
+            "Environment variable `AGE` is not valid unicode",
+            err.unwrap_err().to_string()
+        );
+    }
+
+    #[rstest]
+    fn get_with_env_override_invalid_variable_reported(test_yaml: Value) {
+        let lookup = lookup_in(&[("INVALID_AGE", "old")]);
+        let err = get_with_override_from::<u64, _, _>(&test_yaml, "age", "INVALID_AGE", lookup)
+            .unwrap_err();
+
+        assert_eq!(
+            "Invalid value `old` of environment variable `INVALID_AGE`: Could not parse requested yaml value: expected u64, found string",
+            err.to_string()
+        );
+        assert_eq!(Some("Input object: String(\"old\")"), err.debug_detail());
+    }
+
+    #[rstest]
+    fn get_path_resolved_resolves_relative_and_absolute_paths() {
+        let base = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let expected = base.join("src").join("yutil.rs").canonicalize().unwrap();
+        let absolute = expected.to_str().unwrap();
+        let yaml: Value = serde_yaml::from_str(&format!(
+            "relative: src/../src/yutil.rs\nabsolute: {:?}",
+            absolute
+        ))
+        .unwrap();
+
+        assert_eq!(
+            expected,
+            get_path_resolved(&yaml, "relative", base).unwrap()
+        );
+        assert_eq!(
+            expected,
+            get_path_resolved(&yaml, "absolute", Path::new("/nonexistent")).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case("~", "/home/user")]
+    #[case("~/bin/tool", "/home/user/bin/tool")]
+    #[case("~//bin", "/home/user/bin")]
+    #[case("~user/bin", "~user/bin")]
+    #[case("bin/~/tool", "bin/~/tool")]
+    #[case("/opt/tool", "/opt/tool")]
+    fn expand_home_expands_leading_tilde(#[case] text: &str, #[case] expected: &str) {
+        let home = Some(PathBuf::from("/home/user"));
+        assert_eq!(PathBuf::from(expected), expand_home(text, home).unwrap());
+    }
+
+    #[rstest]
+    fn expand_home_returns_error_when_home_is_unknown() {
+        assert_eq!(
+            "Could not expand `~/bin`: the home directory is not known, set `HOME`",
+            expand_home("~/bin", None).unwrap_err().to_string()
+        );
+        assert_eq!(PathBuf::from("bin"), expand_home("bin", None).unwrap());
+    }
+
+    #[rstest]
+    fn get_path_resolved_expands_home_directory() {
+        let Some(home) = home_dir() else {
+            return;
+        };
+        let yaml: Value = serde_yaml::from_str("{ home: '~', config: '~/bin/tool' }").unwrap();
+        assert_eq!(
+            home.canonicalize().unwrap(),
+            get_path_resolved(&yaml, "home", Path::new("/nonexistent")).unwrap()
+        );
+        assert_eq!(
+            home.join("bin").join("tool"),
+            get_typed_value_by_path::<PathBuf>(&yaml, "config").unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "file: src/missing.rs",
+        "Could not resolve path `src/missing.rs` at `file`"
+    )]
+    #[case("file: ''", "Path at `file` is empty")]
+    #[case(
+        "file: 1",
+        "Could not parse requested yaml value: expected str, found integer"
+    )]
+    fn get_path_resolved_returns_error_when_path_is_invalid(
+        #[case] yaml: &str,
+        #[case] expected: &str,
+    ) {
+        let yaml: Value = serde_yaml::from_str(yaml).unwrap();
+        let err =
+            get_path_resolved(&yaml, "file", Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap_err();
+        assert!(err.message().starts_with(expected), "{}", err);
+    }
+
     #[rstest]
     fn get_typed_value_by_path_valid_value_returned_when_mapping_requested(test_yaml: Value) {
         assert_eq!(